@@ -2,7 +2,14 @@
 
 use std::sync::Arc;
 
+use base_reth_flashblocks::{DEFAULT_BROADCAST_BUFFER_SIZE, DEFAULT_FLASHBLOCK_HISTORY_SIZE};
+use base_reth_rpc::{
+    DEFAULT_MAX_SUBSCRIPTION_MESSAGE_SIZE, DEFAULT_MAX_SUBSCRIPTIONS,
+    DEFAULT_MAX_SUBSCRIPTIONS_PER_CONNECTION, DEFAULT_MAX_TIMEOUT_SEND_RAW_TX_SYNC_MS, LagPolicy,
+    SubscriptionLimits,
+};
 use base_reth_runner::{BaseNodeConfig, FlashblocksCell, FlashblocksConfig, TracingConfig};
+use clap::builder::RangedU64ValueParser;
 use once_cell::sync::OnceCell;
 use reth_optimism_node::args::RollupArgs;
 
@@ -26,6 +33,73 @@ pub struct Args {
     )]
     pub max_pending_blocks_depth: u64,
 
+    /// Approximate memory limit for pending flashblocks state, in MiB. Per-transaction state
+    /// diffs and then older pending blocks are pruned when exceeded.
+    #[arg(long = "max-pending-blocks-memory-mb", value_name = "MAX_PENDING_BLOCKS_MEMORY_MB")]
+    pub max_pending_blocks_memory_mb: Option<usize>,
+
+    /// Max timeout accepted by `eth_sendRawTransactionSync`, in milliseconds.
+    #[arg(
+        long = "max-send-raw-tx-sync-timeout-ms",
+        value_name = "MAX_SEND_RAW_TX_SYNC_TIMEOUT_MS",
+        default_value_t = DEFAULT_MAX_TIMEOUT_SEND_RAW_TX_SYNC_MS
+    )]
+    pub max_send_raw_tx_sync_timeout_ms: u64,
+
+    /// Number of pending state updates buffered for each flashblocks subscriber.
+    #[arg(
+        long = "flashblocks-broadcast-buffer-size",
+        value_name = "FLASHBLOCKS_BROADCAST_BUFFER_SIZE",
+        default_value_t = DEFAULT_BROADCAST_BUFFER_SIZE,
+        value_parser = RangedU64ValueParser::<usize>::new().range(1..)
+    )]
+    pub flashblocks_broadcast_buffer_size: usize,
+
+    /// Number of recent flashblocks kept for `newFlashblocks` subscribers resuming from a
+    /// cursor.
+    #[arg(
+        long = "flashblocks-replay-history-size",
+        value_name = "FLASHBLOCKS_REPLAY_HISTORY_SIZE",
+        default_value_t = DEFAULT_FLASHBLOCK_HISTORY_SIZE,
+        value_parser = RangedU64ValueParser::<usize>::new().range(1..)
+    )]
+    pub flashblocks_replay_history_size: usize,
+
+    /// What flashblocks subscriptions do when the subscriber falls behind the buffered updates:
+    /// `gap` notifies the missed range, `resync` sends a full snapshot and `disconnect` closes
+    /// the subscription.
+    #[arg(
+        long = "flashblocks-lag-policy",
+        value_name = "FLASHBLOCKS_LAG_POLICY",
+        default_value = "gap"
+    )]
+    pub flashblocks_lag_policy: LagPolicy,
+
+    /// Max flashblocks subscriptions of a single connection.
+    #[arg(
+        long = "flashblocks-max-subscriptions-per-connection",
+        value_name = "FLASHBLOCKS_MAX_SUBSCRIPTIONS_PER_CONNECTION",
+        default_value_t = DEFAULT_MAX_SUBSCRIPTIONS_PER_CONNECTION
+    )]
+    pub flashblocks_max_subscriptions_per_connection: usize,
+
+    /// Max flashblocks subscriptions across all connections.
+    #[arg(
+        long = "flashblocks-max-subscriptions",
+        value_name = "FLASHBLOCKS_MAX_SUBSCRIPTIONS",
+        default_value_t = DEFAULT_MAX_SUBSCRIPTIONS
+    )]
+    pub flashblocks_max_subscriptions: usize,
+
+    /// Max size of a flashblocks subscription notification, in bytes. Subscriptions are closed
+    /// with an error rather than sent larger notifications.
+    #[arg(
+        long = "flashblocks-max-subscription-message-size",
+        value_name = "FLASHBLOCKS_MAX_SUBSCRIPTION_MESSAGE_SIZE",
+        default_value_t = DEFAULT_MAX_SUBSCRIPTION_MESSAGE_SIZE
+    )]
+    pub flashblocks_max_subscription_message_size: usize,
+
     /// Enable transaction tracing ExEx for mempool-to-block timing analysis
     #[arg(long = "enable-transaction-tracing", value_name = "ENABLE_TRANSACTION_TRACING")]
     pub enable_transaction_tracing: bool,
@@ -56,6 +130,18 @@ impl From<Args> for BaseNodeConfig {
         let flashblocks = args.websocket_url.map(|websocket_url| FlashblocksConfig {
             websocket_url,
             max_pending_blocks_depth: args.max_pending_blocks_depth,
+            max_pending_blocks_memory: args
+                .max_pending_blocks_memory_mb
+                .map(|mb| mb.saturating_mul(1024 * 1024)),
+            max_send_raw_transaction_sync_timeout_ms: args.max_send_raw_tx_sync_timeout_ms,
+            broadcast_buffer_size: args.flashblocks_broadcast_buffer_size,
+            replay_history_size: args.flashblocks_replay_history_size,
+            subscription_lag_policy: args.flashblocks_lag_policy,
+            subscription_limits: SubscriptionLimits {
+                max_per_connection: args.flashblocks_max_subscriptions_per_connection,
+                max_total: args.flashblocks_max_subscriptions,
+                max_message_size: args.flashblocks_max_subscription_message_size,
+            },
         });
 
         Self {
@@ -11,7 +11,10 @@ use alloy_rpc_types_engine::PayloadId;
 use base_flashtypes::{
     ExecutionPayloadBaseV1, ExecutionPayloadFlashblockDeltaV1, Flashblock, Metadata,
 };
-use base_reth_flashblocks::{FlashblocksAPI, FlashblocksReceiver, FlashblocksState};
+use base_reth_flashblocks::{
+    DEFAULT_BROADCAST_BUFFER_SIZE, DEFAULT_FLASHBLOCK_HISTORY_SIZE, FlashblocksAPI,
+    FlashblocksReceiver, FlashblocksState,
+};
 use base_reth_test_utils::{LocalNodeProvider, TestAccounts, TestHarness};
 use criterion::{BatchSize, Criterion, Throughput, criterion_group, criterion_main};
 use reth::{
@@ -53,12 +56,25 @@ impl BenchSetup {
             .try_into_recovered()
             .expect("recovered block should build");
 
+        // A single sender makes every transaction depend on the previous one, while independent
+        // senders and recipients expose the parallelism available to speculative execution
+        // (compare runs with and without `--features parallel-execution`).
         let flashblocks = tx_counts
             .iter()
-            .map(|count| {
+            .flat_map(|count| {
                 let txs = sample_transactions(&provider, harness.accounts(), *count);
-                let blocks = build_flashblocks(&canonical_block, &txs);
-                (format!("pending_state_{}_txs", count), blocks)
+                let independent_txs =
+                    independent_transactions(&provider, harness.accounts(), *count);
+                [
+                    (
+                        format!("pending_state_{}_txs", count),
+                        build_flashblocks(&canonical_block, &txs),
+                    ),
+                    (
+                        format!("pending_state_{}_independent_txs", count),
+                        build_flashblocks(&canonical_block, &independent_txs),
+                    ),
+                ]
             })
             .collect();
 
@@ -116,7 +132,13 @@ fn pending_state_benches(c: &mut Criterion) {
 }
 
 async fn build_pending_state(input: BenchInput) {
-    let state = FlashblocksState::new(input.provider, 5);
+    let state = FlashblocksState::new(
+        input.provider,
+        5,
+        None,
+        DEFAULT_BROADCAST_BUFFER_SIZE,
+        DEFAULT_FLASHBLOCK_HISTORY_SIZE,
+    );
     state.start();
     state.on_canonical_block_received(input.canonical_block);
 
@@ -268,26 +290,48 @@ fn sample_transactions(
     let signer = B256::from_hex(accounts.alice.private_key).expect("valid private key hex");
     let chain_id = provider.chain_spec().chain_id();
 
-    (0..count as u64)
-        .map(|nonce| {
-            let txn = TransactionBuilder::default()
-                .signer(signer)
-                .chain_id(chain_id)
-                .to(accounts.bob.address)
-                .nonce(nonce)
-                .value(1_000_000_000u128)
-                .gas_limit(TX_GAS_USED)
-                .max_fee_per_gas(1_000_000_000)
-                .max_priority_fee_per_gas(1_000_000_000)
-                .into_eip1559()
-                .as_eip1559()
-                .expect("should convert to eip1559")
-                .clone();
-
-            OpTransactionSigned::Eip1559(txn)
+    (0..count as u64).map(|nonce| transfer(signer, chain_id, accounts.bob.address, nonce)).collect()
+}
+
+fn independent_transactions(
+    provider: &LocalNodeProvider,
+    accounts: &TestAccounts,
+    count: usize,
+) -> Vec<OpTransactionSigned> {
+    let signers: Vec<B256> = accounts
+        .all()
+        .into_iter()
+        .map(|account| B256::from_hex(account.private_key).expect("valid private key hex"))
+        .collect();
+    let chain_id = provider.chain_spec().chain_id();
+
+    (0..count)
+        .map(|idx| {
+            let signer = signers[idx % signers.len()];
+            let nonce = (idx / signers.len()) as u64;
+            let recipient = Address::left_padding_from(&(0x10000 + idx as u64).to_be_bytes());
+            transfer(signer, chain_id, recipient, nonce)
         })
         .collect()
 }
 
+fn transfer(signer: B256, chain_id: u64, to: Address, nonce: u64) -> OpTransactionSigned {
+    let txn = TransactionBuilder::default()
+        .signer(signer)
+        .chain_id(chain_id)
+        .to(to)
+        .nonce(nonce)
+        .value(1_000_000_000u128)
+        .gas_limit(TX_GAS_USED)
+        .max_fee_per_gas(1_000_000_000)
+        .max_priority_fee_per_gas(1_000_000_000)
+        .into_eip1559()
+        .as_eip1559()
+        .expect("should convert to eip1559")
+        .clone();
+
+    OpTransactionSigned::Eip1559(txn)
+}
+
 criterion_group!(benches, pending_state_benches);
 criterion_main!(benches);
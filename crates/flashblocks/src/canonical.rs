@@ -0,0 +1,12 @@
+//! Notifications of canonical blocks reconciled against the pending state.
+
+use alloy_primitives::{BlockNumber, TxHash};
+
+/// A canonical block received by the state processor.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CanonicalBlock {
+    /// The number of the block.
+    pub number: BlockNumber,
+    /// Hashes of the transactions of the block, in order.
+    pub transactions: Vec<TxHash>,
+}
@@ -0,0 +1,267 @@
+//! Ring buffer of the most recent flashblocks broadcast to subscribers.
+
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+};
+
+use alloy_consensus::{Header, Sealed};
+use alloy_provider::network::TransactionResponse;
+use alloy_rpc_types::BlockTransactions;
+use alloy_rpc_types_eth::Header as RPCHeader;
+use op_alloy_network::Optimism;
+use op_alloy_rpc_types::{OpTransactionReceipt, Transaction};
+use reth_rpc_eth_api::RpcBlock;
+
+use crate::{FlashblockId, PendingBlocks};
+
+/// Default number of flashblocks kept for replay, about ten seconds of flashblocks.
+pub const DEFAULT_FLASHBLOCK_HISTORY_SIZE: usize = 50;
+
+/// Transactions added to a pending block by a flashblock, with their receipts.
+#[derive(Debug)]
+struct FlashblockTransactions {
+    /// Index of the flashblock that added the transactions.
+    index: u64,
+    transactions: Vec<Transaction>,
+    receipts: Vec<OpTransactionReceipt>,
+}
+
+/// A flashblock kept for replay, with what is needed to rebuild the notifications sent for it
+/// rather than the whole pending state.
+#[derive(Debug, Clone)]
+pub struct FlashblockReplay {
+    header: Sealed<Header>,
+    index: u64,
+    /// Transactions of the block up to this flashblock, shared with the replays of the other
+    /// flashblocks of the block.
+    transactions: Vec<Arc<FlashblockTransactions>>,
+}
+
+impl FlashblockReplay {
+    /// Creates the replay of the latest flashblock of a pending state. The transactions of the
+    /// block are shared with the replay of its previous flashblock, if given.
+    fn new(pending_blocks: &PendingBlocks, prev: Option<&Self>) -> Self {
+        let header = pending_blocks.latest_header();
+        let (block_number, index) = pending_blocks.latest_flashblock_id();
+
+        let mut transactions = prev
+            .filter(|prev| prev.header.number == block_number && prev.index < index)
+            .map(|prev| prev.transactions.clone())
+            .unwrap_or_default();
+        let delivered = transactions.iter().map(|added| added.transactions.len()).sum();
+
+        let added: Vec<Transaction> = pending_blocks
+            .get_transactions_for_block(block_number)
+            .into_iter()
+            .skip(delivered)
+            .collect();
+        let receipts =
+            added.iter().filter_map(|tx| pending_blocks.get_receipt(tx.tx_hash())).collect();
+        transactions.push(Arc::new(FlashblockTransactions {
+            index,
+            transactions: added,
+            receipts,
+        }));
+
+        Self { header, index, transactions }
+    }
+
+    /// Returns the id of the flashblock.
+    pub fn flashblock_id(&self) -> FlashblockId {
+        (self.header.number, self.index)
+    }
+
+    /// Returns the header of the pending block as of the flashblock.
+    pub const fn header(&self) -> &Sealed<Header> {
+        &self.header
+    }
+
+    /// Returns the pending block as of the flashblock, optionally with full transaction details.
+    pub fn block(&self, full: bool) -> RpcBlock<Optimism> {
+        let block_transactions =
+            self.transactions.iter().flat_map(|added| added.transactions.iter().cloned());
+        let transactions = if full {
+            BlockTransactions::Full(block_transactions.collect())
+        } else {
+            BlockTransactions::Hashes(block_transactions.map(|tx| tx.tx_hash()).collect())
+        };
+
+        RpcBlock::<Optimism> {
+            header: RPCHeader::from_consensus(self.header.clone(), None, None),
+            transactions,
+            uncles: Vec::new(),
+            withdrawals: None,
+        }
+    }
+
+    /// Returns the transactions added to the block by the flashblocks after the given index, with
+    /// their receipts. Returns None if the flashblock of the index is older than the ones kept.
+    pub fn transactions_after(
+        &self,
+        index: u64,
+    ) -> Option<(Vec<Transaction>, Vec<OpTransactionReceipt>)> {
+        // The first transactions kept may have been added by several flashblocks
+        if index < self.transactions.first()?.index {
+            return None;
+        }
+
+        let added = self.transactions.iter().filter(|added| added.index > index);
+        Some((
+            added.clone().flat_map(|added| added.transactions.iter().cloned()).collect(),
+            added.flat_map(|added| added.receipts.iter().cloned()).collect(),
+        ))
+    }
+
+    /// Returns all the transactions of the block up to the flashblock, with their receipts.
+    pub fn transactions(&self) -> (Vec<Transaction>, Vec<OpTransactionReceipt>) {
+        (
+            self.transactions.iter().flat_map(|added| added.transactions.iter().cloned()).collect(),
+            self.transactions.iter().flat_map(|added| added.receipts.iter().cloned()).collect(),
+        )
+    }
+}
+
+/// Keeps the most recent flashblocks, so that subscribers reconnecting with a cursor can be sent
+/// the flashblocks they missed.
+#[derive(Debug)]
+pub struct FlashblockHistory {
+    entries: Mutex<VecDeque<Arc<FlashblockReplay>>>,
+    capacity: usize,
+}
+
+impl Default for FlashblockHistory {
+    fn default() -> Self {
+        Self::new(DEFAULT_FLASHBLOCK_HISTORY_SIZE)
+    }
+}
+
+impl FlashblockHistory {
+    /// Creates a history keeping at most `capacity` flashblocks.
+    pub fn new(capacity: usize) -> Self {
+        Self { entries: Mutex::new(VecDeque::with_capacity(capacity)), capacity }
+    }
+
+    /// Records the latest flashblock of a new pending state, evicting the oldest one if the
+    /// history is full.
+    pub fn push(&self, pending_blocks: &PendingBlocks) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        let mut entries = self.entries.lock().expect("flashblock history lock poisoned");
+        let replay = FlashblockReplay::new(pending_blocks, entries.back().map(Arc::as_ref));
+        if entries.len() == self.capacity {
+            entries.pop_front();
+        }
+        entries.push_back(Arc::new(replay));
+    }
+
+    /// Returns the oldest flashblock still in the history.
+    pub fn oldest(&self) -> Option<FlashblockId> {
+        let entries = self.entries.lock().expect("flashblock history lock poisoned");
+        entries.front().map(|replay| replay.flashblock_id())
+    }
+
+    /// Returns the flashblocks after the cursor, in order. Returns None if the flashblock of the
+    /// cursor was evicted, as flashblocks after it may have been lost too.
+    pub fn after(&self, cursor: FlashblockId) -> Option<Vec<Arc<FlashblockReplay>>> {
+        let entries = self.entries.lock().expect("flashblock history lock poisoned");
+        let oldest = entries.front()?.flashblock_id();
+        if cursor < oldest {
+            return None;
+        }
+
+        Some(entries.iter().filter(|replay| replay.flashblock_id() > cursor).cloned().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::{B256, BlockNumber};
+    use alloy_rpc_types_engine::PayloadId;
+    use base_flashtypes::{ExecutionPayloadFlashblockDeltaV1, Flashblock, Metadata};
+
+    use super::*;
+    use crate::PendingBlocksBuilder;
+
+    fn pending_blocks(block_number: BlockNumber, index: u64) -> PendingBlocks {
+        let mut builder = PendingBlocksBuilder::new();
+        builder.with_header(Sealed::new_unchecked(
+            Header { number: block_number, ..Default::default() },
+            B256::ZERO,
+        ));
+        builder.with_flashblocks([Flashblock {
+            payload_id: PayloadId::new([0; 8]),
+            index,
+            base: None,
+            diff: ExecutionPayloadFlashblockDeltaV1::default(),
+            metadata: Metadata { block_number },
+        }]);
+        builder.build().expect("pending blocks should build")
+    }
+
+    fn ids(entries: &[Arc<FlashblockReplay>]) -> Vec<FlashblockId> {
+        entries.iter().map(|replay| replay.flashblock_id()).collect()
+    }
+
+    #[test]
+    fn test_after_returns_flashblocks_after_cursor() {
+        let history = FlashblockHistory::new(4);
+        for id in [(1, 0), (1, 1), (2, 0)] {
+            history.push(&pending_blocks(id.0, id.1));
+        }
+
+        assert_eq!(ids(&history.after((1, 0)).unwrap()), vec![(1, 1), (2, 0)]);
+        assert_eq!(ids(&history.after((1, 5)).unwrap()), vec![(2, 0)]);
+        assert!(history.after((2, 0)).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_after_rejects_evicted_cursor() {
+        let history = FlashblockHistory::new(2);
+        assert!(history.after((1, 0)).is_none());
+
+        for id in [(1, 0), (1, 1), (1, 2)] {
+            history.push(&pending_blocks(id.0, id.1));
+        }
+
+        assert_eq!(history.oldest(), Some((1, 1)));
+        assert!(history.after((1, 0)).is_none());
+        assert_eq!(ids(&history.after((1, 1)).unwrap()), vec![(1, 2)]);
+    }
+
+    #[test]
+    fn test_replays_share_transactions_of_their_block() {
+        let history = FlashblockHistory::new(4);
+        for id in [(1, 0), (1, 1), (2, 0)] {
+            history.push(&pending_blocks(id.0, id.1));
+        }
+
+        let entries = history.after((0, 0)).unwrap();
+        assert_eq!(entries[1].transactions.len(), 2);
+        assert!(Arc::ptr_eq(&entries[0].transactions[0], &entries[1].transactions[0]));
+        assert_eq!(entries[2].transactions.len(), 1);
+
+        assert!(entries[1].transactions_after(0).is_some());
+    }
+
+    #[test]
+    fn test_transactions_after_rejects_flashblocks_before_the_first_kept() {
+        let history = FlashblockHistory::new(4);
+        history.push(&pending_blocks(1, 2));
+        history.push(&pending_blocks(1, 3));
+
+        // The transactions of the first replay may have been added by flashblocks 0 to 2
+        let latest = history.after((1, 2)).unwrap().pop().unwrap();
+        assert!(latest.transactions_after(1).is_none());
+        assert!(latest.transactions_after(2).is_some());
+    }
+
+    #[test]
+    fn test_empty_history_keeps_nothing() {
+        let history = FlashblockHistory::new(0);
+        history.push(&pending_blocks(1, 0));
+        assert!(history.oldest().is_none());
+    }
+}
@@ -0,0 +1,196 @@
+//! Registry of callers waiting for transactions to be included.
+
+use std::sync::{Arc, Mutex};
+
+use alloy_primitives::{BlockNumber, TxHash, map::foldhash::HashMap};
+use op_alloy_rpc_types::OpTransactionReceipt;
+use tokio::sync::mpsc;
+
+/// Default maximum number of callers that can wait for inclusion at the same time.
+pub const DEFAULT_MAX_INCLUSION_WAITERS: usize = 10_000;
+
+/// How a transaction was included.
+#[derive(Debug, Clone)]
+pub enum Inclusion {
+    /// Included in a flashblock, with its receipt from the pending state.
+    Preconfirmed(Box<OpTransactionReceipt>),
+    /// Included in the canonical block with the given number.
+    Canonical(BlockNumber),
+}
+
+#[derive(Debug, Default)]
+struct Waiters {
+    by_hash: HashMap<TxHash, Vec<(u64, mpsc::UnboundedSender<Inclusion>)>>,
+    next_id: u64,
+    len: usize,
+}
+
+/// Maps transaction hashes to the callers waiting for their inclusion.
+///
+/// The state processor notifies the registry once for every transaction newly included in a
+/// flashblock or a canonical block, so waiting callers don't have to inspect every update
+/// themselves. The number of waiters is bounded, and a waiter is removed as soon as it is dropped.
+/// Notifications are never dropped, as a transaction is only included a few times.
+#[derive(Debug)]
+pub struct InclusionWaiters {
+    waiters: Mutex<Waiters>,
+    max_waiters: usize,
+}
+
+impl Default for InclusionWaiters {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_INCLUSION_WAITERS)
+    }
+}
+
+impl InclusionWaiters {
+    /// Creates a registry accepting at most `max_waiters` waiters at the same time.
+    pub fn new(max_waiters: usize) -> Self {
+        Self { waiters: Mutex::new(Waiters::default()), max_waiters }
+    }
+
+    /// Registers a waiter for the inclusion of a transaction. Returns None if the registry is
+    /// full.
+    pub fn register(self: &Arc<Self>, tx_hash: TxHash) -> Option<InclusionWaiter> {
+        let mut waiters = self.waiters.lock().expect("inclusion waiters lock poisoned");
+        if waiters.len >= self.max_waiters {
+            return None;
+        }
+
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let id = waiters.next_id;
+        waiters.next_id += 1;
+        waiters.len += 1;
+        waiters.by_hash.entry(tx_hash).or_default().push((id, sender));
+
+        Some(InclusionWaiter { registry: self.clone(), tx_hash, id, receiver })
+    }
+
+    /// Returns the number of registered waiters.
+    pub fn len(&self) -> usize {
+        self.waiters.lock().expect("inclusion waiters lock poisoned").len
+    }
+
+    /// Returns true if no waiters are registered.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Notifies the waiters of newly included transactions. `inclusion` is only called for
+    /// transactions that have waiters.
+    pub fn notify<I, F>(&self, tx_hashes: I, inclusion: F)
+    where
+        I: IntoIterator<Item = TxHash>,
+        F: Fn(TxHash) -> Option<Inclusion>,
+    {
+        let waiters = self.waiters.lock().expect("inclusion waiters lock poisoned");
+        if waiters.len == 0 {
+            return;
+        }
+
+        for tx_hash in tx_hashes {
+            let Some(senders) = waiters.by_hash.get(&tx_hash) else {
+                continue;
+            };
+            let Some(inclusion) = inclusion(tx_hash) else {
+                continue;
+            };
+            for (_, sender) in senders {
+                // Sending only fails if the waiter is being dropped
+                let _ = sender.send(inclusion.clone());
+            }
+        }
+    }
+
+    fn unregister(&self, tx_hash: TxHash, id: u64) {
+        let mut waiters = self.waiters.lock().expect("inclusion waiters lock poisoned");
+        let Some(senders) = waiters.by_hash.get_mut(&tx_hash) else {
+            return;
+        };
+
+        let before = senders.len();
+        senders.retain(|(waiter_id, _)| *waiter_id != id);
+        let removed = before - senders.len();
+        if senders.is_empty() {
+            waiters.by_hash.remove(&tx_hash);
+        }
+        waiters.len -= removed;
+    }
+}
+
+/// A caller waiting for the inclusion of a transaction. Dropping it cancels the wait.
+#[derive(Debug)]
+pub struct InclusionWaiter {
+    registry: Arc<InclusionWaiters>,
+    tx_hash: TxHash,
+    id: u64,
+    receiver: mpsc::UnboundedReceiver<Inclusion>,
+}
+
+impl InclusionWaiter {
+    /// Returns the hash of the awaited transaction.
+    pub const fn tx_hash(&self) -> TxHash {
+        self.tx_hash
+    }
+
+    /// Waits for the next inclusion of the transaction.
+    pub async fn recv(&mut self) -> Option<Inclusion> {
+        self.receiver.recv().await
+    }
+}
+
+impl Drop for InclusionWaiter {
+    fn drop(&mut self) {
+        self.registry.unregister(self.tx_hash, self.id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_notifies_registered_waiters() {
+        let registry = Arc::new(InclusionWaiters::new(2));
+        let tx_hash = TxHash::repeat_byte(1);
+        let mut waiter = registry.register(tx_hash).expect("registry has capacity");
+
+        registry.notify([TxHash::repeat_byte(2), tx_hash], |_| Some(Inclusion::Canonical(7)));
+
+        assert!(matches!(waiter.receiver.try_recv(), Ok(Inclusion::Canonical(7))));
+    }
+
+    #[test]
+    fn test_keeps_unconsumed_notifications() {
+        let registry = Arc::new(InclusionWaiters::new(1));
+        let tx_hash = TxHash::repeat_byte(1);
+        let mut waiter = registry.register(tx_hash).expect("registry has capacity");
+
+        for block_number in 0..8 {
+            registry.notify([tx_hash], |_| Some(Inclusion::Canonical(block_number)));
+        }
+
+        for block_number in 0..8 {
+            assert!(matches!(
+                waiter.receiver.try_recv(),
+                Ok(Inclusion::Canonical(number)) if number == block_number
+            ));
+        }
+    }
+
+    #[test]
+    fn test_bounds_and_releases_waiters() {
+        let registry = Arc::new(InclusionWaiters::new(2));
+        let tx_hash = TxHash::repeat_byte(1);
+
+        let first = registry.register(tx_hash).expect("registry has capacity");
+        let second = registry.register(tx_hash).expect("registry has capacity");
+        assert!(registry.register(TxHash::repeat_byte(2)).is_none());
+
+        drop(first);
+        assert_eq!(registry.len(), 1);
+        drop(second);
+        assert!(registry.is_empty());
+        assert!(registry.register(TxHash::repeat_byte(2)).is_some());
+    }
+}
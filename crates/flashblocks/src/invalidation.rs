@@ -0,0 +1,34 @@
+//! Notifications of preconfirmed transactions dropped from the pending state.
+
+use std::sync::Arc;
+
+use alloy_primitives::{BlockNumber, TxHash};
+use serde::{Deserialize, Serialize};
+
+use crate::PendingBlocks;
+
+/// Why preconfirmed transactions were dropped from the pending state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum InvalidationReason {
+    /// A canonical block did not match the pending block, and the pending state was rebuilt.
+    Reorg,
+    /// Transactions of the pending state were missing from the canonical chain while the pending
+    /// state was otherwise kept or cleared.
+    CanonicalMismatch,
+    /// Flashblocks were missed, so the pending state was cleared until the next base flashblock.
+    SequenceGap,
+}
+
+/// Preconfirmed transactions dropped from the pending state.
+#[derive(Debug, Clone)]
+pub struct Invalidation {
+    /// Why the transactions were dropped.
+    pub reason: InvalidationReason,
+    /// The canonical block that triggered the invalidation, if any.
+    pub canonical_block_number: Option<BlockNumber>,
+    /// Hashes of the dropped transactions, in pending order.
+    pub transactions: Vec<TxHash>,
+    /// The pending state the transactions were dropped from, to look up what was preconfirmed.
+    pub pending_blocks: Arc<PendingBlocks>,
+}
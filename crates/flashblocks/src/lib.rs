@@ -6,17 +6,44 @@
 #[macro_use]
 extern crate tracing;
 
+mod canonical;
+pub use canonical::CanonicalBlock;
+
+mod history;
+pub use history::{DEFAULT_FLASHBLOCK_HISTORY_SIZE, FlashblockHistory, FlashblockReplay};
+
+mod inclusion;
+pub use inclusion::{DEFAULT_MAX_INCLUSION_WAITERS, Inclusion, InclusionWaiter, InclusionWaiters};
+
+mod invalidation;
+pub use invalidation::{Invalidation, InvalidationReason};
+
+mod logs;
+pub use logs::{FlashblockId, PendingLogs};
+
+mod memory;
+pub use memory::PendingBlocksMemoryUsage;
+
 mod metrics;
 pub use metrics::Metrics;
 
+#[cfg(feature = "parallel-execution")]
+mod parallel;
+
 mod pending_blocks;
 pub use pending_blocks::{PendingBlocks, PendingBlocksBuilder};
 
+mod processed;
+pub use processed::{FlashblockStatus, ProcessedFlashblock};
+
 mod processor;
-pub use processor::{StateProcessor, StateUpdate};
+pub use processor::{StateNotifiers, StateProcessor, StateUpdate};
+
+mod snapshot;
+pub use snapshot::FlashblockSnapshot;
 
 mod state;
-pub use state::FlashblocksState;
+pub use state::{DEFAULT_BROADCAST_BUFFER_SIZE, FlashblocksState};
 
 mod subscription;
 pub use subscription::FlashblocksSubscriber;
@@ -0,0 +1,227 @@
+//! Ordered and indexed store of the logs emitted by pending transactions.
+
+use alloy_primitives::{Address, B256, BlockNumber, Bloom, BloomInput, map::foldhash::HashMap};
+use alloy_rpc_types_eth::{Filter, Log};
+
+/// Identifies a flashblock by its block number and index within the block.
+pub type FlashblockId = (BlockNumber, u64);
+
+/// The logs emitted by the transactions of a single flashblock.
+#[derive(Debug, Clone)]
+struct FlashblockLogs {
+    id: FlashblockId,
+    /// Position of the first log of the flashblock in the store.
+    start: usize,
+    /// Position after the last log of the flashblock in the store.
+    end: usize,
+    bloom: Bloom,
+}
+
+/// Logs of the pending state, in `(block, transaction index, log index)` order.
+///
+/// Logs are grouped by the flashblock that emitted them, each with its own bloom, and indexed by
+/// address and first topic, so that a query only inspects the logs that can match it.
+#[derive(Debug, Clone, Default)]
+pub struct PendingLogs {
+    logs: Vec<Log>,
+    flashblocks: Vec<FlashblockLogs>,
+    /// Logs of the flashblock that is being built.
+    bloom: Bloom,
+    by_address: HashMap<Address, Vec<usize>>,
+    by_topic0: HashMap<B256, Vec<usize>>,
+}
+
+impl PendingLogs {
+    /// Appends a log to the flashblock that is being built.
+    pub(crate) fn push(&mut self, log: Log) {
+        let position = self.logs.len();
+        self.bloom.accrue_log(&log.inner);
+        self.by_address.entry(log.address()).or_default().push(position);
+        if let Some(topic0) = log.topics().first() {
+            self.by_topic0.entry(*topic0).or_default().push(position);
+        }
+        self.logs.push(log);
+    }
+
+    /// Closes the flashblock that is being built. Logs pushed afterwards belong to the next
+    /// flashblock.
+    pub(crate) fn end_flashblock(&mut self, id: FlashblockId) {
+        let start = self.flashblocks.last().map_or(0, |flashblock| flashblock.end);
+        let bloom = std::mem::take(&mut self.bloom);
+        self.flashblocks.push(FlashblockLogs { id, start, end: self.logs.len(), bloom });
+    }
+
+    /// Removes the logs of a block, rebuilding the indexes.
+    pub(crate) fn remove_block(&mut self, block_number: BlockNumber) {
+        let Self { logs, flashblocks, .. } = std::mem::take(self);
+        let mut logs = logs.into_iter();
+        for flashblock in flashblocks {
+            let flashblock_logs = logs.by_ref().take(flashblock.end - flashblock.start);
+            if flashblock.id.0 == block_number {
+                flashblock_logs.for_each(drop);
+                continue;
+            }
+
+            flashblock_logs.for_each(|log| self.push(log));
+            self.end_flashblock(flashblock.id);
+        }
+    }
+
+    /// Returns all pending logs.
+    pub fn logs(&self) -> &[Log] {
+        &self.logs
+    }
+
+    /// Returns the pending logs matching the filter, in order.
+    pub fn matching(&self, filter: &Filter) -> Vec<Log> {
+        let Some(positions) = self.indexed_positions(filter) else {
+            return self.matching_after(filter, None);
+        };
+
+        positions
+            .into_iter()
+            .map(|position| &self.logs[position])
+            .filter(|log| filter.matches(&log.inner))
+            .cloned()
+            .collect()
+    }
+
+    /// Returns the logs matching the filter that were emitted by flashblocks after the given one,
+    /// in order. Flashblocks whose bloom does not match the filter are skipped.
+    pub fn matching_after(&self, filter: &Filter, after: Option<FlashblockId>) -> Vec<Log> {
+        self.flashblocks
+            .iter()
+            .filter(|flashblock| after.is_none_or(|after| flashblock.id > after))
+            .filter(|flashblock| matches_bloom(filter, &flashblock.bloom))
+            .flat_map(|flashblock| &self.logs[flashblock.start..flashblock.end])
+            .filter(|log| filter.matches(&log.inner))
+            .cloned()
+            .collect()
+    }
+
+    /// Returns the positions of the logs that can match the filter according to the address and
+    /// topic indexes, in order. Returns None if the filter constrains neither.
+    fn indexed_positions(&self, filter: &Filter) -> Option<Vec<usize>> {
+        let mut positions: Vec<usize> = if !filter.address.is_empty() {
+            filter
+                .address
+                .iter()
+                .filter_map(|address| self.by_address.get(address))
+                .flatten()
+                .copied()
+                .collect()
+        } else if !filter.topics[0].is_empty() {
+            filter.topics[0]
+                .iter()
+                .filter_map(|topic| self.by_topic0.get(topic))
+                .flatten()
+                .copied()
+                .collect()
+        } else {
+            return None;
+        };
+
+        positions.sort_unstable();
+        positions.dedup();
+        Some(positions)
+    }
+}
+
+/// Returns whether a bloom may contain logs matching the filter.
+fn matches_bloom(filter: &Filter, bloom: &Bloom) -> bool {
+    let address_matches = filter.address.is_empty()
+        || filter
+            .address
+            .iter()
+            .any(|address| bloom.contains_input(BloomInput::Raw(address.as_slice())));
+
+    address_matches
+        && filter.topics.iter().all(|topics| {
+            topics.is_empty()
+                || topics
+                    .iter()
+                    .any(|topic| bloom.contains_input(BloomInput::Raw(topic.as_slice())))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::{LogData, address, b256};
+
+    use super::*;
+
+    const EMITTER_A: Address = address!("0x00000000000000000000000000000000000000aa");
+    const EMITTER_B: Address = address!("0x00000000000000000000000000000000000000bb");
+    const TOPIC: B256 = b256!("0x0000000000000000000000000000000000000000000000000000000000000001");
+
+    fn log(address: Address, block_number: BlockNumber, log_index: u64) -> Log {
+        Log {
+            inner: alloy_primitives::Log {
+                address,
+                data: LogData::new_unchecked(vec![TOPIC], Default::default()),
+            },
+            block_number: Some(block_number),
+            log_index: Some(log_index),
+            ..Default::default()
+        }
+    }
+
+    fn pending_logs() -> PendingLogs {
+        let mut logs = PendingLogs::default();
+        logs.push(log(EMITTER_A, 1, 0));
+        logs.push(log(EMITTER_B, 1, 1));
+        logs.end_flashblock((1, 0));
+        logs.end_flashblock((1, 1));
+        logs.push(log(EMITTER_B, 2, 0));
+        logs.push(log(EMITTER_A, 2, 1));
+        logs.end_flashblock((2, 0));
+        logs
+    }
+
+    fn positions(logs: &[Log]) -> Vec<(Option<BlockNumber>, Option<u64>)> {
+        logs.iter().map(|log| (log.block_number, log.log_index)).collect()
+    }
+
+    #[test]
+    fn test_matching_returns_logs_in_order() {
+        let logs = pending_logs();
+
+        let filter = Filter::default().address(vec![EMITTER_B, EMITTER_A]);
+        assert_eq!(
+            positions(&logs.matching(&filter)),
+            vec![(Some(1), Some(0)), (Some(1), Some(1)), (Some(2), Some(0)), (Some(2), Some(1))]
+        );
+
+        let filter = Filter::default().address(EMITTER_A).event_signature(TOPIC);
+        assert_eq!(
+            positions(&logs.matching(&filter)),
+            vec![(Some(1), Some(0)), (Some(2), Some(1))]
+        );
+
+        let filter = Filter::default().event_signature(B256::repeat_byte(2));
+        assert!(logs.matching(&filter).is_empty());
+    }
+
+    #[test]
+    fn test_matching_after_skips_earlier_flashblocks() {
+        let logs = pending_logs();
+        let filter = Filter::default().address(EMITTER_A);
+
+        assert_eq!(
+            positions(&logs.matching_after(&filter, Some((1, 1)))),
+            vec![(Some(2), Some(1))]
+        );
+        assert!(logs.matching_after(&filter, Some((2, 0))).is_empty());
+    }
+
+    #[test]
+    fn test_remove_block_rebuilds_indexes() {
+        let mut logs = pending_logs();
+        logs.remove_block(1);
+
+        let filter = Filter::default().address(EMITTER_A);
+        assert_eq!(positions(&logs.matching(&filter)), vec![(Some(2), Some(1))]);
+        assert_eq!(positions(&logs.matching_after(&filter, None)), vec![(Some(2), Some(1))]);
+        assert_eq!(logs.logs().len(), 2);
+    }
+}
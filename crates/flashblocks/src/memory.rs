@@ -0,0 +1,130 @@
+//! Approximate memory accounting for pending flashblock state.
+
+use alloy_eips::Encodable2718;
+use alloy_primitives::{Address, B256, U256};
+use alloy_rpc_types_eth::state::{AccountOverride, StateOverride};
+use base_flashtypes::Flashblock;
+use op_alloy_rpc_types::{OpTransactionReceipt, Transaction};
+use reth::revm::{
+    bytecode::Bytecode,
+    db::{Cache, DbAccount},
+    state::{Account, EvmState, EvmStorageSlot},
+};
+
+/// Approximate number of bytes held by each component of [`PendingBlocks`](crate::PendingBlocks).
+///
+/// Sizes are estimated from the shallow size of each entry plus its variable length payloads
+/// (bytecode, calldata, logs, storage), ignoring allocator and hash table overhead.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PendingBlocksMemoryUsage {
+    /// Raw flashblock payloads.
+    pub flashblocks: usize,
+    /// Pending transactions, including the lookup by hash.
+    pub transactions: usize,
+    /// Transaction receipts.
+    pub receipts: usize,
+    /// Ordered and indexed pending logs.
+    pub logs: usize,
+    /// Per-transaction state diffs.
+    pub transaction_state: usize,
+    /// Accumulated state overrides.
+    pub state_overrides: usize,
+    /// Per-flashblock snapshots.
+    pub snapshots: usize,
+    /// Cached database state.
+    pub db_cache: usize,
+}
+
+impl PendingBlocksMemoryUsage {
+    /// Returns the total approximate number of bytes.
+    pub const fn total(&self) -> usize {
+        self.flashblocks
+            + self.transactions
+            + self.receipts
+            + self.logs
+            + self.transaction_state
+            + self.state_overrides
+            + self.snapshots
+            + self.db_cache
+    }
+}
+
+/// Number of entries dropped to bring pending state under its memory limit.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct PruneStats {
+    /// Per-transaction state diffs removed.
+    pub(crate) transaction_states: usize,
+    /// Older pending blocks whose transactions and receipts were removed.
+    pub(crate) blocks: usize,
+}
+
+/// Transactions of a pending block that was pruned to save memory. The block remains part of the
+/// pending state, but its transactions and receipts can no longer be queried.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct PrunedBlock {
+    /// Hashes of the transactions in the block, in order.
+    pub(crate) transaction_hashes: Vec<B256>,
+    /// State changes of the block's flashblock snapshots, merged in order.
+    pub(crate) state_changes: StateOverride,
+}
+
+pub(crate) fn flashblock_size(flashblock: &Flashblock) -> usize {
+    size_of::<Flashblock>()
+        + flashblock.diff.transactions.iter().map(|tx| tx.len()).sum::<usize>()
+        + flashblock.base.as_ref().map_or(0, |base| base.extra_data.len())
+}
+
+pub(crate) fn transaction_size(transaction: &Transaction) -> usize {
+    size_of::<Transaction>() + transaction.inner.inner.inner().encode_2718_len()
+}
+
+pub(crate) fn receipt_size(receipt: &OpTransactionReceipt) -> usize {
+    size_of::<OpTransactionReceipt>() + receipt.inner.logs().iter().map(log_size).sum::<usize>()
+}
+
+pub(crate) fn log_size(log: &alloy_rpc_types_eth::Log) -> usize {
+    size_of::<alloy_rpc_types_eth::Log>()
+        + log.inner.data.data.len()
+        + log.topics().len() * size_of::<B256>()
+}
+
+pub(crate) fn evm_state_size(state: &EvmState) -> usize {
+    state
+        .values()
+        .map(|account| {
+            size_of::<(Address, Account)>()
+                + account.storage.len() * size_of::<(U256, EvmStorageSlot)>()
+                + account.info.code.as_ref().map_or(0, Bytecode::len)
+        })
+        .sum()
+}
+
+pub(crate) fn state_override_size(state_overrides: &StateOverride) -> usize {
+    state_overrides
+        .values()
+        .map(|account| {
+            size_of::<(Address, AccountOverride)>()
+                + account.code.as_ref().map_or(0, |code| code.len())
+                + account.state.as_ref().map_or(0, |slots| slots.len()) * size_of::<(B256, B256)>()
+                + account.state_diff.as_ref().map_or(0, |slots| slots.len())
+                    * size_of::<(B256, B256)>()
+        })
+        .sum()
+}
+
+pub(crate) fn cache_size(cache: &Cache) -> usize {
+    let accounts = cache
+        .accounts
+        .values()
+        .map(|account| {
+            size_of::<(Address, DbAccount)>() + account.storage.len() * size_of::<(U256, U256)>()
+        })
+        .sum::<usize>();
+    let contracts = cache
+        .contracts
+        .values()
+        .map(|code| size_of::<(B256, Bytecode)>() + code.len())
+        .sum::<usize>();
+
+    accounts + contracts + cache.block_hashes.len() * size_of::<(U256, B256)>()
+}
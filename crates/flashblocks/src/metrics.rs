@@ -1,8 +1,16 @@
 //! Metrics for flashblocks.
 
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
+
 use metrics::{Counter, Gauge, Histogram};
 use metrics_derive::Metrics;
 
+use crate::PendingBlocksMemoryUsage;
+
 /// Metrics for the `reth_flashblocks` component.
 /// Conventions:
 /// - Durations are recorded in seconds (histograms).
@@ -49,6 +57,10 @@ pub struct Metrics {
     #[metric(describe = "Number of times pending snapshot was cleared because of reorg")]
     pub pending_clear_reorg: Counter,
 
+    /// Number of preconfirmed transactions dropped from the pending state.
+    #[metric(describe = "Number of preconfirmed transactions dropped from the pending state")]
+    pub invalidated_transactions: Counter,
+
     /// Pending snapshot flashblock index (current).
     #[metric(describe = "Pending snapshot flashblock index (current)")]
     pub pending_snapshot_fb_index: Gauge,
@@ -60,4 +72,178 @@ pub struct Metrics {
     /// Total number of WebSocket reconnection attempts.
     #[metric(describe = "Total number of WebSocket reconnection attempts")]
     pub reconnect_attempts: Counter,
+
+    /// Time spent speculatively executing transactions in parallel.
+    #[metric(describe = "Time spent speculatively executing transactions in parallel")]
+    pub speculative_execution_duration: Histogram,
+
+    /// Count of speculative execution results reused without re-execution.
+    #[metric(describe = "Count of speculative execution results reused without re-execution")]
+    pub speculative_execution_reused: Counter,
+
+    /// Count of transactions re-executed because their speculative result conflicted.
+    #[metric(
+        describe = "Count of transactions re-executed because their speculative result conflicted"
+    )]
+    pub speculative_execution_conflicts: Counter,
+
+    /// Approximate bytes held by flashblock payloads in pending state.
+    #[metric(describe = "Approximate bytes held by flashblock payloads in pending state")]
+    pub pending_memory_flashblocks_bytes: Gauge,
+
+    /// Approximate bytes held by transactions in pending state.
+    #[metric(describe = "Approximate bytes held by transactions in pending state")]
+    pub pending_memory_transactions_bytes: Gauge,
+
+    /// Approximate bytes held by receipts in pending state.
+    #[metric(describe = "Approximate bytes held by receipts in pending state")]
+    pub pending_memory_receipts_bytes: Gauge,
+
+    /// Approximate bytes held by the pending log store.
+    #[metric(describe = "Approximate bytes held by the pending log store")]
+    pub pending_memory_logs_bytes: Gauge,
+
+    /// Approximate bytes held by per-transaction state diffs in pending state.
+    #[metric(describe = "Approximate bytes held by per-transaction state diffs in pending state")]
+    pub pending_memory_transaction_state_bytes: Gauge,
+
+    /// Approximate bytes held by state overrides in pending state.
+    #[metric(describe = "Approximate bytes held by state overrides in pending state")]
+    pub pending_memory_state_overrides_bytes: Gauge,
+
+    /// Approximate bytes held by per-flashblock snapshots in pending state.
+    #[metric(describe = "Approximate bytes held by per-flashblock snapshots in pending state")]
+    pub pending_memory_snapshots_bytes: Gauge,
+
+    /// Approximate bytes held by the database cache in pending state.
+    #[metric(describe = "Approximate bytes held by the database cache in pending state")]
+    pub pending_memory_db_cache_bytes: Gauge,
+
+    /// Approximate total bytes held by pending state.
+    #[metric(describe = "Approximate total bytes held by pending state")]
+    pub pending_memory_total_bytes: Gauge,
+
+    /// Count of per-transaction state diffs pruned to stay within the memory limit.
+    #[metric(
+        describe = "Count of per-transaction state diffs pruned to stay within the memory limit"
+    )]
+    pub pruned_transaction_states: Counter,
+
+    /// Count of pending blocks pruned to stay within the memory limit.
+    #[metric(describe = "Count of pending blocks pruned to stay within the memory limit")]
+    pub pruned_pending_blocks: Counter,
+}
+
+impl Metrics {
+    /// Updates the pending state memory gauges.
+    pub fn record_memory_usage(&self, usage: &PendingBlocksMemoryUsage) {
+        self.pending_memory_flashblocks_bytes.set(usage.flashblocks as f64);
+        self.pending_memory_transactions_bytes.set(usage.transactions as f64);
+        self.pending_memory_receipts_bytes.set(usage.receipts as f64);
+        self.pending_memory_logs_bytes.set(usage.logs as f64);
+        self.pending_memory_transaction_state_bytes.set(usage.transaction_state as f64);
+        self.pending_memory_state_overrides_bytes.set(usage.state_overrides as f64);
+        self.pending_memory_snapshots_bytes.set(usage.snapshots as f64);
+        self.pending_memory_db_cache_bytes.set(usage.db_cache as f64);
+        self.pending_memory_total_bytes.set(usage.total() as f64);
+    }
+}
+
+/// Latencies of a flashblock moving from the websocket to the queryable pending state, labelled by
+/// flashblock index since later flashblocks in a block carry more transactions to re-validate.
+#[derive(Metrics, Clone)]
+#[metrics(scope = "reth_flashblocks")]
+pub(crate) struct FlashblockLatencyMetrics {
+    /// Time between a flashblock being received and taken off the processing queue.
+    #[metric(
+        describe = "Time between a flashblock being received and taken off the processing queue"
+    )]
+    flashblock_queue_duration: Histogram,
+
+    /// Time between a flashblock being taken off the queue and execution starting.
+    #[metric(
+        describe = "Time between a flashblock being taken off the queue and execution starting"
+    )]
+    flashblock_pre_execution_duration: Histogram,
+
+    /// Time between execution starting and the pending state being swapped in.
+    #[metric(describe = "Time between execution starting and the pending state being swapped in")]
+    flashblock_execution_duration: Histogram,
+
+    /// Time between a flashblock being received and the pending state being swapped in.
+    #[metric(
+        describe = "Time between a flashblock being received and the pending state being swapped in"
+    )]
+    flashblock_end_to_end_duration: Histogram,
+
+    /// Seconds between the block timestamp and the flashblock becoming queryable.
+    #[metric(
+        describe = "Seconds between the block timestamp and the flashblock becoming queryable"
+    )]
+    flashblock_block_timestamp_lag: Histogram,
+}
+
+/// Latency metrics of each flashblock index, registered on first use and reused for every later
+/// flashblock with the same index.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct FlashblockLatencies {
+    by_index: Arc<Mutex<HashMap<u64, FlashblockLatencyMetrics>>>,
+}
+
+impl FlashblockLatencies {
+    /// Returns the latency metrics labelled with the given flashblock index.
+    fn for_index(&self, index: u64) -> FlashblockLatencyMetrics {
+        let mut by_index = self.by_index.lock().expect("flashblock latency metrics mutex poisoned");
+        by_index
+            .entry(index)
+            .or_insert_with(|| {
+                FlashblockLatencyMetrics::new_with_labels(&[(
+                    "flashblock_index",
+                    index.to_string(),
+                )])
+            })
+            .clone()
+    }
+}
+
+/// Timestamps captured as a flashblock moves from the websocket to the queryable pending state.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct FlashblockTimings {
+    index: u64,
+    received_at: Instant,
+    dequeued_at: Instant,
+    /// Set once execution of the first block re-built for the flashblock starts, including
+    /// speculative execution.
+    pub(crate) execution_started_at: Option<Instant>,
+}
+
+impl FlashblockTimings {
+    /// Starts tracking a flashblock that was just taken off the processing queue.
+    pub(crate) fn dequeued(index: u64, received_at: Instant) -> Self {
+        Self { index, received_at, dequeued_at: Instant::now(), execution_started_at: None }
+    }
+
+    /// Records stage latencies once the pending state containing the flashblock was swapped in.
+    pub(crate) fn record_swapped(&self, latencies: &FlashblockLatencies, block_timestamp: u64) {
+        let swapped_at = Instant::now();
+        let metrics = latencies.for_index(self.index);
+
+        metrics.flashblock_queue_duration.record(self.dequeued_at.duration_since(self.received_at));
+        if let Some(execution_started_at) = self.execution_started_at {
+            metrics
+                .flashblock_pre_execution_duration
+                .record(execution_started_at.duration_since(self.dequeued_at));
+            metrics
+                .flashblock_execution_duration
+                .record(swapped_at.duration_since(execution_started_at));
+        }
+        metrics.flashblock_end_to_end_duration.record(swapped_at.duration_since(self.received_at));
+
+        // Can be negative if the flashblock is available before the block timestamp is reached
+        if let Ok(now) = SystemTime::now().duration_since(UNIX_EPOCH) {
+            metrics
+                .flashblock_block_timestamp_lag
+                .record(now.as_secs_f64() - block_timestamp as f64);
+        }
+    }
 }
@@ -0,0 +1,725 @@
+//! Optimistic parallel execution of flashblock transactions.
+//!
+//! Transactions are first executed concurrently against a snapshot of the pending state, while
+//! recording every account and storage slot they read. Results are then validated in block order:
+//! a speculative result is only reused if none of its reads were written by an earlier transaction
+//! of the same batch, otherwise the transaction is re-executed sequentially on committed state.
+//!
+//! Every transaction credits fees to the fee vaults after execution completes, which would make all
+//! transactions conflict with each other. Accounts that are first read once the top-level frame has
+//! finished are therefore tracked as fee credits, and their balances are rebased on top of the
+//! committed state instead of forcing a re-execution.
+
+use std::{cell::Cell, rc::Rc};
+
+use alloy_consensus::transaction::Recovered;
+use alloy_primitives::{
+    Address, B256, U256,
+    map::foldhash::{HashMap, HashSet},
+};
+use eyre::eyre;
+use op_alloy_consensus::OpTxEnvelope;
+use rayon::prelude::*;
+use reth::revm::{
+    Database, DatabaseRef, Inspector,
+    bytecode::Bytecode,
+    context::result::ResultAndState,
+    db::{AccountState, Cache, DbAccount},
+    interpreter::{CallInputs, CallOutcome, CreateInputs, CreateOutcome},
+    state::{AccountInfo, EvmState},
+};
+use reth_evm::{
+    ConfigureEvm, Evm, EvmEnvFor, FromRecoveredTx, HaltReasonFor, InspectorFor, TxEnvFor,
+};
+
+/// Locations read by a speculatively executed transaction.
+#[derive(Debug, Default)]
+struct ReadSet {
+    accounts: HashSet<Address>,
+    storage: HashSet<(Address, U256)>,
+    /// Accounts first read after execution completed, with the value observed at the time.
+    fee_credits: HashMap<Address, Option<AccountInfo>>,
+}
+
+/// State loaded from the underlying provider during speculation, used to warm the pending cache.
+#[derive(Debug, Default)]
+struct LoadedState {
+    accounts: Vec<(Address, Option<AccountInfo>)>,
+    storage: Vec<(Address, U256, U256)>,
+    contracts: Vec<(B256, Bytecode)>,
+}
+
+/// Result of speculatively executing a single transaction.
+#[derive(Debug)]
+pub(crate) struct SpeculativeExecution<H> {
+    result: Option<ResultAndState<H>>,
+    reads: ReadSet,
+    loaded: LoadedState,
+}
+
+/// Database serving reads from a snapshot of the pending cache and the canonical state, recording
+/// every location that is accessed.
+#[derive(Debug)]
+pub(crate) struct SpeculativeDb<'a, DB> {
+    cache: &'a Cache,
+    db: &'a DB,
+    execution_done: Rc<Cell<bool>>,
+    reads: ReadSet,
+    loaded: LoadedState,
+}
+
+impl<'a, DB: DatabaseRef> SpeculativeDb<'a, DB> {
+    fn new(cache: &'a Cache, db: &'a DB, execution_done: Rc<Cell<bool>>) -> Self {
+        Self {
+            cache,
+            db,
+            execution_done,
+            reads: ReadSet::default(),
+            loaded: LoadedState::default(),
+        }
+    }
+
+    fn load_storage(&mut self, address: Address, index: U256) -> Result<U256, DB::Error> {
+        let value = self.db.storage_ref(address, index)?;
+        self.loaded.storage.push((address, index, value));
+        Ok(value)
+    }
+}
+
+impl<DB: DatabaseRef> Database for SpeculativeDb<'_, DB> {
+    type Error = DB::Error;
+
+    fn basic(&mut self, address: Address) -> Result<Option<AccountInfo>, Self::Error> {
+        let info = match self.cache.accounts.get(&address) {
+            Some(account) => account.info(),
+            None => {
+                let info = self.db.basic_ref(address)?;
+                self.loaded.accounts.push((address, info.clone()));
+                info
+            }
+        };
+
+        if self.execution_done.get() && !self.reads.accounts.contains(&address) {
+            self.reads.fee_credits.insert(address, info.clone());
+        } else {
+            self.reads.accounts.insert(address);
+        }
+
+        Ok(info)
+    }
+
+    fn code_by_hash(&mut self, code_hash: B256) -> Result<Bytecode, Self::Error> {
+        match self.cache.contracts.get(&code_hash) {
+            Some(code) => Ok(code.clone()),
+            None => {
+                let code = self.db.code_by_hash_ref(code_hash)?;
+                self.loaded.contracts.push((code_hash, code.clone()));
+                Ok(code)
+            }
+        }
+    }
+
+    fn storage(&mut self, address: Address, index: U256) -> Result<U256, Self::Error> {
+        self.reads.storage.insert((address, index));
+
+        match self.cache.accounts.get(&address) {
+            Some(account) => match account.storage.get(&index) {
+                Some(value) => Ok(*value),
+                None if matches!(
+                    account.account_state,
+                    AccountState::StorageCleared | AccountState::NotExisting
+                ) =>
+                {
+                    Ok(U256::ZERO)
+                }
+                None => self.load_storage(address, index),
+            },
+            None => self.load_storage(address, index),
+        }
+    }
+
+    fn block_hash(&mut self, number: u64) -> Result<B256, Self::Error> {
+        match self.cache.block_hashes.get(&U256::from(number)) {
+            Some(hash) => Ok(*hash),
+            None => self.db.block_hash_ref(number),
+        }
+    }
+}
+
+/// Inspector that flags when the top-level frame of a transaction has completed, so reads made
+/// while crediting fees can be told apart from reads made during execution.
+#[derive(Debug)]
+pub(crate) struct ExecutionPhaseInspector {
+    depth: usize,
+    execution_done: Rc<Cell<bool>>,
+}
+
+impl ExecutionPhaseInspector {
+    const fn new(execution_done: Rc<Cell<bool>>) -> Self {
+        Self { depth: 0, execution_done }
+    }
+
+    fn frame_end(&mut self) {
+        self.depth = self.depth.saturating_sub(1);
+        if self.depth == 0 {
+            self.execution_done.set(true);
+        }
+    }
+}
+
+impl<CTX> Inspector<CTX> for ExecutionPhaseInspector {
+    fn call(&mut self, _context: &mut CTX, _inputs: &mut CallInputs) -> Option<CallOutcome> {
+        self.depth += 1;
+        None
+    }
+
+    fn call_end(&mut self, _context: &mut CTX, _inputs: &CallInputs, _outcome: &mut CallOutcome) {
+        self.frame_end();
+    }
+
+    fn create(&mut self, _context: &mut CTX, _inputs: &mut CreateInputs) -> Option<CreateOutcome> {
+        self.depth += 1;
+        None
+    }
+
+    fn create_end(
+        &mut self,
+        _context: &mut CTX,
+        _inputs: &CreateInputs,
+        _outcome: &mut CreateOutcome,
+    ) {
+        self.frame_end();
+    }
+}
+
+/// Speculatively executes transactions in parallel against a snapshot of the pending state.
+///
+/// Each rayon worker opens its own view of the canonical state through `open_db`. Transactions
+/// for which `skip` returns `true` (e.g. already executed in a previous flashblock) are not
+/// executed. The returned vector is indexed by the position of the transaction in the block.
+pub(crate) fn speculate<C, DB, F>(
+    evm_config: &C,
+    evm_env: &EvmEnvFor<C>,
+    cache: &Cache,
+    transactions: &[(OpTxEnvelope, Address)],
+    skip: impl Fn(&B256) -> bool + Sync,
+    open_db: F,
+) -> Vec<Option<SpeculativeExecution<HaltReasonFor<C>>>>
+where
+    C: ConfigureEvm + Sync,
+    DB: DatabaseRef,
+    F: Fn() -> eyre::Result<DB> + Sync + Send,
+    EvmEnvFor<C>: Sync,
+    TxEnvFor<C>: FromRecoveredTx<OpTxEnvelope>,
+    HaltReasonFor<C>: Send,
+    for<'a, 'b> ExecutionPhaseInspector: InspectorFor<C, &'a mut SpeculativeDb<'b, DB>>,
+{
+    transactions
+        .par_iter()
+        .map_init(&open_db, |db, (transaction, sender)| {
+            if skip(&transaction.tx_hash()) {
+                return None;
+            }
+
+            let db = match db {
+                Ok(db) => db,
+                Err(e) => {
+                    debug!(message = "could not open state for speculative execution", error = %e);
+                    return None;
+                }
+            };
+
+            let execution_done = Rc::new(Cell::new(false));
+            let mut speculative_db = SpeculativeDb::new(cache, db, execution_done.clone());
+            let result = {
+                let mut evm = evm_config.evm_with_env_and_inspector(
+                    &mut speculative_db,
+                    evm_env.clone(),
+                    ExecutionPhaseInspector::new(execution_done),
+                );
+                evm.transact(&Recovered::new_unchecked(transaction.clone(), *sender)).ok()
+            };
+
+            Some(SpeculativeExecution {
+                result,
+                reads: speculative_db.reads,
+                loaded: speculative_db.loaded,
+            })
+        })
+        .collect()
+}
+
+/// Inserts the canonical state loaded during speculation into the pending cache, so transactions
+/// that have to be re-executed do not hit the state provider again.
+pub(crate) fn warm_cache<H>(cache: &mut Cache, executions: &[Option<SpeculativeExecution<H>>]) {
+    for execution in executions.iter().flatten() {
+        for (address, info) in &execution.loaded.accounts {
+            cache.accounts.entry(*address).or_insert_with(|| match info {
+                Some(info) => DbAccount::from(info.clone()),
+                None => DbAccount::new_not_existing(),
+            });
+        }
+
+        for (address, index, value) in &execution.loaded.storage {
+            if let Some(account) = cache.accounts.get_mut(address)
+                && matches!(account.account_state, AccountState::None | AccountState::Touched)
+            {
+                account.storage.entry(*index).or_insert(*value);
+            }
+        }
+
+        for (code_hash, code) in &execution.loaded.contracts {
+            cache.contracts.entry(*code_hash).or_insert_with(|| code.clone());
+        }
+    }
+}
+
+/// Validates speculative results in block order against the writes of committed transactions.
+#[derive(Debug)]
+pub(crate) struct SpeculationTracker<H> {
+    executions: Vec<Option<SpeculativeExecution<H>>>,
+    written_accounts: HashSet<Address>,
+    written_storage: HashSet<(Address, U256)>,
+    cleared_storage: HashSet<Address>,
+    reused: u64,
+    conflicts: u64,
+}
+
+impl<H> SpeculationTracker<H> {
+    /// Creates a tracker for the given speculative executions.
+    pub(crate) fn new(executions: Vec<Option<SpeculativeExecution<H>>>) -> Self {
+        Self {
+            executions,
+            written_accounts: HashSet::default(),
+            written_storage: HashSet::default(),
+            cleared_storage: HashSet::default(),
+            reused: 0,
+            conflicts: 0,
+        }
+    }
+
+    /// Returns the number of speculative results that were reused.
+    pub(crate) const fn reused(&self) -> u64 {
+        self.reused
+    }
+
+    /// Returns the number of speculative results discarded because of conflicts or failures.
+    pub(crate) const fn conflicts(&self) -> u64 {
+        self.conflicts
+    }
+
+    /// Takes the speculative result for the transaction at `idx` if it is still valid on top of the
+    /// committed state. Fee credits are rebased on the current balances read from `db`.
+    pub(crate) fn take_valid<DB: Database>(
+        &mut self,
+        idx: usize,
+        db: &mut DB,
+    ) -> eyre::Result<Option<ResultAndState<H>>> {
+        let Some(execution) = self.executions.get_mut(idx).and_then(Option::take) else {
+            return Ok(None);
+        };
+
+        let Some(mut result) = execution.result.filter(|_| !self.conflicts_with(&execution.reads))
+        else {
+            self.conflicts += 1;
+            return Ok(None);
+        };
+
+        for (address, observed) in &execution.reads.fee_credits {
+            if !self.written_accounts.contains(address) {
+                continue;
+            }
+
+            let current = db
+                .basic(*address)
+                .map_err(|_| eyre!("failed to load fee recipient account {address}"))?;
+
+            let Some(account) = result.state.get_mut(address) else {
+                continue;
+            };
+
+            let rebased = match (observed, current) {
+                (Some(observed), Some(current)) if observed.code_hash == current.code_hash => {
+                    account
+                        .info
+                        .balance
+                        .checked_sub(observed.balance)
+                        .and_then(|credit| current.balance.checked_add(credit))
+                        .map(|balance| (balance, current.nonce))
+                }
+                _ => None,
+            };
+
+            match rebased {
+                Some((balance, nonce)) => {
+                    account.info.balance = balance;
+                    account.info.nonce = nonce;
+                }
+                None => {
+                    self.conflicts += 1;
+                    return Ok(None);
+                }
+            }
+        }
+
+        self.reused += 1;
+        Ok(Some(result))
+    }
+
+    /// Records the writes of a committed transaction.
+    pub(crate) fn record(&mut self, state: &EvmState) {
+        for (address, account) in state {
+            if !account.is_touched() {
+                continue;
+            }
+
+            self.written_accounts.insert(*address);
+            if account.is_created() || account.is_selfdestructed() {
+                self.cleared_storage.insert(*address);
+            }
+
+            self.written_storage.extend(
+                account
+                    .storage
+                    .iter()
+                    .filter(|(_, slot)| slot.is_changed())
+                    .map(|(index, _)| (*address, *index)),
+            );
+        }
+    }
+
+    fn conflicts_with(&self, reads: &ReadSet) -> bool {
+        reads.accounts.iter().any(|address| self.written_accounts.contains(address))
+            || reads.storage.iter().any(|(address, index)| {
+                self.cleared_storage.contains(address)
+                    || self.written_storage.contains(&(*address, *index))
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeMap, BTreeSet};
+
+    use alloy_consensus::{BlockBody, Header, transaction::SignerRecoverable};
+    use alloy_eips::{BlockNumberOrTag, Decodable2718};
+    use alloy_primitives::{Bytes, address, bytes};
+    use alloy_rpc_types_eth::state::StateOverride;
+    use base_reth_test_utils::{TestAccount, TestHarness};
+    use op_alloy_network::TransactionBuilder;
+    use op_alloy_rpc_types::{OpTransactionReceipt, OpTransactionRequest};
+    use reth::{
+        chainspec::ChainSpecProvider,
+        providers::{HeaderProvider, StateProviderFactory},
+        revm::{State, database::StateProviderDatabase, db::CacheDB},
+    };
+    use reth_optimism_evm::{OpEvmConfig, OpNextBlockEnvAttributes};
+    use reth_optimism_primitives::OpBlock;
+
+    use super::*;
+    use crate::PendingStateBuilder;
+
+    const BLOCK_INFO_TXN: Bytes = bytes!(
+        "0x7ef90104a06c0c775b6b492bab9d7e81abdf27f77cafb698551226455a82f559e0f93fea3794deaddeaddeaddeaddeaddeaddeaddeaddead00019442000000000000000000000000000000000000158080830f424080b8b0098999be000008dd00101c1200000000000000020000000068869d6300000000015f277f000000000000000000000000000000000000000000000000000000000d42ac290000000000000000000000000000000000000000000000000000000000000001abf52777e63959936b1bf633a2a643f0da38d63deffe49452fed1bf8a44975d50000000000000000000000005050f69a9786f081509234f1a7f4684b5e5b76c9000000000000000000000000"
+    );
+
+    /// Sets slot 0 to 1, then deploys a contract incrementing slot 0 on every call.
+    const COUNTER_INIT_CODE: Bytes =
+        bytes!("0x6001600055600a6011600039600a6000f360005460010160005500");
+
+    const BENEFICIARY: Address = address!("0x000000000000000000000000000000000000beef");
+
+    /// State of an account, with the storage slots written to.
+    #[derive(Debug, PartialEq, Eq)]
+    struct AccountSummary {
+        balance: U256,
+        nonce: u64,
+        code_hash: B256,
+        storage: BTreeMap<U256, U256>,
+        created: bool,
+        selfdestructed: bool,
+    }
+
+    /// Receipts and state changes of a block executed with or without speculation.
+    #[derive(Debug)]
+    struct BlockExecution {
+        receipts: Vec<OpTransactionReceipt>,
+        changes: Vec<BTreeMap<Address, AccountSummary>>,
+        accounts: BTreeMap<Address, AccountSummary>,
+        reused: u64,
+        conflicts: u64,
+    }
+
+    fn summarize(state: &EvmState) -> BTreeMap<Address, AccountSummary> {
+        state
+            .iter()
+            .filter(|(_, account)| account.is_touched())
+            .map(|(address, account)| {
+                let summary = AccountSummary {
+                    balance: account.info.balance,
+                    nonce: account.info.nonce,
+                    code_hash: account.info.code_hash,
+                    storage: account
+                        .storage
+                        .iter()
+                        .filter(|(_, slot)| slot.is_changed())
+                        .map(|(index, slot)| (*index, slot.present_value))
+                        .collect(),
+                    created: account.is_created(),
+                    selfdestructed: account.is_selfdestructed(),
+                };
+                (*address, summary)
+            })
+            .collect()
+    }
+
+    fn transaction(account: &TestAccount, request: OpTransactionRequest) -> Bytes {
+        account.sign_txn_request(request).expect("transaction should sign").0
+    }
+
+    fn transfer(account: &TestAccount, nonce: u64, to: Address, value: u64) -> Bytes {
+        transaction(
+            account,
+            OpTransactionRequest::default()
+                .with_to(to)
+                .with_value(U256::from(value))
+                .with_nonce(nonce),
+        )
+    }
+
+    /// Executes the transactions in a block on top of genesis, after the L1 info deposit which is
+    /// executed on its own, like the first flashblock of a block.
+    fn execute(
+        harness: &TestHarness,
+        transactions: &[Bytes],
+        speculative: bool,
+    ) -> eyre::Result<BlockExecution> {
+        let provider = harness.blockchain_provider();
+        let chain_spec = provider.chain_spec();
+        let parent =
+            provider.header_by_number(0)?.ok_or_else(|| eyre!("genesis header should exist"))?;
+
+        let mut txs_with_senders = Vec::new();
+        for transaction in std::iter::once(&BLOCK_INFO_TXN).chain(transactions) {
+            let transaction = OpTxEnvelope::decode_2718(&mut transaction.as_ref())?;
+            let sender = transaction.recover_signer()?;
+            txs_with_senders.push((transaction, sender));
+        }
+        let deposit_hash = txs_with_senders[0].0.tx_hash();
+
+        let evm_config = OpEvmConfig::optimism(chain_spec.clone());
+        let evm_env = evm_config.next_evm_env(
+            &parent,
+            &OpNextBlockEnvAttributes {
+                timestamp: parent.timestamp + 2,
+                suggested_fee_recipient: Address::ZERO,
+                prev_randao: B256::ZERO,
+                gas_limit: parent.gas_limit,
+                parent_beacon_block_root: Some(B256::ZERO),
+                extra_data: parent.extra_data.clone(),
+            },
+        )?;
+
+        let block = OpBlock::new(
+            Header {
+                number: parent.number + 1,
+                timestamp: parent.timestamp + 2,
+                gas_limit: parent.gas_limit,
+                base_fee_per_gas: Some(evm_env.block_env.basefee),
+                ..Default::default()
+            },
+            BlockBody {
+                transactions: txs_with_senders.iter().map(|(tx, _)| tx.clone()).collect(),
+                ommers: Vec::new(),
+                withdrawals: None,
+            },
+        );
+        let l1_block_info = reth_optimism_evm::extract_l1_info(&block.body)?;
+
+        let open_db = || -> eyre::Result<_> {
+            Ok(StateProviderDatabase::new(
+                provider.state_by_block_number_or_tag(BlockNumberOrTag::Number(parent.number))?,
+            ))
+        };
+        let state = State::builder().with_database(open_db()?).with_bundle_update().build();
+
+        let mut pending_state_builder = PendingStateBuilder::new(
+            chain_spec.clone(),
+            evm_config.evm_with_env(CacheDB::new(state), evm_env.clone()),
+            block.clone(),
+            B256::ZERO,
+            None,
+            l1_block_info.clone(),
+            StateOverride::default(),
+            *evm_config.block_executor_factory().receipt_builder(),
+        );
+        let (deposit, sender) = txs_with_senders[0].clone();
+        pending_state_builder.execute_transaction(0, Recovered::new_unchecked(deposit, sender))?;
+        let (mut db, state_overrides) = pending_state_builder.into_db_and_state_overrides();
+
+        let mut speculation = speculative.then(|| {
+            let executions = speculate(
+                &evm_config,
+                &evm_env,
+                &db.cache,
+                &txs_with_senders,
+                |tx_hash| *tx_hash == deposit_hash,
+                &open_db,
+            );
+            warm_cache(&mut db.cache, &executions);
+            SpeculationTracker::new(executions)
+        });
+
+        let mut pending_state_builder = PendingStateBuilder::new(
+            chain_spec,
+            evm_config.evm_with_env(db, evm_env),
+            block,
+            B256::ZERO,
+            None,
+            l1_block_info,
+            state_overrides,
+            *evm_config.block_executor_factory().receipt_builder(),
+        );
+
+        let mut receipts = Vec::new();
+        let mut changes = Vec::new();
+        for (idx, (transaction, sender)) in txs_with_senders.into_iter().enumerate().skip(1) {
+            let transaction = Recovered::new_unchecked(transaction, sender);
+            let executed = match speculation.as_mut() {
+                Some(speculation) => pending_state_builder.execute_transaction_speculatively(
+                    idx,
+                    transaction,
+                    speculation,
+                )?,
+                None => pending_state_builder.execute_transaction(idx, transaction)?,
+            };
+            receipts.push(executed.receipt);
+            changes.push(summarize(executed.state.as_ref().expect("transaction was executed")));
+        }
+
+        // Compare the committed state of every account and slot written by the block
+        let mut written = BTreeMap::<Address, BTreeSet<U256>>::new();
+        for (address, account) in changes.iter().flatten() {
+            written.entry(*address).or_default().extend(account.storage.keys().copied());
+        }
+
+        let (mut db, _) = pending_state_builder.into_db_and_state_overrides();
+        let mut accounts = BTreeMap::new();
+        for (address, slots) in written {
+            let info = db.basic(address)?.unwrap_or_default();
+            let mut storage = BTreeMap::new();
+            for index in slots {
+                storage.insert(index, db.storage(address, index)?);
+            }
+            accounts.insert(
+                address,
+                AccountSummary {
+                    balance: info.balance,
+                    nonce: info.nonce,
+                    code_hash: info.code_hash,
+                    storage,
+                    created: false,
+                    selfdestructed: false,
+                },
+            );
+        }
+
+        Ok(BlockExecution {
+            receipts,
+            changes,
+            accounts,
+            reused: speculation.as_ref().map_or(0, SpeculationTracker::reused),
+            conflicts: speculation.as_ref().map_or(0, SpeculationTracker::conflicts),
+        })
+    }
+
+    /// Executes the transactions sequentially and speculatively, and checks that both produce the
+    /// same receipts and state. Returns the speculative execution.
+    fn assert_equivalent(harness: &TestHarness, transactions: &[Bytes]) -> BlockExecution {
+        let sequential = execute(harness, transactions, false).expect("sequential execution");
+        let speculative = execute(harness, transactions, true).expect("speculative execution");
+
+        assert_eq!(speculative.receipts, sequential.receipts);
+        assert_eq!(speculative.changes, sequential.changes);
+        assert_eq!(speculative.accounts, sequential.accounts);
+        assert_eq!(
+            speculative.reused + speculative.conflicts,
+            transactions.len() as u64,
+            "every transaction should have been speculated"
+        );
+
+        speculative
+    }
+
+    #[tokio::test]
+    async fn test_independent_transactions_rebase_fee_credits() {
+        let harness = TestHarness::new().await.expect("harness should start");
+        let accounts = harness.accounts();
+
+        let transactions = [
+            transfer(&accounts.alice, 0, Address::with_last_byte(0x11), 1),
+            transfer(&accounts.bob, 0, Address::with_last_byte(0x22), 2),
+            transfer(&accounts.charlie, 0, Address::with_last_byte(0x33), 3),
+        ];
+
+        // Every transaction credits the fee vaults, which are rebased instead of conflicting
+        let speculative = assert_equivalent(&harness, &transactions);
+        assert_eq!(speculative.reused, 3);
+        assert_eq!(speculative.conflicts, 0);
+    }
+
+    #[tokio::test]
+    async fn test_same_sender_transactions_are_re_executed() {
+        let harness = TestHarness::new().await.expect("harness should start");
+        let accounts = harness.accounts();
+
+        let transactions = [
+            transfer(&accounts.alice, 0, accounts.bob.address, 1),
+            transfer(&accounts.alice, 1, accounts.charlie.address, 2),
+            transfer(&accounts.alice, 2, accounts.bob.address, 3),
+        ];
+
+        let speculative = assert_equivalent(&harness, &transactions);
+        assert_eq!(speculative.reused, 1);
+        assert_eq!(speculative.conflicts, 2);
+    }
+
+    #[tokio::test]
+    async fn test_created_and_self_destructed_accounts() {
+        let harness = TestHarness::new().await.expect("harness should start");
+        let accounts = harness.accounts();
+
+        let counter = accounts.deployer.address.create(0);
+        // Init code sending the value it was deployed with to the beneficiary
+        let self_destruct = [[0x73].as_slice(), BENEFICIARY.as_slice(), &[0xff]].concat();
+
+        let transactions = [
+            transaction(
+                &accounts.deployer,
+                OpTransactionRequest::default().with_deploy_code(COUNTER_INIT_CODE).with_nonce(0),
+            ),
+            // Reads the code and storage of the contract created by the previous transaction
+            transaction(
+                &accounts.alice,
+                OpTransactionRequest::default().with_to(counter).with_nonce(0),
+            ),
+            transaction(
+                &accounts.bob,
+                OpTransactionRequest::default()
+                    .with_deploy_code(Bytes::from(self_destruct))
+                    .with_value(U256::from(1_000))
+                    .with_nonce(0),
+            ),
+            // Reads the beneficiary credited by the self-destruct
+            transfer(&accounts.charlie, 0, BENEFICIARY, 1),
+        ];
+
+        let speculative = assert_equivalent(&harness, &transactions);
+        assert!(speculative.conflicts >= 2);
+
+        let counter_state = &speculative.accounts[&counter];
+        assert_eq!(counter_state.storage[&U256::ZERO], U256::from(2));
+        assert_eq!(speculative.accounts[&BENEFICIARY].balance, U256::from(1_001));
+        assert!(speculative.changes[2].values().any(|account| account.selfdestructed));
+    }
+}
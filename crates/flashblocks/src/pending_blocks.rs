@@ -1,24 +1,31 @@
-use std::sync::Arc;
+use std::{collections::BTreeMap, sync::Arc};
 
 use alloy_consensus::{Header, Sealed};
 use alloy_eips::BlockNumberOrTag;
 use alloy_primitives::{
-    Address, B256, BlockNumber, TxHash, U256,
+    Address, B256, BlockNumber, Bytes, KECCAK256_EMPTY, TxHash, U256,
     map::foldhash::{HashMap, HashMapExt},
 };
 use alloy_provider::network::TransactionResponse;
 use alloy_rpc_types::{BlockTransactions, state::StateOverride};
 use alloy_rpc_types_eth::{Filter, Header as RPCHeader, Log};
-use arc_swap::Guard;
 use base_flashtypes::Flashblock;
 use eyre::eyre;
 use op_alloy_network::Optimism;
 use op_alloy_rpc_types::{OpTransactionReceipt, Transaction};
-use reth::revm::{db::Cache, state::EvmState};
+use reth::revm::{
+    bytecode::Bytecode,
+    db::{AccountState, Cache},
+    state::EvmState,
+};
 use reth_rpc_convert::RpcTransaction;
 use reth_rpc_eth_api::{RpcBlock, RpcReceipt};
 
-use crate::PendingBlocksAPI;
+use crate::{
+    FlashblockId, FlashblockSnapshot, PendingBlocksAPI, PendingBlocksMemoryUsage, PendingLogs,
+    memory::{self, PruneStats, PrunedBlock},
+    snapshot::merge_state_changes,
+};
 
 /// Builder for [`PendingBlocks`].
 #[derive(Debug)]
@@ -28,12 +35,16 @@ pub struct PendingBlocksBuilder {
 
     transactions: Vec<Transaction>,
     account_balances: HashMap<Address, U256>,
-    transaction_count: HashMap<Address, U256>,
+    account_nonces: HashMap<Address, u64>,
     transaction_receipts: HashMap<B256, OpTransactionReceipt>,
     transactions_by_hash: HashMap<B256, Transaction>,
     transaction_state: HashMap<B256, EvmState>,
     transaction_senders: HashMap<B256, Address>,
+    logs: PendingLogs,
     state_overrides: Option<StateOverride>,
+    flashblock_snapshots: BTreeMap<(BlockNumber, u64), Arc<FlashblockSnapshot>>,
+    pruned_blocks: BTreeMap<BlockNumber, Arc<PrunedBlock>>,
+    next_base_fee: Option<u64>,
 
     db_cache: Cache,
 }
@@ -45,12 +56,16 @@ impl PendingBlocksBuilder {
             headers: Vec::new(),
             transactions: Vec::new(),
             account_balances: HashMap::new(),
-            transaction_count: HashMap::new(),
+            account_nonces: HashMap::new(),
             transaction_receipts: HashMap::new(),
             transactions_by_hash: HashMap::new(),
             transaction_state: HashMap::new(),
             transaction_senders: HashMap::new(),
+            logs: PendingLogs::default(),
             state_overrides: None,
+            flashblock_snapshots: BTreeMap::new(),
+            pruned_blocks: BTreeMap::new(),
+            next_base_fee: None,
             db_cache: Cache::default(),
         }
     }
@@ -89,6 +104,26 @@ impl PendingBlocksBuilder {
         self
     }
 
+    /// Seeds account balances and nonces from a previous pending state, whose transaction state
+    /// diffs may have been pruned. Values from executed transactions take precedence.
+    #[inline]
+    pub(crate) fn with_account_states(&mut self, pending_blocks: &PendingBlocks) -> &Self {
+        self.account_balances.extend(&pending_blocks.account_balances);
+        self.account_nonces.extend(&pending_blocks.account_nonces);
+        self
+    }
+
+    /// Carries over a block whose transactions were pruned from the previous pending state.
+    #[inline]
+    pub(crate) fn with_pruned_block(
+        &mut self,
+        block_number: BlockNumber,
+        pruned_block: Arc<PrunedBlock>,
+    ) -> &Self {
+        self.pruned_blocks.insert(block_number, pruned_block);
+        self
+    }
+
     #[inline]
     pub(crate) fn with_transaction_sender(&mut self, hash: B256, sender: Address) -> &Self {
         self.transaction_senders.insert(hash, sender);
@@ -96,16 +131,16 @@ impl PendingBlocksBuilder {
     }
 
     #[inline]
-    pub(crate) fn increment_nonce(&mut self, sender: Address) -> &Self {
-        let zero = U256::from(0);
-        let current_count = self.transaction_count.get(&sender).unwrap_or(&zero);
-
-        _ = self.transaction_count.insert(sender, *current_count + U256::from(1));
+    pub(crate) fn with_account_nonce(&mut self, address: Address, nonce: u64) -> &Self {
+        self.account_nonces.insert(address, nonce);
         self
     }
 
     #[inline]
     pub(crate) fn with_receipt(&mut self, hash: B256, receipt: OpTransactionReceipt) -> &Self {
+        for log in receipt.inner.logs() {
+            self.logs.push(log.clone());
+        }
         self.transaction_receipts.insert(hash, receipt);
         self
     }
@@ -122,6 +157,21 @@ impl PendingBlocksBuilder {
         self
     }
 
+    /// Records the snapshot taken after a flashblock was applied. The logs of receipts added
+    /// since the previous snapshot are attributed to this flashblock.
+    #[inline]
+    pub(crate) fn with_flashblock_snapshot(&mut self, snapshot: Arc<FlashblockSnapshot>) -> &Self {
+        self.logs.end_flashblock((snapshot.block_number(), snapshot.index()));
+        self.flashblock_snapshots.insert((snapshot.block_number(), snapshot.index()), snapshot);
+        self
+    }
+
+    #[inline]
+    pub(crate) fn with_next_base_fee(&mut self, next_base_fee: Option<u64>) -> &Self {
+        self.next_base_fee = next_base_fee;
+        self
+    }
+
     pub(crate) fn build(self) -> eyre::Result<PendingBlocks> {
         if self.headers.is_empty() {
             return Err(eyre!("missing headers"));
@@ -136,12 +186,16 @@ impl PendingBlocksBuilder {
             headers: self.headers,
             transactions: self.transactions,
             account_balances: self.account_balances,
-            transaction_count: self.transaction_count,
+            account_nonces: self.account_nonces,
             transaction_receipts: self.transaction_receipts,
             transactions_by_hash: self.transactions_by_hash,
             transaction_state: self.transaction_state,
             transaction_senders: self.transaction_senders,
+            logs: self.logs,
             state_overrides: self.state_overrides,
+            flashblock_snapshots: self.flashblock_snapshots,
+            pruned_blocks: self.pruned_blocks,
+            next_base_fee: self.next_base_fee,
             db_cache: self.db_cache,
         })
     }
@@ -155,12 +209,16 @@ pub struct PendingBlocks {
     transactions: Vec<Transaction>,
 
     account_balances: HashMap<Address, U256>,
-    transaction_count: HashMap<Address, U256>,
+    account_nonces: HashMap<Address, u64>,
     transaction_receipts: HashMap<B256, OpTransactionReceipt>,
     transactions_by_hash: HashMap<B256, Transaction>,
     transaction_state: HashMap<B256, EvmState>,
     transaction_senders: HashMap<B256, Address>,
+    logs: PendingLogs,
     state_overrides: Option<StateOverride>,
+    flashblock_snapshots: BTreeMap<(BlockNumber, u64), Arc<FlashblockSnapshot>>,
+    pruned_blocks: BTreeMap<BlockNumber, Arc<PrunedBlock>>,
+    next_base_fee: Option<u64>,
 
     db_cache: Cache,
 }
@@ -186,11 +244,31 @@ impl PendingBlocks {
         self.flashblocks.last().unwrap().index
     }
 
+    /// Returns the index of the latest flashblock of a pending block.
+    pub fn get_latest_flashblock_index(&self, block_number: BlockNumber) -> Option<u64> {
+        self.flashblocks
+            .iter()
+            .rev()
+            .find(|flashblock| flashblock.metadata.block_number == block_number)
+            .map(|flashblock| flashblock.index)
+    }
+
     /// Returns the latest header.
     pub fn latest_header(&self) -> Sealed<Header> {
         self.headers.last().unwrap().clone()
     }
 
+    /// Returns the header of a pending block.
+    pub fn get_header(&self, block_number: BlockNumber) -> Option<Sealed<Header>> {
+        self.headers.iter().find(|header| header.number == block_number).cloned()
+    }
+
+    /// Returns the predicted base fee of the block following the latest pending block, derived
+    /// from the gas used so far.
+    pub const fn get_next_base_fee(&self) -> Option<u64> {
+        self.next_base_fee
+    }
+
     /// Returns all flashblocks.
     pub fn get_flashblocks(&self) -> Vec<Flashblock> {
         self.flashblocks.clone()
@@ -201,6 +279,12 @@ impl PendingBlocks {
         self.transaction_state.get(hash).cloned()
     }
 
+    /// Returns true if the transaction was executed in the pending state. Its EVM state may have
+    /// been pruned to save memory.
+    pub fn has_executed_transaction(&self, hash: &B256) -> bool {
+        self.transaction_receipts.contains_key(hash)
+    }
+
     /// Returns the sender of a transaction.
     pub fn get_transaction_sender(&self, tx_hash: &B256) -> Option<Address> {
         self.transaction_senders.get(tx_hash).cloned()
@@ -211,6 +295,19 @@ impl PendingBlocks {
         self.db_cache.clone()
     }
 
+    /// Returns the hashes of all transactions for a specific block number, including blocks
+    /// whose transactions were pruned.
+    pub fn get_transaction_hashes_for_block(&self, block_number: BlockNumber) -> Vec<B256> {
+        match self.pruned_blocks.get(&block_number) {
+            Some(pruned_block) => pruned_block.transaction_hashes.clone(),
+            None => self
+                .get_transactions_for_block(block_number)
+                .iter()
+                .map(|tx| tx.tx_hash())
+                .collect(),
+        }
+    }
+
     /// Returns all transactions for a specific block number.
     pub fn get_transactions_for_block(&self, block_number: BlockNumber) -> Vec<Transaction> {
         self.transactions
@@ -222,9 +319,47 @@ impl PendingBlocks {
 
     /// Returns the latest block, optionally with full transaction details.
     pub fn get_latest_block(&self, full: bool) -> RpcBlock<Optimism> {
-        let header = self.latest_header();
-        let block_number = header.number;
-        let block_transactions: Vec<Transaction> = self.get_transactions_for_block(block_number);
+        self.build_block(self.latest_header(), full)
+    }
+
+    /// Returns the pending block with the given hash, optionally with full transaction details.
+    /// Returns None if no pending block has the hash or its transactions were pruned.
+    pub fn get_block_by_hash(&self, hash: B256, full: bool) -> Option<RpcBlock<Optimism>> {
+        // Flashblocks without a block hash are sealed with the zero hash
+        if hash.is_zero() {
+            return None;
+        }
+
+        let header = self.headers.iter().find(|header| header.hash() == hash)?;
+        if self.pruned_blocks.contains_key(&header.number) {
+            return None;
+        }
+
+        Some(self.build_block(header.clone(), full))
+    }
+
+    /// Returns the transaction at the given position in the latest block.
+    pub fn get_latest_transaction_by_index(&self, index: usize) -> Option<Transaction> {
+        let block_number = self.latest_block_number();
+        self.transactions
+            .iter()
+            .filter(|tx| tx.block_number.unwrap_or(0) == block_number)
+            .nth(index)
+            .cloned()
+    }
+
+    /// Returns the receipts of all transactions in the latest block, in order.
+    pub fn get_latest_block_receipts(&self) -> Vec<OpTransactionReceipt> {
+        let block_number = self.latest_block_number();
+        self.transactions
+            .iter()
+            .filter(|tx| tx.block_number.unwrap_or(0) == block_number)
+            .filter_map(|tx| self.get_receipt(tx.tx_hash()))
+            .collect()
+    }
+
+    fn build_block(&self, header: Sealed<Header>, full: bool) -> RpcBlock<Optimism> {
+        let block_transactions: Vec<Transaction> = self.get_transactions_for_block(header.number);
 
         let transactions = if full {
             BlockTransactions::Full(block_transactions)
@@ -251,9 +386,10 @@ impl PendingBlocks {
         self.transactions_by_hash.get(&tx_hash).cloned()
     }
 
-    /// Returns the transaction count for an address in pending state.
-    pub fn get_transaction_count(&self, address: Address) -> U256 {
-        self.transaction_count.get(&address).cloned().unwrap_or(U256::from(0))
+    /// Returns the nonce of an address after executing pending transactions. Returns None if the
+    /// account was not touched by pending transactions.
+    pub fn get_nonce(&self, address: Address) -> Option<u64> {
+        self.account_nonces.get(&address).copied()
     }
 
     /// Returns the balance for an address in pending state.
@@ -266,20 +402,287 @@ impl PendingBlocks {
         self.state_overrides.clone()
     }
 
-    /// Returns logs matching the filter from pending state.
-    pub fn get_pending_logs(&self, filter: &Filter) -> Vec<Log> {
-        let mut logs = Vec::new();
-
-        // Iterate through all transaction receipts in pending state
-        for receipt in self.transaction_receipts.values() {
-            for log in receipt.inner.logs() {
-                if filter.matches(&log.inner) {
-                    logs.push(log.clone());
-                }
+    /// Returns the value of a storage slot in pending state. Returns None if the slot was neither
+    /// written nor read while executing pending transactions.
+    pub fn get_storage_at(&self, address: Address, slot: B256) -> Option<B256> {
+        let overridden = self
+            .state_overrides
+            .as_ref()
+            .and_then(|overrides| overrides.get(&address))
+            .and_then(|account| account.state_diff.as_ref())
+            .and_then(|state_diff| state_diff.get(&slot));
+        if let Some(value) = overridden {
+            return Some(*value);
+        }
+
+        let account = self.db_cache.accounts.get(&address)?;
+        match account.storage.get(&U256::from_be_bytes(slot.0)) {
+            Some(value) => Some(B256::from(*value)),
+            // Created or destroyed accounts have no storage left in the canonical state
+            None if matches!(
+                account.account_state,
+                AccountState::StorageCleared | AccountState::NotExisting
+            ) =>
+            {
+                Some(B256::ZERO)
             }
+            None => None,
+        }
+    }
+
+    /// Returns the code of an address in pending state. Returns None if the account was not
+    /// loaded while executing pending transactions.
+    pub fn get_code(&self, address: Address) -> Option<Bytes> {
+        let overridden = self
+            .state_overrides
+            .as_ref()
+            .and_then(|overrides| overrides.get(&address))
+            .and_then(|account| account.code.clone());
+        if overridden.is_some() {
+            return overridden;
         }
 
-        logs
+        let account = self.db_cache.accounts.get(&address)?;
+        if account.account_state == AccountState::NotExisting
+            || account.info.code_hash == KECCAK256_EMPTY
+        {
+            return Some(Bytes::new());
+        }
+
+        match &account.info.code {
+            Some(code) => Some(code.original_bytes()),
+            None => {
+                self.db_cache.contracts.get(&account.info.code_hash).map(Bytecode::original_bytes)
+            }
+        }
+    }
+
+    /// Returns the snapshot of pending state taken after the given flashblock was applied.
+    pub fn get_flashblock_snapshot(
+        &self,
+        block_number: BlockNumber,
+        index: u64,
+    ) -> Option<Arc<FlashblockSnapshot>> {
+        self.flashblock_snapshots.get(&(block_number, index)).cloned()
+    }
+
+    /// Returns the state overrides accumulated up to and including the given flashblock, from the
+    /// state changes of pruned blocks and of every snapshot up to it.
+    pub fn get_state_overrides_at_flashblock(
+        &self,
+        block_number: BlockNumber,
+        index: u64,
+    ) -> Option<StateOverride> {
+        self.flashblock_snapshots.get(&(block_number, index))?;
+
+        let mut state_overrides = StateOverride::default();
+        for pruned_block in self.pruned_blocks.range(..block_number).map(|(_, b)| b) {
+            merge_state_changes(&mut state_overrides, &pruned_block.state_changes);
+        }
+        for snapshot in self.flashblock_snapshots.range(..=(block_number, index)).map(|(_, s)| s) {
+            merge_state_changes(&mut state_overrides, snapshot.state_changes());
+        }
+        Some(state_overrides)
+    }
+
+    /// Returns the state overrides accumulated by the pending blocks before the given one, from
+    /// the state changes of pruned blocks and of every snapshot of those blocks.
+    pub fn get_state_overrides_before_block(&self, block_number: BlockNumber) -> StateOverride {
+        let mut state_overrides = StateOverride::default();
+        for pruned_block in self.pruned_blocks.range(..block_number).map(|(_, b)| b) {
+            merge_state_changes(&mut state_overrides, &pruned_block.state_changes);
+        }
+        for snapshot in self.flashblock_snapshots.range(..(block_number, 0)).map(|(_, s)| s) {
+            merge_state_changes(&mut state_overrides, snapshot.state_changes());
+        }
+        state_overrides
+    }
+
+    /// Returns the balance of an address as of the given flashblock. Returns None if the address
+    /// was not touched by pending transactions up to it, or the flashblock has no snapshot.
+    pub fn get_balance_at_flashblock(
+        &self,
+        address: Address,
+        block_number: BlockNumber,
+        index: u64,
+    ) -> Option<U256> {
+        self.flashblock_snapshots.get(&(block_number, index))?;
+
+        self.flashblock_snapshots
+            .range(..=(block_number, index))
+            .rev()
+            .find_map(|(_, snapshot)| snapshot.state_changes().get(&address))
+            .or_else(|| {
+                self.pruned_blocks
+                    .range(..block_number)
+                    .rev()
+                    .find_map(|(_, pruned_block)| pruned_block.state_changes.get(&address))
+            })
+            .and_then(|account| account.balance)
+    }
+
+    /// Returns the index of the flashblock that included the transaction at the given position of
+    /// a block. Returns None if the snapshots of the block were pruned.
+    pub fn get_transaction_flashblock_index(
+        &self,
+        block_number: BlockNumber,
+        transaction_index: usize,
+    ) -> Option<u64> {
+        self.flashblock_snapshots
+            .range((block_number, 0)..=(block_number, u64::MAX))
+            .find(|(_, snapshot)| snapshot.transaction_count() > transaction_index)
+            .map(|((_, index), _)| *index)
+    }
+
+    /// Returns the receipt for a transaction if it was included at or before the given flashblock.
+    pub fn get_receipt_at_flashblock(
+        &self,
+        tx_hash: TxHash,
+        block_number: BlockNumber,
+        index: u64,
+    ) -> Option<OpTransactionReceipt> {
+        let snapshot = self.flashblock_snapshots.get(&(block_number, index))?;
+        let receipt = self.get_receipt(tx_hash)?;
+
+        let included = match (receipt.inner.block_number, receipt.inner.transaction_index) {
+            (Some(number), _) if number < block_number => true,
+            (Some(number), Some(tx_index)) if number == block_number => {
+                (tx_index as usize) < snapshot.transaction_count()
+            }
+            _ => false,
+        };
+
+        included.then_some(receipt)
+    }
+
+    /// Returns the transactions of a block that was pruned to save memory.
+    pub(crate) fn get_pruned_block(&self, block_number: BlockNumber) -> Option<Arc<PrunedBlock>> {
+        self.pruned_blocks.get(&block_number).cloned()
+    }
+
+    /// Returns the approximate memory held by each component of the pending state.
+    pub fn memory_usage(&self) -> PendingBlocksMemoryUsage {
+        PendingBlocksMemoryUsage {
+            flashblocks: self.flashblocks.iter().map(memory::flashblock_size).sum(),
+            transactions: 2 * self.transactions.iter().map(memory::transaction_size).sum::<usize>(),
+            receipts: self.transaction_receipts.values().map(memory::receipt_size).sum(),
+            logs: self.logs.logs().iter().map(memory::log_size).sum(),
+            transaction_state: self.transaction_state.values().map(memory::evm_state_size).sum(),
+            state_overrides: self.state_overrides.as_ref().map_or(0, memory::state_override_size),
+            snapshots: self
+                .flashblock_snapshots
+                .values()
+                .map(|snapshot| memory::state_override_size(snapshot.state_changes()))
+                .chain(
+                    self.pruned_blocks.values().map(|pruned_block| {
+                        memory::state_override_size(&pruned_block.state_changes)
+                    }),
+                )
+                .sum(),
+            db_cache: memory::cache_size(&self.db_cache),
+        }
+    }
+
+    /// Drops data until the pending state fits within `max_bytes`. Per-transaction state diffs
+    /// are pruned first, oldest first, followed by the transactions and receipts of blocks older
+    /// than the latest one. The latest block, accumulated state and database cache are kept.
+    pub(crate) fn prune_to_memory_limit(&mut self, max_bytes: usize) -> PruneStats {
+        let mut stats = PruneStats::default();
+        let mut usage = self.memory_usage().total();
+
+        for transaction in &self.transactions {
+            if usage <= max_bytes {
+                return stats;
+            }
+
+            if let Some(state) = self.transaction_state.remove(&transaction.tx_hash()) {
+                usage = usage.saturating_sub(memory::evm_state_size(&state));
+                stats.transaction_states += 1;
+            }
+        }
+
+        let latest_block_number = self.latest_block_number();
+        while usage > max_bytes {
+            let Some(block_number) = self
+                .transactions
+                .first()
+                .and_then(|tx| tx.block_number)
+                .filter(|number| *number < latest_block_number)
+            else {
+                break;
+            };
+
+            usage = usage.saturating_sub(self.prune_block(block_number));
+            stats.blocks += 1;
+        }
+
+        stats
+    }
+
+    /// Removes the transactions, receipts and snapshots of a block, keeping what is needed to
+    /// carry it over to the next pending state. Returns the approximate number of bytes freed.
+    fn prune_block(&mut self, block_number: BlockNumber) -> usize {
+        let mut pruned_block = PrunedBlock::default();
+        let mut freed = self
+            .logs
+            .logs()
+            .iter()
+            .filter(|log| log.block_number == Some(block_number))
+            .map(memory::log_size)
+            .sum::<usize>();
+        let Self {
+            transactions,
+            transactions_by_hash,
+            transaction_receipts,
+            transaction_state,
+            transaction_senders,
+            ..
+        } = self;
+
+        transactions.retain(|tx| {
+            if tx.block_number != Some(block_number) {
+                return true;
+            }
+
+            let tx_hash = tx.tx_hash();
+            pruned_block.transaction_hashes.push(tx_hash);
+            freed += 2 * memory::transaction_size(tx);
+            transactions_by_hash.remove(&tx_hash);
+            freed += transaction_receipts.remove(&tx_hash).as_ref().map_or(0, memory::receipt_size);
+            freed += transaction_state.remove(&tx_hash).as_ref().map_or(0, memory::evm_state_size);
+            transaction_senders.remove(&tx_hash);
+            false
+        });
+        // The state changes of the pruned snapshots are still part of the state as of later ones
+        let remaining_snapshots = self.flashblock_snapshots.split_off(&(block_number + 1, 0));
+        let pruned_snapshots =
+            std::mem::replace(&mut self.flashblock_snapshots, remaining_snapshots);
+        for snapshot in pruned_snapshots.values() {
+            freed += memory::state_override_size(snapshot.state_changes());
+            merge_state_changes(&mut pruned_block.state_changes, snapshot.state_changes());
+        }
+        self.logs.remove_block(block_number);
+
+        // Merged changes are never larger than the snapshot changes they were merged from
+        freed = freed.saturating_sub(memory::state_override_size(&pruned_block.state_changes));
+        self.pruned_blocks.insert(block_number, Arc::new(pruned_block));
+        freed
+    }
+
+    /// Returns logs matching the filter from pending state, in `(block, transaction index, log
+    /// index)` order.
+    pub fn get_pending_logs(&self, filter: &Filter) -> Vec<Log> {
+        self.logs.matching(filter)
+    }
+
+    /// Returns logs matching the filter emitted by flashblocks after the given one, in order.
+    pub fn get_pending_logs_after(&self, filter: &Filter, after: Option<FlashblockId>) -> Vec<Log> {
+        self.logs.matching_after(filter, after)
+    }
+
+    /// Returns the block number and index of the latest flashblock.
+    pub fn latest_flashblock_id(&self) -> FlashblockId {
+        (self.latest_block_number(), self.latest_flashblock_index())
     }
 
     /// Returns all pending transactions from flashblocks.
@@ -287,25 +690,55 @@ impl PendingBlocks {
         self.transactions.clone()
     }
 
+    /// Returns the pending transactions added by the flashblocks after the given one, in order.
+    /// Transactions of the given block are skipped if its flashblock is no longer known.
+    pub fn get_transactions_after(&self, after: FlashblockId) -> Vec<Transaction> {
+        let (block_number, _) = after;
+        let delivered = self.flashblock_snapshots.get(&after).map(|s| s.transaction_count());
+
+        self.transactions
+            .iter()
+            .filter(|tx| match tx.block_number.unwrap_or_default() {
+                number if number == block_number => delivered.is_some_and(|delivered| {
+                    tx.transaction_index.unwrap_or_default() as usize >= delivered
+                }),
+                number => number > block_number,
+            })
+            .cloned()
+            .collect()
+    }
+
     /// Returns the hashes of all pending transactions from flashblocks.
     pub fn get_pending_transaction_hashes(&self) -> Vec<B256> {
         self.transactions.iter().map(|tx| tx.tx_hash()).collect()
     }
 }
 
-impl PendingBlocksAPI for Guard<Option<Arc<PendingBlocks>>> {
+impl PendingBlocksAPI for Option<Arc<PendingBlocks>> {
     fn get_canonical_block_number(&self) -> BlockNumberOrTag {
         self.as_ref().map(|pb| pb.canonical_block_number()).unwrap_or(BlockNumberOrTag::Latest)
     }
 
-    fn get_transaction_count(&self, address: Address) -> U256 {
-        self.as_ref().map(|pb| pb.get_transaction_count(address)).unwrap_or_else(|| U256::from(0))
+    fn get_nonce(&self, address: Address) -> Option<u64> {
+        self.as_ref().and_then(|pb| pb.get_nonce(address))
     }
 
     fn get_block(&self, full: bool) -> Option<RpcBlock<Optimism>> {
         self.as_ref().map(|pb| pb.get_latest_block(full))
     }
 
+    fn get_block_by_hash(&self, hash: B256, full: bool) -> Option<RpcBlock<Optimism>> {
+        self.as_ref().and_then(|pb| pb.get_block_by_hash(hash, full))
+    }
+
+    fn get_transaction_by_block_index(&self, index: usize) -> Option<RpcTransaction<Optimism>> {
+        self.as_ref().and_then(|pb| pb.get_latest_transaction_by_index(index))
+    }
+
+    fn get_block_receipts(&self) -> Option<Vec<RpcReceipt<Optimism>>> {
+        self.as_ref().map(|pb| pb.get_latest_block_receipts())
+    }
+
     fn get_transaction_receipt(
         &self,
         tx_hash: alloy_primitives::TxHash,
@@ -328,7 +761,199 @@ impl PendingBlocksAPI for Guard<Option<Arc<PendingBlocks>>> {
         self.as_ref().map(|pb| pb.get_state_overrides()).unwrap_or_default()
     }
 
+    fn get_storage_at(&self, address: Address, slot: B256) -> Option<B256> {
+        self.as_ref().and_then(|pb| pb.get_storage_at(address, slot))
+    }
+
+    fn get_code(&self, address: Address) -> Option<Bytes> {
+        self.as_ref().and_then(|pb| pb.get_code(address))
+    }
+
     fn get_pending_logs(&self, filter: &Filter) -> Vec<Log> {
         self.as_ref().map(|pb| pb.get_pending_logs(filter)).unwrap_or_default()
     }
+
+    fn get_next_base_fee(&self) -> Option<u64> {
+        self.as_ref().and_then(|pb| pb.get_next_base_fee())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::address;
+    use alloy_rpc_types_engine::PayloadId;
+    use alloy_rpc_types_eth::state::AccountOverride;
+    use base_flashtypes::{ExecutionPayloadFlashblockDeltaV1, Metadata};
+    use op_alloy_consensus::{OpTxEnvelope, TxDeposit};
+    use reth::revm::state::Account;
+
+    use super::*;
+
+    const ALICE: Address = address!("0x00000000000000000000000000000000000a11ce");
+    const ACCOUNTS_PER_STATE: u8 = 4;
+
+    fn flashblock(block_number: BlockNumber) -> Flashblock {
+        Flashblock {
+            payload_id: PayloadId::new([0; 8]),
+            index: 0,
+            base: None,
+            diff: ExecutionPayloadFlashblockDeltaV1::default(),
+            metadata: Metadata { block_number },
+        }
+    }
+
+    fn transaction(block_number: BlockNumber, index: u64) -> Transaction {
+        let hash = B256::with_last_byte((block_number * 10 + index) as u8);
+        Transaction {
+            inner: alloy_rpc_types_eth::Transaction {
+                inner: alloy_consensus::transaction::Recovered::new_unchecked(
+                    OpTxEnvelope::Deposit(Sealed::new_unchecked(TxDeposit::default(), hash)),
+                    Address::ZERO,
+                ),
+                block_hash: None,
+                block_number: Some(block_number),
+                transaction_index: Some(index),
+                effective_gas_price: None,
+            },
+            deposit_nonce: None,
+            deposit_receipt_version: None,
+        }
+    }
+
+    fn transaction_state() -> EvmState {
+        (0..ACCOUNTS_PER_STATE).map(|i| (Address::with_last_byte(i), Account::default())).collect()
+    }
+
+    fn balance_change(balance: u64) -> StateOverride {
+        StateOverride::from_iter([(
+            ALICE,
+            AccountOverride { balance: Some(U256::from(balance)), ..Default::default() },
+        )])
+    }
+
+    /// Pending state of blocks 1 and 2, with two transactions and a flashblock crediting Alice in
+    /// each block.
+    fn pending_blocks() -> PendingBlocks {
+        let mut builder = PendingBlocksBuilder::new();
+        for block_number in [1, 2] {
+            builder.with_header(Sealed::new_unchecked(
+                Header { number: block_number, ..Default::default() },
+                B256::with_last_byte(block_number as u8),
+            ));
+            builder.with_flashblocks([flashblock(block_number)]);
+            for index in 0..2 {
+                let transaction = transaction(block_number, index);
+                builder.with_transaction_state(transaction.tx_hash(), transaction_state());
+                builder.with_transaction(transaction);
+            }
+            builder.with_flashblock_snapshot(Arc::new(FlashblockSnapshot::new(
+                block_number,
+                0,
+                2,
+                balance_change(block_number * 100),
+            )));
+        }
+        builder.build().expect("pending blocks should build")
+    }
+
+    #[test]
+    fn test_latest_flashblock_index_of_each_block() {
+        let pending_blocks = pending_blocks();
+
+        assert_eq!(pending_blocks.get_latest_flashblock_index(1), Some(0));
+        assert_eq!(pending_blocks.get_latest_flashblock_index(2), Some(0));
+        assert_eq!(pending_blocks.get_latest_flashblock_index(3), None);
+    }
+
+    #[test]
+    fn test_state_overrides_before_block() {
+        let mut pending_blocks = pending_blocks();
+
+        assert!(pending_blocks.get_state_overrides_before_block(1).is_empty());
+        assert_eq!(pending_blocks.get_state_overrides_before_block(2), balance_change(100));
+        assert_eq!(pending_blocks.get_state_overrides_before_block(3), balance_change(200));
+
+        // State changes of pruned blocks still count towards later blocks
+        pending_blocks.prune_to_memory_limit(0);
+        assert_eq!(pending_blocks.get_state_overrides_before_block(2), balance_change(100));
+    }
+
+    #[test]
+    fn test_prune_drops_oldest_transaction_states_first() {
+        let mut pending_blocks = pending_blocks();
+        let usage = pending_blocks.memory_usage().total();
+        let state_size = memory::evm_state_size(&transaction_state());
+
+        let stats = pending_blocks.prune_to_memory_limit(usage - state_size);
+
+        assert_eq!(stats, PruneStats { transaction_states: 1, blocks: 0 });
+        assert!(pending_blocks.get_transaction_state(&transaction(1, 0).tx_hash()).is_none());
+        for (block_number, index) in [(1, 1), (2, 0), (2, 1)] {
+            let tx_hash = transaction(block_number, index).tx_hash();
+            assert!(pending_blocks.get_transaction_state(&tx_hash).is_some());
+        }
+        assert!(pending_blocks.memory_usage().total() <= usage - state_size);
+    }
+
+    #[test]
+    fn test_prune_drops_older_blocks_once_transaction_states_are_gone() {
+        let mut pending_blocks = pending_blocks();
+        let state_size = memory::evm_state_size(&transaction_state());
+        let max_bytes = pending_blocks.memory_usage().total() - 4 * state_size - 1;
+
+        let stats = pending_blocks.prune_to_memory_limit(max_bytes);
+
+        assert_eq!(stats, PruneStats { transaction_states: 4, blocks: 1 });
+        assert!(pending_blocks.memory_usage().total() <= max_bytes);
+        assert!(pending_blocks.get_transactions_for_block(1).is_empty());
+        assert_eq!(pending_blocks.get_transactions_for_block(2).len(), 2);
+        assert!(pending_blocks.get_flashblock_snapshot(1, 0).is_none());
+        assert!(pending_blocks.get_flashblock_snapshot(2, 0).is_some());
+    }
+
+    #[test]
+    fn test_prune_keeps_the_latest_block() {
+        let mut pending_blocks = pending_blocks();
+
+        let stats = pending_blocks.prune_to_memory_limit(0);
+
+        assert_eq!(stats, PruneStats { transaction_states: 4, blocks: 1 });
+        assert_eq!(pending_blocks.get_transactions_for_block(2).len(), 2);
+        assert!(pending_blocks.get_pruned_block(2).is_none());
+    }
+
+    #[test]
+    fn test_pruned_block_is_carried_over_to_the_next_pending_state() {
+        let mut pending_blocks = pending_blocks();
+        pending_blocks.prune_to_memory_limit(0);
+
+        let pruned_block = pending_blocks.get_pruned_block(1).expect("block 1 should be pruned");
+        assert_eq!(
+            pruned_block.transaction_hashes,
+            vec![transaction(1, 0).tx_hash(), transaction(1, 1).tx_hash()]
+        );
+
+        // The next pending state carries the pruned block over instead of re-executing it, like
+        // the processor does for the blocks of the previous pending state
+        let mut builder = PendingBlocksBuilder::new();
+        builder.with_account_states(&pending_blocks);
+        for block_number in [1, 2] {
+            builder.with_header(pending_blocks.get_header(block_number).unwrap());
+            builder.with_flashblocks([flashblock(block_number)]);
+        }
+        builder.with_pruned_block(1, pruned_block);
+        builder.with_flashblock_snapshot(pending_blocks.get_flashblock_snapshot(2, 0).unwrap());
+        let next = builder.build().expect("pending blocks should build");
+
+        assert_eq!(
+            next.get_transaction_hashes_for_block(1),
+            pending_blocks.get_transaction_hashes_for_block(1)
+        );
+        // State changes of the pruned block remain part of the state as of later flashblocks
+        assert_eq!(
+            next.get_state_overrides_at_flashblock(2, 0),
+            pending_blocks.get_state_overrides_at_flashblock(2, 0)
+        );
+        assert_eq!(next.get_balance_at_flashblock(ALICE, 2, 0), Some(U256::from(200)));
+    }
 }
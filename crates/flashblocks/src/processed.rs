@@ -0,0 +1,28 @@
+//! Flashblocks received from upstream, with how the state processor handled them.
+
+use base_flashtypes::Flashblock;
+use serde::{Deserialize, Serialize};
+
+/// How the state processor handled a received flashblock.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "camelCase")]
+pub enum FlashblockStatus {
+    /// Applied to the pending state.
+    Accepted,
+    /// Already applied to the pending state, so it was ignored.
+    Duplicate,
+    /// Not applied to the pending state.
+    Rejected {
+        /// Why the flashblock was rejected.
+        reason: String,
+    },
+}
+
+/// A flashblock as received from upstream, with how it was processed.
+#[derive(Debug, Clone)]
+pub struct ProcessedFlashblock {
+    /// The flashblock, as sent by the builder.
+    pub flashblock: Flashblock,
+    /// How the flashblock was processed.
+    pub status: FlashblockStatus,
+}
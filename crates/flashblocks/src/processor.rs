@@ -7,7 +7,7 @@ use alloy_consensus::{
     transaction::{Recovered, SignerRecoverable},
 };
 use alloy_eips::BlockNumberOrTag;
-use alloy_primitives::{Address, B256, BlockNumber, Bytes};
+use alloy_primitives::{Address, B256, BlockNumber, Bytes, map::foldhash::HashSet};
 use alloy_rpc_types::Withdrawal;
 use alloy_rpc_types_engine::{ExecutionPayloadV1, ExecutionPayloadV2, ExecutionPayloadV3};
 use alloy_rpc_types_eth::state::StateOverride;
@@ -19,7 +19,7 @@ use op_alloy_network::TransactionResponse;
 use rayon::prelude::*;
 use reth::{
     chainspec::{ChainSpecProvider, EthChainSpec},
-    providers::{BlockReaderIdExt, StateProviderFactory},
+    providers::{BlockNumReader, BlockReaderIdExt, StateProviderFactory},
     revm::{State, database::StateProviderDatabase, db::CacheDB},
 };
 use reth_evm::ConfigureEvm;
@@ -30,7 +30,11 @@ use reth_primitives::RecoveredBlock;
 use tokio::sync::{Mutex, broadcast::Sender, mpsc::UnboundedReceiver};
 
 use crate::{
-    Metrics, PendingBlocks, PendingBlocksBuilder, PendingStateBuilder,
+    CanonicalBlock, FlashblockHistory, FlashblockSnapshot, FlashblockStatus, Inclusion,
+    InclusionWaiters, Invalidation, InvalidationReason, Metrics, PendingBlocks,
+    PendingBlocksBuilder, PendingStateBuilder, ProcessedFlashblock,
+    memory::PruneStats,
+    metrics::{FlashblockLatencies, FlashblockTimings},
     validation::{
         CanonicalBlockReconciler, FlashblockSequenceValidator, ReconciliationStrategy,
         ReorgDetector, SequenceValidationResult,
@@ -43,7 +47,29 @@ pub enum StateUpdate {
     /// New canonical block to reconcile against pending state.
     Canonical(RecoveredBlock<OpBlock>),
     /// Incoming flashblock payload to extend pending state.
-    Flashblock(Flashblock),
+    Flashblock {
+        /// The flashblock payload.
+        flashblock: Flashblock,
+        /// Local time at which the flashblock was received from upstream.
+        received_at: Instant,
+    },
+}
+
+/// Where the state processor publishes the updates of the pending state to subscribers.
+#[derive(Debug, Clone)]
+pub struct StateNotifiers {
+    /// Sends every new pending state built from a flashblock.
+    pub flashblocks: Sender<Arc<PendingBlocks>>,
+    /// Sends the preconfirmed transactions dropped from the pending state.
+    pub invalidations: Sender<Arc<Invalidation>>,
+    /// Sends the canonical blocks reconciled against the pending state.
+    pub canonical_blocks: Sender<Arc<CanonicalBlock>>,
+    /// Sends every flashblock received from upstream, with how it was processed.
+    pub processed_flashblocks: Sender<Arc<ProcessedFlashblock>>,
+    /// Callers waiting for transactions to be included.
+    pub inclusion_waiters: Arc<InclusionWaiters>,
+    /// The most recent flashblocks, kept for replay to subscribers.
+    pub history: Arc<FlashblockHistory>,
 }
 
 /// Processes flashblocks and canonical blocks to keep pending state updated.
@@ -52,9 +78,11 @@ pub struct StateProcessor<Client> {
     rx: Arc<Mutex<UnboundedReceiver<StateUpdate>>>,
     pending_blocks: Arc<ArcSwapOption<PendingBlocks>>,
     max_depth: u64,
+    max_memory_bytes: Option<usize>,
     metrics: Metrics,
+    latencies: FlashblockLatencies,
     client: Client,
-    sender: Sender<Arc<PendingBlocks>>,
+    notifiers: StateNotifiers,
 }
 
 impl<Client> StateProcessor<Client>
@@ -70,10 +98,20 @@ where
         client: Client,
         pending_blocks: Arc<ArcSwapOption<PendingBlocks>>,
         max_depth: u64,
+        max_memory_bytes: Option<usize>,
         rx: Arc<Mutex<UnboundedReceiver<StateUpdate>>>,
-        sender: Sender<Arc<PendingBlocks>>,
+        notifiers: StateNotifiers,
     ) -> Self {
-        Self { metrics: Metrics::default(), pending_blocks, client, max_depth, rx, sender }
+        Self {
+            metrics: Metrics::default(),
+            latencies: FlashblockLatencies::default(),
+            pending_blocks,
+            client,
+            max_depth,
+            max_memory_bytes,
+            rx,
+            notifiers,
+        }
     }
 
     /// Processes updates from the queue until the channel closes.
@@ -83,6 +121,18 @@ where
             match update {
                 StateUpdate::Canonical(block) => {
                     debug!(message = "processing canonical block", block_number = block.number);
+                    let canonical_block = Arc::new(CanonicalBlock {
+                        number: block.number,
+                        transactions: block.body().transactions().map(|tx| tx.tx_hash()).collect(),
+                    });
+                    self.notifiers
+                        .inclusion_waiters
+                        .notify(canonical_block.transactions.iter().copied(), |_| {
+                            Some(Inclusion::Canonical(block.number))
+                        });
+                    // Sent before the pending state is updated, so subscribers receive it before
+                    // the flashblocks that follow
+                    _ = self.notifiers.canonical_blocks.send(canonical_block);
                     match self.process_canonical_block(prev_pending_blocks, &block) {
                         Ok(new_pending_blocks) => {
                             self.pending_blocks.swap(new_pending_blocks);
@@ -92,39 +142,154 @@ where
                         }
                     }
                 }
-                StateUpdate::Flashblock(flashblock) => {
+                StateUpdate::Flashblock { flashblock, received_at } => {
                     let start_time = Instant::now();
+                    let mut timings = FlashblockTimings::dequeued(flashblock.index, received_at);
                     debug!(
                         message = "processing flashblock",
                         block_number = flashblock.metadata.block_number,
                         flashblock_index = flashblock.index
                     );
-                    match self.process_flashblock(prev_pending_blocks, flashblock) {
+                    // The flashblock is only kept for subscribers to processed flashblocks
+                    let received = (self.notifiers.processed_flashblocks.receiver_count() > 0)
+                        .then(|| flashblock.clone());
+                    let mut status = FlashblockStatus::Accepted;
+                    match self.process_flashblock(
+                        prev_pending_blocks.clone(),
+                        flashblock,
+                        &mut timings.execution_started_at,
+                        &mut status,
+                    ) {
                         Ok(new_pending_blocks) => {
-                            if new_pending_blocks.is_some() {
-                                _ = self.sender.send(new_pending_blocks.clone().unwrap())
+                            // The pending state is only cleared by a flashblock that does not
+                            // follow the previous ones
+                            if let Some(prev_pending_blocks) = &prev_pending_blocks
+                                && new_pending_blocks.is_none()
+                            {
+                                self.notify_invalidated(
+                                    prev_pending_blocks,
+                                    None,
+                                    None,
+                                    InvalidationReason::SequenceGap,
+                                );
+                            }
+                            // Duplicate flashblocks leave the pending state unchanged
+                            let updated_timestamp = new_pending_blocks
+                                .as_ref()
+                                .filter(|pending_blocks| {
+                                    !prev_pending_blocks
+                                        .as_ref()
+                                        .is_some_and(|prev| Arc::ptr_eq(prev, pending_blocks))
+                                })
+                                .map(|pending_blocks| pending_blocks.latest_header().timestamp);
+                            if let Some(pending_blocks) = &new_pending_blocks {
+                                self.notify_preconfirmed(
+                                    prev_pending_blocks.as_deref(),
+                                    pending_blocks,
+                                );
+                                if updated_timestamp.is_some() {
+                                    self.notifiers.history.push(pending_blocks);
+                                }
+                                _ = self.notifiers.flashblocks.send(pending_blocks.clone())
                             }
 
                             self.pending_blocks.swap(new_pending_blocks);
                             self.metrics.block_processing_duration.record(start_time.elapsed());
+                            if let Some(block_timestamp) = updated_timestamp {
+                                timings.record_swapped(&self.latencies, block_timestamp);
+                            }
                         }
                         Err(e) => {
                             error!(message = "could not process Flashblock", error = %e);
                             self.metrics.block_processing_error.increment(1);
+                            status = FlashblockStatus::Rejected { reason: e.to_string() };
                         }
                     }
+                    if let Some(flashblock) = received {
+                        _ = self
+                            .notifiers
+                            .processed_flashblocks
+                            .send(Arc::new(ProcessedFlashblock { flashblock, status }));
+                    }
                 }
             }
         }
     }
 
+    /// Notifies inclusion waiters of the transactions included since the previous pending state.
+    fn notify_preconfirmed(
+        &self,
+        prev_pending_blocks: Option<&PendingBlocks>,
+        pending_blocks: &PendingBlocks,
+    ) {
+        if self.notifiers.inclusion_waiters.is_empty() {
+            return;
+        }
+
+        self.notifiers.inclusion_waiters.notify(
+            pending_blocks.get_pending_transaction_hashes(),
+            |hash| {
+                if prev_pending_blocks.is_some_and(|prev| prev.has_executed_transaction(&hash)) {
+                    return None;
+                }
+                pending_blocks
+                    .get_receipt(hash)
+                    .map(|receipt| Inclusion::Preconfirmed(Box::new(receipt)))
+            },
+        );
+    }
+
+    /// Notifies subscribers of the transactions of the previous pending state that are neither
+    /// in the canonical block nor in the new pending state. Only blocks from the canonical block
+    /// on are considered, as older pending blocks were reconciled by earlier canonical blocks.
+    fn notify_invalidated(
+        &self,
+        prev_pending_blocks: &Arc<PendingBlocks>,
+        new_pending_blocks: Option<&PendingBlocks>,
+        canonical_block: Option<(BlockNumber, &[B256])>,
+        reason: InvalidationReason,
+    ) {
+        let from_block_number = match canonical_block {
+            Some((block_number, _)) => block_number,
+            None => self.client.best_block_number().map_or(0, |number| number + 1),
+        };
+        let from_block_number = from_block_number.max(prev_pending_blocks.earliest_block_number());
+
+        let mut kept: HashSet<B256> =
+            canonical_block.map(|(_, hashes)| hashes.iter().copied().collect()).unwrap_or_default();
+        if let Some(new_pending_blocks) = new_pending_blocks {
+            kept.extend(transaction_hashes_from(new_pending_blocks, from_block_number));
+        }
+
+        let transactions: Vec<B256> =
+            transaction_hashes_from(prev_pending_blocks, from_block_number)
+                .filter(|hash| !kept.contains(hash))
+                .collect();
+        if transactions.is_empty() {
+            return;
+        }
+
+        warn!(
+            message = "preconfirmed transactions dropped from pending state",
+            reason = ?reason,
+            dropped_transactions = transactions.len(),
+        );
+        self.metrics.invalidated_transactions.increment(transactions.len() as u64);
+        _ = self.notifiers.invalidations.send(Arc::new(Invalidation {
+            reason,
+            canonical_block_number: canonical_block.map(|(block_number, _)| block_number),
+            transactions,
+            pending_blocks: prev_pending_blocks.clone(),
+        }));
+    }
+
     fn process_canonical_block(
         &self,
         prev_pending_blocks: Option<Arc<PendingBlocks>>,
         block: &RecoveredBlock<OpBlock>,
     ) -> eyre::Result<Option<Arc<PendingBlocks>>> {
         let pending_blocks = match &prev_pending_blocks {
-            Some(pb) => pb,
+            Some(pb) => pb.clone(),
             None => {
                 debug!(message = "no pending state to update with canonical block, skipping");
                 return Ok(None);
@@ -138,8 +303,7 @@ where
         self.metrics.pending_snapshot_height.set(pending_blocks.latest_block_number() as f64);
 
         // Check for reorg by comparing transaction sets
-        let tracked_txns = pending_blocks.get_transactions_for_block(block.number);
-        let tracked_txn_hashes: Vec<_> = tracked_txns.iter().map(|tx| tx.tx_hash()).collect();
+        let tracked_txn_hashes = pending_blocks.get_transaction_hashes_for_block(block.number);
         let block_txn_hashes: Vec<_> = block.body().transactions().map(|tx| tx.tx_hash()).collect();
 
         let reorg_result = ReorgDetector::detect(&tracked_txn_hashes, &block_txn_hashes);
@@ -154,7 +318,7 @@ where
             reorg_detected,
         );
 
-        match strategy {
+        let new_pending_blocks = match strategy {
             ReconciliationStrategy::CatchUp => {
                 debug!(
                     message = "pending snapshot cleared because canonical caught up",
@@ -177,7 +341,7 @@ where
 
                 // If there is a reorg, we re-process all future flashblocks without reusing the existing pending state
                 flashblocks.retain(|flashblock| flashblock.metadata.block_number > block.number);
-                self.build_pending_state(None, &flashblocks)
+                self.build_pending_state(None, &flashblocks, &mut None)
             }
             ReconciliationStrategy::DepthLimitExceeded { depth, max_depth } => {
                 debug!(
@@ -187,7 +351,7 @@ where
                 );
 
                 flashblocks.retain(|flashblock| flashblock.metadata.block_number > block.number);
-                self.build_pending_state(None, &flashblocks)
+                self.build_pending_state(None, &flashblocks, &mut None)
             }
             ReconciliationStrategy::Continue => {
                 debug!(
@@ -200,28 +364,49 @@ where
                 );
                 // If no reorg, we can continue building on top of the existing pending state
                 // NOTE: We do not retain specific flashblocks here to avoid losing track of our "earliest" pending block number
-                self.build_pending_state(prev_pending_blocks, &flashblocks)
+                self.build_pending_state(prev_pending_blocks, &flashblocks, &mut None)
             }
             ReconciliationStrategy::NoPendingState => {
                 // This case is already handled above, but included for completeness
                 debug!(message = "no pending state to update with canonical block, skipping");
                 Ok(None)
             }
-        }
+        }?;
+
+        let reason = match strategy {
+            ReconciliationStrategy::HandleReorg => InvalidationReason::Reorg,
+            _ => InvalidationReason::CanonicalMismatch,
+        };
+        self.notify_invalidated(
+            &pending_blocks,
+            new_pending_blocks.as_deref(),
+            Some((block.number, block_txn_hashes.as_slice())),
+            reason,
+        );
+
+        Ok(new_pending_blocks)
     }
 
     fn process_flashblock(
         &self,
         prev_pending_blocks: Option<Arc<PendingBlocks>>,
         flashblock: Flashblock,
+        execution_started_at: &mut Option<Instant>,
+        status: &mut FlashblockStatus,
     ) -> eyre::Result<Option<Arc<PendingBlocks>>> {
         let pending_blocks = match &prev_pending_blocks {
             Some(pb) => pb,
             None => {
                 if flashblock.index == 0 {
-                    return self.build_pending_state(None, &vec![flashblock]);
+                    return self.build_pending_state(None, &vec![flashblock], execution_started_at);
                 } else {
                     info!(message = "waiting for first Flashblock");
+                    *status = FlashblockStatus::Rejected {
+                        reason: format!(
+                            "waiting for the base flashblock of a block, received flashblock {}",
+                            flashblock.index
+                        ),
+                    };
                     return Ok(None);
                 }
             }
@@ -241,7 +426,7 @@ where
                 // or the first flashblock for the next block
                 let mut flashblocks = pending_blocks.get_flashblocks();
                 flashblocks.push(flashblock);
-                self.build_pending_state(prev_pending_blocks, &flashblocks)
+                self.build_pending_state(prev_pending_blocks, &flashblocks, execution_started_at)
             }
             SequenceValidationResult::Duplicate => {
                 // We have received a duplicate flashblock for the current block
@@ -251,9 +436,10 @@ where
                     curr_block = %pending_blocks.latest_block_number(),
                     flashblock_index = %flashblock.index,
                 );
+                *status = FlashblockStatus::Duplicate;
                 Ok(prev_pending_blocks)
             }
-            SequenceValidationResult::InvalidNewBlockIndex { block_number, index: _ } => {
+            SequenceValidationResult::InvalidNewBlockIndex { block_number, index } => {
                 // We have received a non-zero flashblock for a new block
                 self.metrics.unexpected_block_order.increment(1);
                 error!(
@@ -261,9 +447,14 @@ where
                     curr_block = %pending_blocks.latest_block_number(),
                     new_block = %block_number,
                 );
+                *status = FlashblockStatus::Rejected {
+                    reason: format!(
+                        "received flashblock {index} of new block {block_number} without its base flashblock"
+                    ),
+                };
                 Ok(None)
             }
-            SequenceValidationResult::NonSequentialGap { expected: _, actual: _ } => {
+            SequenceValidationResult::NonSequentialGap { expected, actual } => {
                 // We have received a non-sequential Flashblock for the current block
                 self.metrics.unexpected_block_order.increment(1);
                 error!(
@@ -271,6 +462,12 @@ where
                     curr_block = %pending_blocks.latest_block_number(),
                     new_block = %flashblock.metadata.block_number,
                 );
+                *status = FlashblockStatus::Rejected {
+                    reason: format!(
+                        "expected flashblock {expected} of block {}, received flashblock {actual}",
+                        flashblock.metadata.block_number
+                    ),
+                };
                 Ok(None)
             }
         }
@@ -280,6 +477,7 @@ where
         &self,
         prev_pending_blocks: Option<Arc<PendingBlocks>>,
         flashblocks: &Vec<Flashblock>,
+        execution_started_at: &mut Option<Instant>,
     ) -> eyre::Result<Option<Arc<PendingBlocks>>> {
         // BTreeMap guarantees ascending order of keys while iterating
         let mut flashblocks_per_block = BTreeMap::<BlockNumber, Vec<&Flashblock>>::new();
@@ -296,6 +494,8 @@ where
             "Failed to extract header for canonical block number {}. This can be ignored if the node has recently restarted, restored from a snapshot or is still syncing.",
             canonical_block
         ))?;
+        // The block after the latest pending one is expected after the same interval
+        let mut block_time = 0;
 
         let evm_config = OpEvmConfig::optimism(self.client.chain_spec());
         let state_provider =
@@ -303,6 +503,10 @@ where
         let state_provider_db = StateProviderDatabase::new(state_provider);
         let state = State::builder().with_database(state_provider_db).with_bundle_update().build();
         let mut pending_blocks_builder = PendingBlocksBuilder::new();
+        if let Some(pending_blocks) = &prev_pending_blocks {
+            // Accounts touched by transactions whose state diffs were pruned are carried over
+            pending_blocks_builder.with_account_states(pending_blocks);
+        }
 
         let mut db = match &prev_pending_blocks {
             Some(pending_blocks) => CacheDB { cache: pending_blocks.get_db_cache(), db: state },
@@ -314,7 +518,7 @@ where
                 pending_blocks.get_state_overrides().unwrap_or_default()
             });
 
-        for (_block_number, flashblocks) in flashblocks_per_block {
+        for (block_number, flashblocks) in flashblocks_per_block {
             let base = flashblocks
                 .first()
                 .ok_or(eyre!("cannot build a pending block from no flashblocks"))?
@@ -337,6 +541,17 @@ where
                 .flat_map(|flashblock| flashblock.diff.withdrawals.clone())
                 .collect();
 
+            // Number of transactions in the block once each flashblock has been applied
+            let mut flashblock_boundaries = flashblocks
+                .iter()
+                .scan(0, |end, flashblock| {
+                    *end += flashblock.diff.transactions.len();
+                    Some((flashblock.index, *end))
+                })
+                .collect::<Vec<_>>()
+                .into_iter()
+                .peekable();
+
             pending_blocks_builder.with_flashblocks(
                 flashblocks.iter().map(|&x| x.clone()).collect::<Vec<Flashblock>>(),
             );
@@ -365,12 +580,26 @@ where
                 },
             };
 
-            let block: OpBlock = execution_payload.try_into_block()?;
+            let mut block: OpBlock = execution_payload.try_into_block()?;
+            // The payload does not carry the parent beacon block root, which is needed to replay
+            // the block's pre-execution changes
+            block.header.parent_beacon_block_root = Some(base.parent_beacon_block_root);
             let l1_block_info = reth_optimism_evm::extract_l1_info(&block.body)?;
             let block_header = block.header.clone(); // prevents us from needing to clone the entire block
-            let sealed_header = block_header.clone().seal(B256::ZERO); // zero block hash for flashblocks
+            let sealed_header = block_header.clone().seal(latest_flashblock.diff.block_hash);
             pending_blocks_builder.with_header(sealed_header);
 
+            // Transactions of pruned blocks are already reflected in the cached state, so only the
+            // flashblocks and header are carried over
+            if let Some(pruned_block) =
+                prev_pending_blocks.as_ref().and_then(|p| p.get_pruned_block(block_number))
+            {
+                pending_blocks_builder.with_pruned_block(block_number, pruned_block);
+                block_time = block_header.timestamp.saturating_sub(last_block_header.timestamp);
+                last_block_header = block_header;
+                continue;
+            }
+
             let block_env_attributes = OpNextBlockEnvAttributes {
                 timestamp: base.timestamp,
                 suggested_fee_recipient: base.fee_recipient,
@@ -381,7 +610,7 @@ where
             };
 
             let evm_env = evm_config.next_evm_env(&last_block_header, &block_env_attributes)?;
-            let evm = evm_config.evm_with_env(db, evm_env);
+            execution_started_at.get_or_insert_with(Instant::now);
 
             // Parallel sender recovery - batch all ECDSA operations upfront
             let recovery_start = Instant::now();
@@ -404,45 +633,156 @@ where
                 .collect::<eyre::Result<_>>()?;
             self.metrics.sender_recovery_duration.record(recovery_start.elapsed());
 
+            // Speculatively execute transactions that were not executed in a previous flashblock
+            // against the state as of the start of this block, warming the cache along the way.
+            #[cfg(feature = "parallel-execution")]
+            let mut speculation = {
+                let speculation_start = Instant::now();
+                let executions = crate::parallel::speculate(
+                    &evm_config,
+                    &evm_env,
+                    &db.cache,
+                    &txs_with_senders,
+                    |tx_hash| {
+                        prev_pending_blocks
+                            .as_ref()
+                            .is_some_and(|p| p.has_executed_transaction(tx_hash))
+                    },
+                    || {
+                        Ok(StateProviderDatabase::new(self.client.state_by_block_number_or_tag(
+                            BlockNumberOrTag::Number(canonical_block),
+                        )?))
+                    },
+                );
+                crate::parallel::warm_cache(&mut db.cache, &executions);
+                self.metrics.speculative_execution_duration.record(speculation_start.elapsed());
+                crate::parallel::SpeculationTracker::new(executions)
+            };
+
+            let evm = evm_config.evm_with_env(db, evm_env);
             let mut pending_state_builder = PendingStateBuilder::new(
                 self.client.chain_spec(),
                 evm,
                 block,
+                latest_flashblock.diff.block_hash,
                 prev_pending_blocks.clone(),
                 l1_block_info,
                 state_overrides,
                 *evm_config.block_executor_factory().receipt_builder(),
             );
 
+            // Snapshots are reused from the previous pending state for flashblocks that were already
+            // processed, as their transactions are not re-executed and record no state changes.
+            let mut snapshot_flashblocks =
+                |executed: usize,
+                 pending_blocks_builder: &mut PendingBlocksBuilder,
+                 state_changes: &mut StateOverride| {
+                    while let Some((index, _)) =
+                        flashblock_boundaries.next_if(|&(_, end)| end <= executed)
+                    {
+                        let state_changes = std::mem::take(state_changes);
+                        let snapshot = prev_pending_blocks
+                            .as_ref()
+                            .and_then(|p| p.get_flashblock_snapshot(block_number, index))
+                            .unwrap_or_else(|| {
+                                Arc::new(FlashblockSnapshot::new(
+                                    block_number,
+                                    index,
+                                    executed,
+                                    state_changes,
+                                ))
+                            });
+                        pending_blocks_builder.with_flashblock_snapshot(snapshot);
+                    }
+                };
+            snapshot_flashblocks(
+                0,
+                &mut pending_blocks_builder,
+                pending_state_builder.flashblock_changes_mut(),
+            );
+
             for (idx, (transaction, sender)) in txs_with_senders.into_iter().enumerate() {
                 let tx_hash = transaction.tx_hash();
 
                 pending_blocks_builder.with_transaction_sender(tx_hash, sender);
-                pending_blocks_builder.increment_nonce(sender);
 
                 let recovered_transaction = Recovered::new_unchecked(transaction, sender);
 
+                #[cfg(feature = "parallel-execution")]
+                let executed_transaction = pending_state_builder
+                    .execute_transaction_speculatively(
+                        idx,
+                        recovered_transaction,
+                        &mut speculation,
+                    )?;
+                #[cfg(not(feature = "parallel-execution"))]
                 let executed_transaction =
                     pending_state_builder.execute_transaction(idx, recovered_transaction)?;
 
-                for (address, account) in executed_transaction.state.iter() {
+                for (address, account) in executed_transaction.state.iter().flatten() {
                     if account.is_touched() {
                         pending_blocks_builder.with_account_balance(*address, account.info.balance);
+                        pending_blocks_builder.with_account_nonce(*address, account.info.nonce);
                     }
                 }
 
                 pending_blocks_builder.with_transaction(executed_transaction.rpc_transaction);
                 pending_blocks_builder.with_receipt(tx_hash, executed_transaction.receipt);
-                pending_blocks_builder.with_transaction_state(tx_hash, executed_transaction.state);
+                if let Some(state) = executed_transaction.state {
+                    pending_blocks_builder.with_transaction_state(tx_hash, state);
+                }
+
+                snapshot_flashblocks(
+                    idx + 1,
+                    &mut pending_blocks_builder,
+                    pending_state_builder.flashblock_changes_mut(),
+                );
+            }
+
+            #[cfg(feature = "parallel-execution")]
+            {
+                self.metrics.speculative_execution_reused.increment(speculation.reused());
+                self.metrics.speculative_execution_conflicts.increment(speculation.conflicts());
             }
 
             (db, state_overrides) = pending_state_builder.into_db_and_state_overrides();
+            block_time = block_header.timestamp.saturating_sub(last_block_header.timestamp);
             last_block_header = block_header;
         }
 
         pending_blocks_builder.with_state_overrides(state_overrides);
         pending_blocks_builder.with_db_cache(db.cache);
+        pending_blocks_builder.with_next_base_fee(
+            self.client
+                .chain_spec()
+                .next_block_base_fee(&last_block_header, last_block_header.timestamp + block_time),
+        );
+
+        let mut pending_blocks = pending_blocks_builder.build()?;
+        if let Some(max_memory_bytes) = self.max_memory_bytes {
+            let stats = pending_blocks.prune_to_memory_limit(max_memory_bytes);
+            if stats != PruneStats::default() {
+                warn!(
+                    message = "pruned pending state to stay within memory limit",
+                    max_memory_bytes,
+                    pruned_transaction_states = stats.transaction_states,
+                    pruned_blocks = stats.blocks,
+                );
+            }
+            self.metrics.pruned_transaction_states.increment(stats.transaction_states as u64);
+            self.metrics.pruned_pending_blocks.increment(stats.blocks as u64);
+        }
+        self.metrics.record_memory_usage(&pending_blocks.memory_usage());
 
-        Ok(Some(Arc::new(pending_blocks_builder.build()?)))
+        Ok(Some(Arc::new(pending_blocks)))
     }
 }
+
+/// Returns the hashes of the transactions of the pending blocks from the given block on.
+fn transaction_hashes_from(
+    pending_blocks: &PendingBlocks,
+    from_block_number: BlockNumber,
+) -> impl Iterator<Item = B256> + '_ {
+    (from_block_number..=pending_blocks.latest_block_number())
+        .flat_map(|block_number| pending_blocks.get_transaction_hashes_for_block(block_number))
+}
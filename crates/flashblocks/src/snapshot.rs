@@ -0,0 +1,69 @@
+//! Per-flashblock snapshots of pending state.
+
+use alloy_primitives::BlockNumber;
+use alloy_rpc_types_eth::state::StateOverride;
+
+/// Lightweight view of the pending state as of a single flashblock.
+///
+/// Snapshots only record the state changes made by the transactions of the flashblock and the
+/// number of transactions of the block executed up to and including it. The state as of the
+/// flashblock, transactions and receipts are resolved against the owning
+/// [`PendingBlocks`](crate::PendingBlocks).
+#[derive(Debug, Clone)]
+pub struct FlashblockSnapshot {
+    block_number: BlockNumber,
+    index: u64,
+    transaction_count: usize,
+    state_changes: StateOverride,
+}
+
+impl FlashblockSnapshot {
+    /// Creates a new snapshot for the given flashblock.
+    pub const fn new(
+        block_number: BlockNumber,
+        index: u64,
+        transaction_count: usize,
+        state_changes: StateOverride,
+    ) -> Self {
+        Self { block_number, index, transaction_count, state_changes }
+    }
+
+    /// Returns the block number of the flashblock.
+    pub const fn block_number(&self) -> BlockNumber {
+        self.block_number
+    }
+
+    /// Returns the index of the flashblock within its block.
+    pub const fn index(&self) -> u64 {
+        self.index
+    }
+
+    /// Returns the number of transactions of the block executed up to and including this
+    /// flashblock.
+    pub const fn transaction_count(&self) -> usize {
+        self.transaction_count
+    }
+
+    /// Returns the accounts changed by the transactions of this flashblock, with their state
+    /// after it was applied.
+    pub const fn state_changes(&self) -> &StateOverride {
+        &self.state_changes
+    }
+}
+
+/// Applies state changes on top of accumulated state overrides.
+pub(crate) fn merge_state_changes(state_overrides: &mut StateOverride, changes: &StateOverride) {
+    for (address, account) in changes {
+        let existing = state_overrides.entry(*address).or_default();
+        existing.balance = account.balance;
+        existing.nonce = account.nonce;
+        existing.code = account.code.clone();
+
+        if let Some(state_diff) = &account.state_diff {
+            existing
+                .state_diff
+                .get_or_insert(Default::default())
+                .extend(state_diff.iter().map(|(slot, value)| (*slot, *value)));
+        }
+    }
+}
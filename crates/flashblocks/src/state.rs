@@ -1,8 +1,9 @@
 //! Flashblocks state management.
 
-use std::sync::Arc;
+use std::{sync::Arc, time::Instant};
 
 use alloy_consensus::Header;
+use alloy_primitives::TxHash;
 use arc_swap::{ArcSwapOption, Guard};
 use base_flashtypes::Flashblock;
 use reth::{
@@ -12,26 +13,24 @@ use reth::{
 use reth_optimism_chainspec::OpHardforks;
 use reth_optimism_primitives::OpBlock;
 use reth_primitives::RecoveredBlock;
-use tokio::sync::{
-    Mutex,
-    broadcast::{self, Sender},
-    mpsc,
-};
+use tokio::sync::{Mutex, broadcast, mpsc};
 
 use crate::{
-    FlashblocksAPI, FlashblocksReceiver, PendingBlocks,
-    processor::{StateProcessor, StateUpdate},
+    CanonicalBlock, FlashblockHistory, FlashblocksAPI, FlashblocksReceiver, InclusionWaiter,
+    InclusionWaiters, Invalidation, PendingBlocks, ProcessedFlashblock,
+    processor::{StateNotifiers, StateProcessor, StateUpdate},
 };
 
-// Buffer 4s of flashblocks for flashblock_sender
-const BUFFER_SIZE: usize = 20;
+/// Default number of updates buffered for each subscriber to the pending state, about 4s of
+/// flashblocks.
+pub const DEFAULT_BROADCAST_BUFFER_SIZE: usize = 20;
 
 /// Manages the pending flashblock state and processes incoming updates.
 #[derive(Debug, Clone)]
 pub struct FlashblocksState<Client> {
     pending_blocks: Arc<ArcSwapOption<PendingBlocks>>,
     queue: mpsc::UnboundedSender<StateUpdate>,
-    flashblock_sender: Sender<Arc<PendingBlocks>>,
+    notifiers: StateNotifiers,
     state_processor: StateProcessor<Client>,
 }
 
@@ -44,19 +43,40 @@ where
         + 'static,
 {
     /// Creates a new flashblocks state manager.
-    pub fn new(client: Client, max_pending_blocks_depth: u64) -> Self {
+    ///
+    /// When `max_pending_blocks_memory` is set, pending state is pruned to stay within that many
+    /// bytes (approximately). Subscribers that fall more than `broadcast_buffer_size` updates
+    /// behind miss the oldest ones. The last `history_size` flashblocks are kept for subscribers
+    /// resuming from a cursor.
+    pub fn new(
+        client: Client,
+        max_pending_blocks_depth: u64,
+        max_pending_blocks_memory: Option<usize>,
+        broadcast_buffer_size: usize,
+        history_size: usize,
+    ) -> Self {
         let (tx, rx) = mpsc::unbounded_channel::<StateUpdate>();
         let pending_blocks: Arc<ArcSwapOption<PendingBlocks>> = Arc::new(ArcSwapOption::new(None));
-        let (flashblock_sender, _) = broadcast::channel(BUFFER_SIZE);
+        // Broadcast channels need room for at least one update
+        let broadcast_buffer_size = broadcast_buffer_size.max(1);
+        let notifiers = StateNotifiers {
+            flashblocks: broadcast::channel(broadcast_buffer_size).0,
+            invalidations: broadcast::channel(broadcast_buffer_size).0,
+            canonical_blocks: broadcast::channel(broadcast_buffer_size).0,
+            processed_flashblocks: broadcast::channel(broadcast_buffer_size).0,
+            inclusion_waiters: Arc::new(InclusionWaiters::default()),
+            history: Arc::new(FlashblockHistory::new(history_size)),
+        };
         let state_processor = StateProcessor::new(
             client,
             pending_blocks.clone(),
             max_pending_blocks_depth,
+            max_pending_blocks_memory,
             Arc::new(Mutex::new(rx)),
-            flashblock_sender.clone(),
+            notifiers.clone(),
         );
 
-        Self { pending_blocks, queue: tx, flashblock_sender, state_processor }
+        Self { pending_blocks, queue: tx, notifiers, state_processor }
     }
 
     /// Starts the flashblocks state processor.
@@ -83,9 +103,14 @@ where
 
 impl<Client> FlashblocksReceiver for FlashblocksState<Client> {
     fn on_flashblock_received(&self, flashblock: Flashblock) {
+        // Flashblocks that did not come through the websocket subscriber are timed from here
+        self.on_flashblock_received_at(flashblock, Instant::now());
+    }
+
+    fn on_flashblock_received_at(&self, flashblock: Flashblock, received_at: Instant) {
         let flashblock_index = flashblock.index;
         let block_number = flashblock.metadata.block_number;
-        match self.queue.send(StateUpdate::Flashblock(flashblock)) {
+        match self.queue.send(StateUpdate::Flashblock { flashblock, received_at }) {
             Ok(_) => {
                 info!(
                     message = "added flashblock to processing queue",
@@ -105,6 +130,26 @@ impl<Client> FlashblocksAPI for FlashblocksState<Client> {
     }
 
     fn subscribe_to_flashblocks(&self) -> broadcast::Receiver<Arc<PendingBlocks>> {
-        self.flashblock_sender.subscribe()
+        self.notifiers.flashblocks.subscribe()
+    }
+
+    fn subscribe_to_invalidations(&self) -> broadcast::Receiver<Arc<Invalidation>> {
+        self.notifiers.invalidations.subscribe()
+    }
+
+    fn subscribe_to_canonical_blocks(&self) -> broadcast::Receiver<Arc<CanonicalBlock>> {
+        self.notifiers.canonical_blocks.subscribe()
+    }
+
+    fn subscribe_to_processed_flashblocks(&self) -> broadcast::Receiver<Arc<ProcessedFlashblock>> {
+        self.notifiers.processed_flashblocks.subscribe()
+    }
+
+    fn register_inclusion_waiter(&self, tx_hash: TxHash) -> Option<InclusionWaiter> {
+        self.notifiers.inclusion_waiters.register(tx_hash)
+    }
+
+    fn flashblock_history(&self) -> &FlashblockHistory {
+        &self.notifiers.history
     }
 }
@@ -9,9 +9,11 @@ use alloy_primitives::B256;
 use alloy_rpc_types::TransactionTrait;
 use alloy_rpc_types_eth::state::StateOverride;
 use eyre::eyre;
-use op_alloy_consensus::{OpDepositReceipt, OpTxEnvelope, OpTxReceipt};
+use op_alloy_consensus::{OpDepositReceipt, OpReceipt, OpTxEnvelope, OpTxReceipt};
 use op_alloy_rpc_types::{OpTransactionReceipt, Transaction};
-use reth::revm::{Database, DatabaseCommit, context::result::ResultAndState, state::EvmState};
+use reth::revm::{
+    Database, DatabaseCommit, bytecode::Bytecode, context::result::ResultAndState, state::EvmState,
+};
 use reth_evm::{
     Evm, FromRecoveredTx, eth::receipt_builder::ReceiptBuilderCtx, op_revm::L1BlockInfo,
 };
@@ -22,6 +24,8 @@ use reth_optimism_rpc::OpReceiptBuilder as OpRpcReceiptBuilder;
 use reth_rpc_convert::transaction::ConvertReceiptInput;
 
 use crate::PendingBlocks;
+#[cfg(feature = "parallel-execution")]
+use crate::parallel::SpeculationTracker;
 
 /// Represents the result of executing or fetching a cached pending transaction.
 #[derive(Debug, Clone)]
@@ -30,8 +34,8 @@ pub struct ExecutedPendingTransaction {
     pub rpc_transaction: Transaction,
     /// The receipt of the transaction.
     pub receipt: OpTransactionReceipt,
-    /// The updated EVM state.
-    pub state: EvmState,
+    /// The updated EVM state, or None if it was pruned from the previous pending state.
+    pub state: Option<EvmState>,
 }
 
 /// Executes or fetches cached values for transactions in a flashblock.
@@ -42,12 +46,15 @@ pub struct PendingStateBuilder<E, ChainSpec> {
 
     evm: E,
     pending_block: Block<OpTxEnvelope, Header>,
+    /// Hash the pending block is sealed with as of the latest flashblock.
+    block_hash: B256,
     l1_block_info: L1BlockInfo,
     chain_spec: ChainSpec,
     receipt_builder: OpRethReceiptBuilder,
 
     prev_pending_blocks: Option<Arc<PendingBlocks>>,
     state_overrides: StateOverride,
+    flashblock_changes: StateOverride,
 }
 
 impl<E, ChainSpec, DB> PendingStateBuilder<E, ChainSpec>
@@ -62,6 +69,7 @@ where
         chain_spec: ChainSpec,
         evm: E,
         pending_block: Block<OpTxEnvelope, Header>,
+        block_hash: B256,
         prev_pending_blocks: Option<Arc<PendingBlocks>>,
         l1_block_info: L1BlockInfo,
         state_overrides: StateOverride,
@@ -69,17 +77,29 @@ where
     ) -> Self {
         Self {
             pending_block,
+            block_hash,
             evm,
             cumulative_gas_used: 0,
             next_log_index: 0,
             prev_pending_blocks,
             l1_block_info,
             state_overrides,
+            flashblock_changes: StateOverride::default(),
             chain_spec,
             receipt_builder,
         }
     }
 
+    /// Returns the state overrides accumulated so far.
+    pub const fn state_overrides(&self) -> &StateOverride {
+        &self.state_overrides
+    }
+
+    /// Returns the state changes of transactions executed since the last flashblock snapshot.
+    pub(crate) const fn flashblock_changes_mut(&mut self) -> &mut StateOverride {
+        &mut self.flashblock_changes
+    }
+
     /// Consumes the builder and returns the database and state overrides.
     pub fn into_db_and_state_overrides(self) -> (DB, StateOverride) {
         (self.evm.into_db(), self.state_overrides)
@@ -94,23 +114,14 @@ where
     ) -> eyre::Result<ExecutedPendingTransaction> {
         let tx_hash = transaction.tx_hash();
 
-        let effective_gas_price = if transaction.is_deposit() {
-            0
-        } else {
-            self.pending_block
-                .base_fee_per_gas
-                .map(|base_fee| {
-                    transaction.effective_tip_per_gas(base_fee).unwrap_or_default()
-                        + base_fee as u128
-                })
-                .unwrap_or_else(|| transaction.max_fee_per_gas())
-        };
+        let effective_gas_price = self.effective_gas_price(&transaction);
 
-        // Check if we have all the data we need (receipt + state)
+        // Check if we have all the data we need (receipt + state). The state may have been
+        // pruned to save memory, in which case the transaction is still not re-executed as its
+        // effects are already part of the cached database state, and the state stays pruned.
         let cached_data = self.prev_pending_blocks.as_ref().and_then(|p| {
             let receipt = p.get_receipt(tx_hash)?;
-            let state = p.get_transaction_state(&tx_hash)?;
-            Some((receipt, state))
+            Some((receipt, p.get_transaction_state(&tx_hash)))
         });
 
         // If cached, we can fill out pending block data using previous execution results
@@ -122,12 +133,61 @@ where
         }
     }
 
+    /// Executes a single transaction, reusing its speculative result when it does not conflict with
+    /// transactions committed before it. Should be called in order for each transaction.
+    #[cfg(feature = "parallel-execution")]
+    pub(crate) fn execute_transaction_speculatively(
+        &mut self,
+        idx: usize,
+        transaction: Recovered<OpTxEnvelope>,
+        speculation: &mut SpeculationTracker<E::HaltReason>,
+    ) -> eyre::Result<ExecutedPendingTransaction> {
+        if self
+            .prev_pending_blocks
+            .as_ref()
+            .is_some_and(|p| p.has_executed_transaction(&transaction.tx_hash()))
+        {
+            return self.execute_transaction(idx, transaction);
+        }
+
+        let effective_gas_price = self.effective_gas_price(&transaction);
+        let executed = match speculation.take_valid(idx, self.evm.db_mut())? {
+            Some(result_and_state) => self.apply_execution_result(
+                transaction,
+                idx,
+                effective_gas_price,
+                result_and_state,
+            )?,
+            None => self.execute_with_evm(transaction, idx, effective_gas_price)?,
+        };
+        if let Some(state) = &executed.state {
+            speculation.record(state);
+        }
+
+        Ok(executed)
+    }
+
+    /// Returns the price per gas paid by the transaction in the pending block.
+    fn effective_gas_price(&self, transaction: &Recovered<OpTxEnvelope>) -> u128 {
+        if transaction.is_deposit() {
+            0
+        } else {
+            self.pending_block
+                .base_fee_per_gas
+                .map(|base_fee| {
+                    transaction.effective_tip_per_gas(base_fee).unwrap_or_default()
+                        + base_fee as u128
+                })
+                .unwrap_or_else(|| transaction.max_fee_per_gas())
+        }
+    }
+
     /// Builds transaction result from cached receipt and state data.
     fn execute_with_cached_data(
         &mut self,
         transaction: Recovered<OpTxEnvelope>,
         receipt: OpTransactionReceipt,
-        state: EvmState,
+        state: Option<EvmState>,
         idx: usize,
         effective_gas_price: u128,
     ) -> eyre::Result<ExecutedPendingTransaction> {
@@ -141,7 +201,7 @@ where
         let rpc_transaction = Transaction {
             inner: alloy_rpc_types_eth::Transaction {
                 inner: transaction,
-                block_hash: None,
+                block_hash: Some(self.block_hash),
                 block_number: Some(self.pending_block.number),
                 transaction_index: Some(idx as u64),
                 effective_gas_price: Some(effective_gas_price),
@@ -156,6 +216,9 @@ where
             .ok_or(eyre!("cumulative gas used overflow"))?;
         self.next_log_index += receipt.inner.logs().len();
 
+        // The block hash changes with every flashblock, so receipts of earlier ones are outdated
+        let receipt = with_block_hash(receipt, self.block_hash);
+
         Ok(ExecutedPendingTransaction { rpc_transaction, receipt, state })
     }
 
@@ -169,114 +232,8 @@ where
         let tx_hash = transaction.tx_hash();
 
         match self.evm.transact(&transaction) {
-            Ok(ResultAndState { state, result }) => {
-                let gas_used = result.gas_used();
-                for (addr, acc) in &state {
-                    let existing_override = self.state_overrides.entry(*addr).or_default();
-                    existing_override.balance = Some(acc.info.balance);
-                    existing_override.nonce = Some(acc.info.nonce);
-                    existing_override.code = acc.info.code.clone().map(|code| code.bytes());
-
-                    let existing = existing_override.state_diff.get_or_insert(Default::default());
-                    let changed_slots = acc
-                        .storage
-                        .iter()
-                        .map(|(&key, slot)| (B256::from(key), B256::from(slot.present_value)));
-
-                    existing.extend(changed_slots);
-                }
-
-                self.cumulative_gas_used = self
-                    .cumulative_gas_used
-                    .checked_add(gas_used)
-                    .ok_or(eyre!("cumulative gas used overflow"))?;
-
-                let is_canyon_active =
-                    self.chain_spec.is_canyon_active_at_timestamp(self.pending_block.timestamp);
-
-                let is_regolith_active =
-                    self.chain_spec.is_regolith_active_at_timestamp(self.pending_block.timestamp);
-
-                let receipt = match self.receipt_builder.build_receipt(ReceiptBuilderCtx {
-                    tx: &transaction,
-                    evm: &mut self.evm,
-                    result,
-                    state: &state,
-                    cumulative_gas_used: self.cumulative_gas_used,
-                }) {
-                    Ok(receipt) => receipt,
-                    Err(ctx) => {
-                        // This is a deposit transaction, so build the receipt from the context
-                        let receipt = alloy_consensus::Receipt {
-                            status: Eip658Value::Eip658(ctx.result.is_success()),
-                            cumulative_gas_used: ctx.cumulative_gas_used,
-                            logs: ctx.result.into_logs(),
-                        };
-
-                        let deposit_nonce = (is_regolith_active && transaction.is_deposit())
-                            .then(|| {
-                                self.evm
-                                    .db_mut()
-                                    .basic(transaction.signer())
-                                    .map(|acc| acc.unwrap_or_default().nonce)
-                            })
-                            .transpose()
-                            .map_err(|_| eyre!("failed to load cache account for depositor"))?;
-
-                        self.receipt_builder.build_deposit_receipt(OpDepositReceipt {
-                            inner: receipt,
-                            deposit_nonce,
-                            deposit_receipt_version: is_canyon_active.then_some(1),
-                        })
-                    }
-                };
-
-                let meta = TransactionMeta {
-                    tx_hash,
-                    index: idx as u64,
-                    block_hash: B256::ZERO, // block hash is not available yet for flashblocks
-                    block_number: self.pending_block.number,
-                    base_fee: self.pending_block.base_fee_per_gas,
-                    excess_blob_gas: self.pending_block.excess_blob_gas,
-                    timestamp: self.pending_block.timestamp,
-                };
-
-                let sender = transaction.signer();
-                let input: ConvertReceiptInput<'_, OpPrimitives> = ConvertReceiptInput {
-                    receipt: receipt.clone(),
-                    tx: Recovered::new_unchecked(&transaction, sender),
-                    gas_used,
-                    next_log_index: self.next_log_index,
-                    meta,
-                };
-
-                let op_receipt =
-                    OpRpcReceiptBuilder::new(&self.chain_spec, input, &mut self.l1_block_info)
-                        .unwrap()
-                        .build();
-                self.next_log_index += receipt.logs().len();
-
-                let (deposit_receipt_version, deposit_nonce) = if transaction.is_deposit() {
-                    let op_receipt_inner = &op_receipt.inner.inner.receipt;
-                    (op_receipt_inner.deposit_receipt_version(), op_receipt_inner.deposit_nonce())
-                } else {
-                    (None, None)
-                };
-
-                let rpc_transaction = Transaction {
-                    inner: alloy_rpc_types_eth::Transaction {
-                        inner: transaction,
-                        block_hash: None,
-                        block_number: Some(self.pending_block.number),
-                        transaction_index: Some(idx as u64),
-                        effective_gas_price: Some(effective_gas_price),
-                    },
-                    deposit_nonce,
-                    deposit_receipt_version,
-                };
-                self.evm.db_mut().commit(state.clone());
-
-                Ok(ExecutedPendingTransaction { rpc_transaction, receipt: op_receipt, state })
+            Ok(result_and_state) => {
+                self.apply_execution_result(transaction, idx, effective_gas_price, result_and_state)
             }
             Err(e) => Err(eyre!(
                 "failed to execute transaction: {:?} tx_hash: {:?} sender: {:?}",
@@ -286,4 +243,143 @@ where
             )),
         }
     }
+
+    /// Commits the result of executing a transaction and builds the pending transaction from it.
+    fn apply_execution_result(
+        &mut self,
+        transaction: Recovered<OpTxEnvelope>,
+        idx: usize,
+        effective_gas_price: u128,
+        ResultAndState { state, result }: ResultAndState<E::HaltReason>,
+    ) -> eyre::Result<ExecutedPendingTransaction> {
+        let tx_hash = transaction.tx_hash();
+        let gas_used = result.gas_used();
+        merge_state_overrides(&mut self.state_overrides, &state);
+        merge_state_overrides(&mut self.flashblock_changes, &state);
+
+        self.cumulative_gas_used = self
+            .cumulative_gas_used
+            .checked_add(gas_used)
+            .ok_or(eyre!("cumulative gas used overflow"))?;
+
+        let is_canyon_active =
+            self.chain_spec.is_canyon_active_at_timestamp(self.pending_block.timestamp);
+
+        let is_regolith_active =
+            self.chain_spec.is_regolith_active_at_timestamp(self.pending_block.timestamp);
+
+        let receipt = match self.receipt_builder.build_receipt(ReceiptBuilderCtx {
+            tx: &transaction,
+            evm: &mut self.evm,
+            result,
+            state: &state,
+            cumulative_gas_used: self.cumulative_gas_used,
+        }) {
+            Ok(receipt) => receipt,
+            Err(ctx) => {
+                // This is a deposit transaction, so build the receipt from the context
+                let receipt = alloy_consensus::Receipt {
+                    status: Eip658Value::Eip658(ctx.result.is_success()),
+                    cumulative_gas_used: ctx.cumulative_gas_used,
+                    logs: ctx.result.into_logs(),
+                };
+
+                let deposit_nonce = (is_regolith_active && transaction.is_deposit())
+                    .then(|| {
+                        self.evm
+                            .db_mut()
+                            .basic(transaction.signer())
+                            .map(|acc| acc.unwrap_or_default().nonce)
+                    })
+                    .transpose()
+                    .map_err(|_| eyre!("failed to load cache account for depositor"))?;
+
+                self.receipt_builder.build_deposit_receipt(OpDepositReceipt {
+                    inner: receipt,
+                    deposit_nonce,
+                    deposit_receipt_version: is_canyon_active.then_some(1),
+                })
+            }
+        };
+
+        let meta = TransactionMeta {
+            tx_hash,
+            index: idx as u64,
+            block_hash: self.block_hash,
+            block_number: self.pending_block.number,
+            base_fee: self.pending_block.base_fee_per_gas,
+            excess_blob_gas: self.pending_block.excess_blob_gas,
+            timestamp: self.pending_block.timestamp,
+        };
+
+        let sender = transaction.signer();
+        let input: ConvertReceiptInput<'_, OpPrimitives> = ConvertReceiptInput {
+            receipt: receipt.clone(),
+            tx: Recovered::new_unchecked(&transaction, sender),
+            gas_used,
+            next_log_index: self.next_log_index,
+            meta,
+        };
+
+        let op_receipt = OpRpcReceiptBuilder::new(&self.chain_spec, input, &mut self.l1_block_info)
+            .unwrap()
+            .build();
+        self.next_log_index += receipt.logs().len();
+
+        let (deposit_receipt_version, deposit_nonce) = if transaction.is_deposit() {
+            let op_receipt_inner = &op_receipt.inner.inner.receipt;
+            (op_receipt_inner.deposit_receipt_version(), op_receipt_inner.deposit_nonce())
+        } else {
+            (None, None)
+        };
+
+        let rpc_transaction = Transaction {
+            inner: alloy_rpc_types_eth::Transaction {
+                inner: transaction,
+                block_hash: Some(self.block_hash),
+                block_number: Some(self.pending_block.number),
+                transaction_index: Some(idx as u64),
+                effective_gas_price: Some(effective_gas_price),
+            },
+            deposit_nonce,
+            deposit_receipt_version,
+        };
+        self.evm.db_mut().commit(state.clone());
+
+        Ok(ExecutedPendingTransaction { rpc_transaction, receipt: op_receipt, state: Some(state) })
+    }
+}
+
+/// Sets the block hash of a receipt and of its logs.
+fn with_block_hash(mut receipt: OpTransactionReceipt, block_hash: B256) -> OpTransactionReceipt {
+    receipt.inner.block_hash = Some(block_hash);
+    let logs = match &mut receipt.inner.inner.receipt {
+        OpReceipt::Legacy(receipt)
+        | OpReceipt::Eip2930(receipt)
+        | OpReceipt::Eip1559(receipt)
+        | OpReceipt::Eip7702(receipt) => &mut receipt.logs,
+        OpReceipt::Deposit(receipt) => &mut receipt.inner.logs,
+    };
+    for log in logs {
+        log.block_hash = Some(block_hash);
+    }
+    receipt
+}
+
+/// Applies the state changes of an executed transaction to the accumulated state overrides.
+fn merge_state_overrides(state_overrides: &mut StateOverride, state: &EvmState) {
+    for (addr, acc) in state {
+        let existing_override = state_overrides.entry(*addr).or_default();
+        existing_override.balance = Some(acc.info.balance);
+        existing_override.nonce = Some(acc.info.nonce);
+        existing_override.code = acc.info.code.as_ref().map(Bytecode::original_bytes);
+
+        let existing = existing_override.state_diff.get_or_insert(Default::default());
+        let changed_slots = acc
+            .storage
+            .iter()
+            .map(|(&key, slot)| (B256::from(key), B256::from(slot.present_value)));
+
+        existing.extend(changed_slots);
+    }
 }
@@ -1,6 +1,9 @@
 //! WebSocket subscription handling for flashblocks.
 
-use std::{sync::Arc, time::Duration};
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use base_flashtypes::Flashblock;
 use futures_util::{SinkExt as _, StreamExt};
@@ -13,7 +16,7 @@ use crate::{FlashblocksReceiver, Metrics};
 // Simplify actor messages to just handle shutdown
 #[derive(Debug)]
 enum ActorMessage {
-    BestPayload { payload: Flashblock },
+    BestPayload { payload: Flashblock, received_at: Instant },
 }
 
 /// Subscribes to flashblocks via WebSocket and forwards them to the receiver.
@@ -68,12 +71,13 @@ where
                         'conn: loop {
                             tokio::select! {
                                 Some(msg) = read.next() => {
+                                    let received_at = Instant::now();
                                     metrics.upstream_messages.increment(1);
 
                                     match msg {
                                         Ok(Message::Binary(bytes)) => match Flashblock::try_decode_message(bytes) {
                                             Ok(payload) => {
-                                                let _ = sender.send(ActorMessage::BestPayload { payload: payload.clone() }).await.map_err(|e| {
+                                                let _ = sender.send(ActorMessage::BestPayload { payload: payload.clone(), received_at }).await.map_err(|e| {
                                                     error!(message = "Failed to publish message to channel", error = %e);
                                                 });
                                             }
@@ -160,8 +164,8 @@ where
         tokio::spawn(async move {
             while let Some(message) = mailbox.recv().await {
                 match message {
-                    ActorMessage::BestPayload { payload } => {
-                        flashblocks_state.on_flashblock_received(payload);
+                    ActorMessage::BestPayload { payload, received_at } => {
+                        flashblocks_state.on_flashblock_received_at(payload, received_at);
                     }
                 }
             }
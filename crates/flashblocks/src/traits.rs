@@ -1,9 +1,9 @@
 //! Traits for the Flashblocks module.
 
-use std::sync::Arc;
+use std::{sync::Arc, time::Instant};
 
 use alloy_eips::BlockNumberOrTag;
-use alloy_primitives::{Address, TxHash, U256};
+use alloy_primitives::{Address, B256, Bytes, TxHash, U256};
 use alloy_rpc_types_eth::{Filter, Log, state::StateOverride};
 use arc_swap::Guard;
 use base_flashtypes::Flashblock;
@@ -12,12 +12,28 @@ use reth_rpc_convert::RpcTransaction;
 use reth_rpc_eth_api::{RpcBlock, RpcReceipt};
 use tokio::sync::broadcast;
 
-use crate::PendingBlocks;
+use crate::{
+    CanonicalBlock, FlashblockHistory, InclusionWaiter, Invalidation, PendingBlocks,
+    ProcessedFlashblock,
+};
 
 /// Trait for receiving flashblock updates.
+///
+/// The time a flashblock was received is not a field of [`Flashblock`], which mirrors the upstream
+/// payload. It is passed alongside it through
+/// [`on_flashblock_received_at`](Self::on_flashblock_received_at) instead, and carried to the
+/// state processor in [`StateUpdate::Flashblock`](crate::StateUpdate::Flashblock) to measure
+/// processing latency. Receivers that do not track latency only implement
+/// [`on_flashblock_received`](Self::on_flashblock_received).
 pub trait FlashblocksReceiver {
     /// Called when a new flashblock is received.
     fn on_flashblock_received(&self, flashblock: Flashblock);
+
+    /// Called when a new flashblock is received, with the local time it was received from
+    /// upstream.
+    fn on_flashblock_received_at(&self, flashblock: Flashblock, _received_at: Instant) {
+        self.on_flashblock_received(flashblock);
+    }
 }
 
 /// Core API for accessing flashblock state and data.
@@ -27,6 +43,25 @@ pub trait FlashblocksAPI {
 
     /// Subscribes to flashblock updates.
     fn subscribe_to_flashblocks(&self) -> broadcast::Receiver<Arc<PendingBlocks>>;
+
+    /// Subscribes to notifications of preconfirmed transactions dropped from the pending state.
+    /// An invalidation is always sent before the flashblock updates that follow it.
+    fn subscribe_to_invalidations(&self) -> broadcast::Receiver<Arc<Invalidation>>;
+
+    /// Subscribes to the canonical blocks reconciled against the pending state. A canonical block
+    /// is always sent before the flashblock updates that follow it.
+    fn subscribe_to_canonical_blocks(&self) -> broadcast::Receiver<Arc<CanonicalBlock>>;
+
+    /// Subscribes to every flashblock received from upstream, with how it was processed. A
+    /// flashblock is sent once the pending state was updated with it.
+    fn subscribe_to_processed_flashblocks(&self) -> broadcast::Receiver<Arc<ProcessedFlashblock>>;
+
+    /// Registers a waiter notified when the transaction is included in a flashblock or a
+    /// canonical block. Returns None if too many callers are already waiting.
+    fn register_inclusion_waiter(&self, tx_hash: TxHash) -> Option<InclusionWaiter>;
+
+    /// Returns the most recent flashblocks, kept for replay to subscribers.
+    fn flashblock_history(&self) -> &FlashblockHistory;
 }
 
 /// API for accessing pending blocks data.
@@ -34,12 +69,22 @@ pub trait PendingBlocksAPI {
     /// Get the canonical block number on top of which all pending state is built
     fn get_canonical_block_number(&self) -> BlockNumberOrTag;
 
-    /// Get the pending transactions count for an address
-    fn get_transaction_count(&self, address: Address) -> U256;
+    /// Gets the nonce for an address after executing pending transactions. Returns None if address
+    /// not updated in flashblocks.
+    fn get_nonce(&self, address: Address) -> Option<u64>;
 
     /// Retrieves the current block. If `full` is true, includes full transaction details.
     fn get_block(&self, full: bool) -> Option<RpcBlock<Optimism>>;
 
+    /// Retrieves a pending block by its hash. If `full` is true, includes full transaction details.
+    fn get_block_by_hash(&self, hash: B256, full: bool) -> Option<RpcBlock<Optimism>>;
+
+    /// Gets the transaction at the given position in the current block.
+    fn get_transaction_by_block_index(&self, index: usize) -> Option<RpcTransaction<Optimism>>;
+
+    /// Gets the receipts of all transactions in the current block.
+    fn get_block_receipts(&self) -> Option<Vec<RpcReceipt<Optimism>>>;
+
     /// Gets transaction receipt by hash.
     fn get_transaction_receipt(&self, tx_hash: TxHash) -> Option<RpcReceipt<Optimism>>;
 
@@ -52,6 +97,15 @@ pub trait PendingBlocksAPI {
     /// Gets the state overrides for the pending blocks
     fn get_state_overrides(&self) -> Option<StateOverride>;
 
+    /// Gets the value of a storage slot. Returns None if the slot was not accessed in flashblocks.
+    fn get_storage_at(&self, address: Address, slot: B256) -> Option<B256>;
+
+    /// Gets the code of an address. Returns None if the account was not accessed in flashblocks.
+    fn get_code(&self, address: Address) -> Option<Bytes>;
+
     /// Gets logs from pending state matching the provided filter.
     fn get_pending_logs(&self, filter: &Filter) -> Vec<Log>;
+
+    /// Gets the predicted base fee of the block following the pending blocks.
+    fn get_next_base_fee(&self) -> Option<u64>;
 }
@@ -5,7 +5,7 @@ use std::{sync::Arc, time::Duration};
 use alloy_consensus::{Receipt, Transaction};
 use alloy_eips::{BlockHashOrNumber, Encodable2718};
 use alloy_primitives::{
-    Address, B256, BlockNumber, Bytes, U256, hex::FromHex, map::foldhash::HashMap,
+    Address, B256, BlockNumber, Bytes, U256, address, hex::FromHex, map::foldhash::HashMap,
 };
 use alloy_rpc_types_engine::PayloadId;
 use base_flashtypes::{
@@ -116,19 +116,15 @@ impl TestHarness {
         let nonce = self
             .flashblocks
             .get_pending_blocks()
-            .get_transaction_count(self.address(u))
-            .to::<u64>();
+            .get_nonce(self.address(u))
+            .unwrap_or(basic_account.nonce);
         let balance = self
             .flashblocks
             .get_pending_blocks()
             .get_balance(self.address(u))
             .unwrap_or(basic_account.balance);
 
-        Account {
-            nonce: nonce + basic_account.nonce,
-            balance,
-            bytecode_hash: basic_account.bytecode_hash,
-        }
+        Account { nonce, balance, bytecode_hash: basic_account.bytecode_hash }
     }
 
     fn build_transaction_to_send_eth(
@@ -648,12 +644,9 @@ async fn test_only_current_pending_state_cleared_upon_canonical_block_reorg() {
 }
 
 #[tokio::test]
-async fn test_nonce_uses_pending_canon_block_instead_of_latest() {
-    // Test for race condition when a canon block comes in but user
-    // requests their nonce prior to the StateProcessor processing the canon block
-    // causing it to return an n+1 nonce instead of n
-    // because underlying reth node `latest` block is already updated, but
-    // relevant pending state has not been cleared yet
+async fn test_nonce_is_unaffected_by_unprocessed_canon_block() {
+    // Pending nonces are read from executed state, so a canon block reaching the underlying
+    // chain before the StateProcessor processes it must not change the pending nonce
     let mut test = TestHarness::new().await;
 
     test.send_flashblock(FlashblockBuilder::new_base(&test).build()).await;
@@ -668,48 +661,39 @@ async fn test_nonce_uses_pending_canon_block_instead_of_latest() {
     )
     .await;
 
-    let pending_nonce =
-        test.provider.basic_account(&test.address(User::Alice)).unwrap().unwrap().nonce
-            + test
-                .flashblocks
-                .get_pending_blocks()
-                .get_transaction_count(test.address(User::Alice))
-                .to::<u64>();
-    assert_eq!(pending_nonce, 1);
+    let pending_nonce = test.flashblocks.get_pending_blocks().get_nonce(test.address(User::Alice));
+    assert_eq!(pending_nonce, Some(1));
 
     test.new_canonical_block_without_processing(vec![
         test.build_transaction_to_send_eth_with_nonce(User::Alice, User::Bob, 100, 0),
     ])
     .await;
 
-    let pending_nonce =
-        test.provider.basic_account(&test.address(User::Alice)).unwrap().unwrap().nonce
-            + test
-                .flashblocks
-                .get_pending_blocks()
-                .get_transaction_count(test.address(User::Alice))
-                .to::<u64>();
-
-    // This is 2, because canon block has reached the underlying chain
-    // but the StateProcessor hasn't processed it
-    // so pending nonce is effectively double-counting the same transaction, leading to a nonce of 2
-    assert_eq!(pending_nonce, 2);
-
-    // On the RPC level, we correctly return 1 because we
-    // use the pending canon block instead of the latest block when fetching
-    // onchain nonce count to compute
-    // pending_nonce = onchain_nonce + pending_txn_count
-    let canon_block = test.flashblocks.get_pending_blocks().get_canonical_block_number();
-    let canon_state_provider = test.provider.state_by_block_number_or_tag(canon_block).unwrap();
-    let canon_nonce =
-        canon_state_provider.account_nonce(&test.address(User::Alice)).unwrap().unwrap();
-    let pending_nonce = canon_nonce
-        + test
-            .flashblocks
-            .get_pending_blocks()
-            .get_transaction_count(test.address(User::Alice))
-            .to::<u64>();
-    assert_eq!(pending_nonce, 1);
+    let canonical_nonce =
+        test.provider.basic_account(&test.address(User::Alice)).unwrap().unwrap().nonce;
+    assert_eq!(canonical_nonce, 1);
+
+    let pending_nonce = test.flashblocks.get_pending_blocks().get_nonce(test.address(User::Alice));
+    assert_eq!(pending_nonce, Some(1));
+}
+
+#[tokio::test]
+async fn test_nonce_tracks_deposits() {
+    let mut test = TestHarness::new().await;
+
+    // The base flashblock only contains the L1 info deposit
+    test.send_flashblock(FlashblockBuilder::new_base(&test).build()).await;
+
+    let depositor = address!("0xdeaddeaddeaddeaddeaddeaddeaddeaddead0001");
+    let canonical_nonce =
+        test.provider.basic_account(&depositor).unwrap().map(|account| account.nonce).unwrap_or(0);
+    assert_eq!(
+        test.flashblocks.get_pending_blocks().get_nonce(depositor),
+        Some(canonical_nonce + 1)
+    );
+
+    // Accounts not touched by pending transactions are not tracked
+    assert_eq!(test.flashblocks.get_pending_blocks().get_nonce(test.address(User::Charlie)), None);
 }
 
 #[tokio::test]
@@ -860,3 +844,69 @@ async fn test_progress_canonical_blocks_without_flashblocks() {
     assert_eq!(block_two.transaction_count(), 3);
     assert!(test.flashblocks.get_pending_blocks().get_block(true).is_none());
 }
+
+#[tokio::test]
+async fn test_flashblock_snapshots_track_state_per_index() {
+    let test = TestHarness::new().await;
+
+    let base = FlashblockBuilder::new_base(&test).build();
+    let block_number = base.metadata.block_number;
+    test.send_flashblock(base).await;
+
+    let first_transfer = test.build_transaction_to_send_eth(User::Alice, User::Bob, 100_000);
+    let first_hash = *first_transfer.hash();
+    test.send_flashblock(
+        FlashblockBuilder::new(&test, 1).with_transactions(vec![first_transfer]).build(),
+    )
+    .await;
+
+    let second_transfer = test.build_transaction_to_send_eth(User::Alice, User::Bob, 200_000);
+    let second_hash = *second_transfer.hash();
+    test.send_flashblock(
+        FlashblockBuilder::new(&test, 2).with_transactions(vec![second_transfer]).build(),
+    )
+    .await;
+
+    let pending_blocks = test.flashblocks.get_pending_blocks();
+    let pending_blocks = pending_blocks.as_ref().expect("pending state is built");
+
+    let bob = test.address(User::Bob);
+    let base_snapshot =
+        pending_blocks.get_flashblock_snapshot(block_number, 0).expect("snapshot for index 0");
+    assert_eq!(base_snapshot.transaction_count(), 1);
+    assert!(!base_snapshot.state_changes().contains_key(&bob));
+    assert_eq!(pending_blocks.get_balance_at_flashblock(bob, block_number, 0), None);
+
+    let first_snapshot =
+        pending_blocks.get_flashblock_snapshot(block_number, 1).expect("snapshot for index 1");
+    assert_eq!(first_snapshot.transaction_count(), 2);
+    assert_eq!(
+        pending_blocks.get_balance_at_flashblock(bob, block_number, 1),
+        Some(test.expected_pending_balance(User::Bob, 100_000))
+    );
+
+    let second_snapshot =
+        pending_blocks.get_flashblock_snapshot(block_number, 2).expect("snapshot for index 2");
+    assert_eq!(second_snapshot.transaction_count(), 3);
+    assert_eq!(
+        pending_blocks.get_balance_at_flashblock(bob, block_number, 2),
+        Some(test.expected_pending_balance(User::Bob, 300_000))
+    );
+
+    // Snapshots only record the accounts changed by their own flashblock
+    assert!(second_snapshot.state_changes().contains_key(&bob));
+    assert!(!second_snapshot.state_changes().contains_key(&test.address(User::Charlie)));
+    let overrides = pending_blocks
+        .get_state_overrides_at_flashblock(block_number, 2)
+        .expect("overrides for index 2");
+    assert_eq!(
+        overrides.get(&bob).and_then(|account| account.balance),
+        Some(test.expected_pending_balance(User::Bob, 300_000))
+    );
+
+    assert!(pending_blocks.get_receipt_at_flashblock(first_hash, block_number, 0).is_none());
+    assert!(pending_blocks.get_receipt_at_flashblock(first_hash, block_number, 1).is_some());
+    assert!(pending_blocks.get_receipt_at_flashblock(second_hash, block_number, 1).is_none());
+    assert!(pending_blocks.get_receipt_at_flashblock(second_hash, block_number, 2).is_some());
+    assert!(pending_blocks.get_flashblock_snapshot(block_number, 3).is_none());
+}
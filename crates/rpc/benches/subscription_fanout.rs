@@ -0,0 +1,347 @@
+//! CPU cost of notifying `newFlashblocks` subscribers of a flashblock.
+//!
+//! The figure is the CPU time of the bench process, which runs the node: processing each
+//! flashblock and notifying every subscriber of it. The subscribers read their notifications in a
+//! child process, so the client side of the websockets is not counted.
+//!
+//! Compare against the tree before a change by saving a baseline there with
+//! `just bench-subscriptions --save-baseline before`, then running
+//! `just bench-subscriptions --baseline before` with the change.
+
+#![allow(missing_docs)]
+
+use std::{
+    env,
+    io::{self, BufRead, BufReader, Write},
+    process::{Child, Command, Stdio},
+    thread,
+    time::Duration,
+};
+
+use alloy_eips::Encodable2718;
+use alloy_primitives::{Address, B256, Bytes, U256, hex::FromHex};
+use alloy_rpc_types_engine::PayloadId;
+use base_flashtypes::{
+    ExecutionPayloadBaseV1, ExecutionPayloadFlashblockDeltaV1, Flashblock, Metadata,
+};
+use base_reth_test_utils::{FlashblocksHarness, L1_BLOCK_INFO_DEPOSIT_TX};
+use criterion::{
+    BenchmarkId, Criterion, Throughput, criterion_group,
+    measurement::{Measurement, ValueFormatter, WallTime},
+};
+use futures_util::{SinkExt, StreamExt};
+use reth::{
+    chainspec::{ChainSpecProvider, EthChainSpec},
+    transaction_pool::test_utils::TransactionBuilder,
+};
+use reth_optimism_primitives::OpTransactionSigned;
+use serde_json::json;
+use tokio::{runtime::Runtime, sync::mpsc, time::timeout};
+use tokio_tungstenite::{connect_async, tungstenite::Message};
+
+/// Formats CPU times like wall times.
+static WALL_TIME: WallTime = WallTime;
+
+const TX_GAS_USED: u64 = 21_000;
+const BLOCK_TRANSACTIONS: u64 = 100;
+// Stays below the default limit of subscriptions per connection.
+const SUBSCRIPTIONS_PER_CONNECTION: usize = 25;
+/// Environment variables that make the bench run as the subscription reader process.
+const READER_WS_URL_ENV: &str = "SUBSCRIPTION_BENCH_READER_WS_URL";
+const READER_SUBSCRIBERS_ENV: &str = "SUBSCRIPTION_BENCH_READER_SUBSCRIBERS";
+
+/// Measures the CPU time used by all the threads of the process, so that the server's work to
+/// notify subscribers is measured rather than how long notifications take to arrive. The
+/// subscribers are read in a child process, which this does not include.
+struct ProcessCpuTime;
+
+impl ProcessCpuTime {
+    fn now() -> Duration {
+        let mut time = libc::timespec { tv_sec: 0, tv_nsec: 0 };
+        // SAFETY: `time` is a valid timespec for the duration of the call
+        let result = unsafe { libc::clock_gettime(libc::CLOCK_PROCESS_CPUTIME_ID, &mut time) };
+        assert_eq!(result, 0, "process CPU time should be readable");
+        Duration::new(time.tv_sec as u64, time.tv_nsec as u32)
+    }
+}
+
+impl Measurement for ProcessCpuTime {
+    type Intermediate = Duration;
+    type Value = Duration;
+
+    fn start(&self) -> Self::Intermediate {
+        Self::now()
+    }
+
+    fn end(&self, start: Self::Intermediate) -> Self::Value {
+        Self::now().saturating_sub(start)
+    }
+
+    fn add(&self, v1: &Self::Value, v2: &Self::Value) -> Self::Value {
+        *v1 + *v2
+    }
+
+    fn zero(&self) -> Self::Value {
+        Duration::ZERO
+    }
+
+    fn to_f64(&self, value: &Self::Value) -> f64 {
+        value.as_nanos() as f64
+    }
+
+    fn formatter(&self) -> &dyn ValueFormatter {
+        WALL_TIME.formatter()
+    }
+}
+
+/// `newFlashblocks` subscribers spread over websocket connections, read by a child process so
+/// that their CPU time is not measured. The child reports on `notified` once every subscriber has
+/// been notified of a flashblock.
+struct Subscribers {
+    reader: Child,
+    notified: mpsc::UnboundedReceiver<()>,
+}
+
+impl Subscribers {
+    async fn new(ws_url: &str, count: usize) -> Self {
+        let mut reader = Command::new(env::current_exe().expect("bench executable path"))
+            .env(READER_WS_URL_ENV, ws_url)
+            .env(READER_SUBSCRIBERS_ENV, count.to_string())
+            .stdout(Stdio::piped())
+            .spawn()
+            .expect("subscription bench: reader process should start");
+
+        let (notify, notified) = mpsc::unbounded_channel();
+        let stdout = reader.stdout.take().expect("reader stdout is piped");
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                if line.is_err() || notify.send(()).is_err() {
+                    return;
+                }
+            }
+        });
+
+        let mut subscribers = Self { reader, notified };
+        // The reader reports once all of its subscriptions are accepted
+        subscribers.wait_for_flashblock().await;
+        subscribers
+    }
+
+    async fn wait_for_flashblock(&mut self) {
+        timeout(Duration::from_secs(10), self.notified.recv())
+            .await
+            .expect("subscribers were not notified in time")
+            .expect("subscription reader should be running");
+    }
+}
+
+impl Drop for Subscribers {
+    fn drop(&mut self) {
+        let _ = self.reader.kill();
+        let _ = self.reader.wait();
+    }
+}
+
+/// Runs in the reader process: subscribes `count` times to `newFlashblocks`, then writes a line to
+/// stdout once it is ready and each time `count` notifications have arrived.
+fn run_reader(ws_url: &str, count: usize) {
+    let runtime = Runtime::new().expect("tokio runtime should start");
+    runtime.block_on(async {
+        let (notify, mut notified) = mpsc::unbounded_channel();
+
+        for connection in 0..count.div_ceil(SUBSCRIPTIONS_PER_CONNECTION) {
+            let subscriptions = (count - connection * SUBSCRIPTIONS_PER_CONNECTION)
+                .min(SUBSCRIPTIONS_PER_CONNECTION);
+            let (mut ws_stream, _) =
+                connect_async(ws_url).await.expect("subscription bench: websocket should connect");
+
+            for id in 0..subscriptions {
+                ws_stream
+                    .send(Message::Text(
+                        json!({
+                            "jsonrpc": "2.0",
+                            "id": id,
+                            "method": "eth_subscribe",
+                            "params": ["newFlashblocks"]
+                        })
+                        .to_string()
+                        .into(),
+                    ))
+                    .await
+                    .expect("subscribe request should be sent");
+            }
+            for _ in 0..subscriptions {
+                let response = ws_stream.next().await.expect("subscribe response expected");
+                let response: serde_json::Value = serde_json::from_str(
+                    response
+                        .expect("subscribe response should be read")
+                        .to_text()
+                        .expect("subscribe response should be text"),
+                )
+                .expect("subscribe response should be json");
+                assert!(response["result"].is_string(), "subscription rejected: {response}");
+            }
+
+            let notify = notify.clone();
+            tokio::spawn(async move {
+                while let Some(Ok(message)) = ws_stream.next().await {
+                    if message.is_text() && notify.send(()).is_err() {
+                        return;
+                    }
+                }
+            });
+        }
+
+        let mut stdout = io::stdout();
+        writeln!(stdout, "ready").and_then(|()| stdout.flush()).expect("stdout should be writable");
+        let mut received = 0;
+        while notified.recv().await.is_some() {
+            received += 1;
+            if received % count == 0 {
+                writeln!(stdout, "notified")
+                    .and_then(|()| stdout.flush())
+                    .expect("stdout should be writable");
+            }
+        }
+    });
+}
+
+/// Builds a pending block of transfers, then sends flashblocks that add nothing to it so that
+/// every notification carries the same full block.
+struct FlashblockSource {
+    harness: FlashblocksHarness,
+    block_number: u64,
+    next_index: u64,
+}
+
+impl FlashblockSource {
+    async fn new() -> Self {
+        let harness =
+            FlashblocksHarness::new().await.expect("subscription bench: harness should start");
+        let canonical_block = harness.latest_block();
+        let block_number = canonical_block.number + 1;
+
+        let signer =
+            B256::from_hex(harness.accounts().alice.private_key).expect("valid private key hex");
+        let chain_id = harness.blockchain_provider().chain_spec().chain_id();
+        let recipient = harness.accounts().bob.address;
+        let transactions = (0..BLOCK_TRANSACTIONS)
+            .map(|nonce| transfer(signer, chain_id, recipient, nonce).encoded_2718().into())
+            .collect();
+
+        harness
+            .send_flashblocks([
+                Flashblock {
+                    payload_id: PayloadId::default(),
+                    index: 0,
+                    base: Some(ExecutionPayloadBaseV1 {
+                        parent_beacon_block_root: canonical_block.hash(),
+                        parent_hash: canonical_block.hash(),
+                        fee_recipient: Address::ZERO,
+                        prev_randao: B256::ZERO,
+                        block_number,
+                        gas_limit: canonical_block.gas_limit,
+                        timestamp: canonical_block.timestamp + 2,
+                        extra_data: Bytes::new(),
+                        base_fee_per_gas: U256::from(100),
+                    }),
+                    diff: ExecutionPayloadFlashblockDeltaV1 {
+                        blob_gas_used: Some(0),
+                        transactions: vec![L1_BLOCK_INFO_DEPOSIT_TX],
+                        ..Default::default()
+                    },
+                    metadata: Metadata { block_number },
+                },
+                delta_flashblock(block_number, 1, transactions),
+            ])
+            .await
+            .expect("pending block should be built");
+
+        Self { harness, block_number, next_index: 2 }
+    }
+
+    async fn send_next(&mut self) {
+        let flashblock = delta_flashblock(self.block_number, self.next_index, Vec::new());
+        self.next_index += 1;
+        self.harness.send_flashblock(flashblock).await.expect("flashblock should be processed");
+    }
+}
+
+fn subscription_fanout_benches(c: &mut Criterion<ProcessCpuTime>) {
+    let runtime = Runtime::new().expect("tokio runtime should start");
+    let mut source = runtime.block_on(FlashblockSource::new());
+    let ws_url = source.harness.ws_url();
+
+    let mut group = c.benchmark_group("subscription_fanout");
+    group.sample_size(10);
+
+    for count in [1, 100, 1_000] {
+        let mut subscribers = runtime.block_on(Subscribers::new(&ws_url, count));
+        group.throughput(Throughput::Elements(count as u64));
+
+        group.bench_with_input(BenchmarkId::new("new_flashblocks", count), &count, |b, _| {
+            b.iter_custom(|iters| {
+                runtime.block_on(async {
+                    let start = ProcessCpuTime.start();
+                    for _ in 0..iters {
+                        source.send_next().await;
+                        subscribers.wait_for_flashblock().await;
+                    }
+                    ProcessCpuTime.end(start)
+                })
+            })
+        });
+    }
+
+    group.finish();
+}
+
+fn delta_flashblock(block_number: u64, index: u64, transactions: Vec<Bytes>) -> Flashblock {
+    Flashblock {
+        payload_id: PayloadId::default(),
+        index,
+        base: None,
+        diff: ExecutionPayloadFlashblockDeltaV1 {
+            gas_used: TX_GAS_USED * BLOCK_TRANSACTIONS,
+            transactions,
+            ..Default::default()
+        },
+        metadata: Metadata { block_number },
+    }
+}
+
+fn transfer(signer: B256, chain_id: u64, to: Address, nonce: u64) -> OpTransactionSigned {
+    let txn = TransactionBuilder::default()
+        .signer(signer)
+        .chain_id(chain_id)
+        .to(to)
+        .nonce(nonce)
+        .value(1_000_000_000u128)
+        .gas_limit(TX_GAS_USED)
+        .max_fee_per_gas(1_000_000_000)
+        .max_priority_fee_per_gas(1_000_000_000)
+        .into_eip1559()
+        .as_eip1559()
+        .expect("should convert to eip1559")
+        .clone();
+
+    OpTransactionSigned::Eip1559(txn)
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().with_measurement(ProcessCpuTime);
+    targets = subscription_fanout_benches
+}
+
+fn main() {
+    // The bench re-runs itself as the subscription reader process
+    if let (Ok(ws_url), Ok(count)) = (env::var(READER_WS_URL_ENV), env::var(READER_SUBSCRIBERS_ENV))
+    {
+        run_reader(&ws_url, count.parse().expect("subscriber count should be a number"));
+        return;
+    }
+
+    benches();
+    Criterion::default().configure_from_args().final_summary();
+}
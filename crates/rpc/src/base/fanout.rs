@@ -0,0 +1,222 @@
+//! Notifications serialized once and shared by every subscription of the same variant.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, OnceLock},
+};
+
+use base_reth_flashblocks::PendingBlocks;
+use serde::{Serialize, Serializer};
+use serde_json::value::RawValue;
+use tracing::error;
+
+/// A serialized notification, shared by all the subscriptions it is sent to.
+#[derive(Clone, Debug)]
+pub(crate) struct SharedJson(Arc<RawValue>);
+
+impl SharedJson {
+    /// Returns the length of the serialized notification, in bytes.
+    pub(crate) fn len(&self) -> usize {
+        self.0.get().len()
+    }
+}
+
+impl Serialize for SharedJson {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.serialize(serializer)
+    }
+}
+
+/// Subscription variants whose notifications only depend on the pending state.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub(crate) enum SharedVariant {
+    /// `newFlashblocks` with full pending blocks.
+    NewFlashblocks,
+    /// `newFlashblockTransactions` with full transactions.
+    FullTransactions,
+    /// `newFlashblockTransactions` with transaction hashes.
+    TransactionHashes,
+}
+
+/// The notification of a variant for a pending state, built by the first subscription to ask
+/// for it.
+#[derive(Debug)]
+struct SharedEntry {
+    pending_blocks: Arc<PendingBlocks>,
+    notification: OnceLock<Option<SharedJson>>,
+}
+
+/// Keeps the notification of each variant for the latest pending state, so that it is built and
+/// serialized once however many subscriptions are sent it.
+#[derive(Debug, Default)]
+pub(crate) struct SharedNotifications {
+    entries: Mutex<HashMap<SharedVariant, Arc<SharedEntry>>>,
+}
+
+impl SharedNotifications {
+    /// Returns the notification of the variant for the pending state, built with `build` and
+    /// serialized by the first subscription to ask for it. Returns None if there is nothing to
+    /// notify.
+    pub(crate) fn get_or_build<T: Serialize>(
+        &self,
+        variant: SharedVariant,
+        pending_blocks: &Arc<PendingBlocks>,
+        build: impl FnOnce(&PendingBlocks) -> Option<T>,
+    ) -> Option<SharedJson> {
+        let entry = {
+            let mut entries = self.entries.lock().expect("shared notifications lock poisoned");
+            match entries.get(&variant) {
+                Some(entry) if Arc::ptr_eq(&entry.pending_blocks, pending_blocks) => {
+                    Some(Arc::clone(entry))
+                }
+                // Older pending states, sent to subscriptions catching up, do not replace the
+                // latest one
+                Some(entry)
+                    if entry.pending_blocks.latest_flashblock_id()
+                        > pending_blocks.latest_flashblock_id() =>
+                {
+                    None
+                }
+                _ => {
+                    let entry = Arc::new(SharedEntry {
+                        pending_blocks: Arc::clone(pending_blocks),
+                        notification: OnceLock::new(),
+                    });
+                    entries.insert(variant, Arc::clone(&entry));
+                    Some(entry)
+                }
+            }
+        };
+
+        match entry {
+            Some(entry) => {
+                entry.notification.get_or_init(|| serialize(build(pending_blocks)?)).clone()
+            }
+            None => serialize(build(pending_blocks)?),
+        }
+    }
+}
+
+/// Serializes a notification to be shared.
+pub(crate) fn serialize<T: Serialize>(notification: T) -> Option<SharedJson> {
+    match serde_json::value::to_raw_value(&notification) {
+        Ok(raw) => Some(SharedJson(Arc::from(raw))),
+        Err(err) => {
+            error!(
+                target: "flashblocks_rpc::pubsub",
+                %err,
+                "Failed to serialize shared subscription message"
+            );
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use alloy_primitives::{Address, B256, Bytes, U256};
+    use alloy_rpc_types_engine::PayloadId;
+    use base_flashtypes::{
+        ExecutionPayloadBaseV1, ExecutionPayloadFlashblockDeltaV1, Flashblock, Metadata,
+    };
+    use base_reth_flashblocks::FlashblocksAPI;
+    use base_reth_test_utils::{FlashblocksHarness, L1_BLOCK_INFO_DEPOSIT_TX};
+
+    use super::*;
+
+    /// Returns the pending states of a base flashblock and of an empty flashblock following it.
+    async fn pending_states() -> (FlashblocksHarness, Arc<PendingBlocks>, Arc<PendingBlocks>) {
+        let harness = FlashblocksHarness::new().await.expect("harness should start");
+        let mut updates = harness.flashblocks_state().subscribe_to_flashblocks();
+        let canonical_block = harness.latest_block();
+        let block_number = canonical_block.number + 1;
+
+        harness
+            .send_flashblocks([
+                Flashblock {
+                    payload_id: PayloadId::default(),
+                    index: 0,
+                    base: Some(ExecutionPayloadBaseV1 {
+                        parent_beacon_block_root: canonical_block.hash(),
+                        parent_hash: canonical_block.hash(),
+                        fee_recipient: Address::ZERO,
+                        prev_randao: B256::ZERO,
+                        block_number,
+                        gas_limit: canonical_block.gas_limit,
+                        timestamp: canonical_block.timestamp + 2,
+                        extra_data: Bytes::new(),
+                        base_fee_per_gas: U256::from(100),
+                    }),
+                    diff: ExecutionPayloadFlashblockDeltaV1 {
+                        blob_gas_used: Some(0),
+                        transactions: vec![L1_BLOCK_INFO_DEPOSIT_TX],
+                        ..Default::default()
+                    },
+                    metadata: Metadata { block_number },
+                },
+                Flashblock {
+                    payload_id: PayloadId::default(),
+                    index: 1,
+                    base: None,
+                    diff: ExecutionPayloadFlashblockDeltaV1::default(),
+                    metadata: Metadata { block_number },
+                },
+            ])
+            .await
+            .expect("flashblocks should be processed");
+
+        let older = updates.recv().await.expect("base flashblock state expected");
+        let latest = updates.recv().await.expect("second flashblock state expected");
+        (harness, older, latest)
+    }
+
+    /// Returns a notification builder counting how many times it is called.
+    fn counted(builds: &AtomicUsize) -> impl FnOnce(&PendingBlocks) -> Option<u64> + '_ {
+        |pending_blocks| {
+            builds.fetch_add(1, Ordering::SeqCst);
+            Some(pending_blocks.latest_flashblock_index())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_notification_is_shared_by_subscribers() {
+        let (_harness, _, latest) = pending_states().await;
+        let shared = SharedNotifications::default();
+        let builds = AtomicUsize::new(0);
+
+        let first = shared
+            .get_or_build(SharedVariant::NewFlashblocks, &latest, counted(&builds))
+            .expect("notification expected");
+        let second = shared
+            .get_or_build(SharedVariant::NewFlashblocks, &latest, counted(&builds))
+            .expect("notification expected");
+
+        assert!(Arc::ptr_eq(&first.0, &second.0));
+        assert_eq!(builds.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_older_state_does_not_replace_latest_notification() {
+        let (_harness, older, latest) = pending_states().await;
+        let shared = SharedNotifications::default();
+        let builds = AtomicUsize::new(0);
+
+        let latest_json = shared
+            .get_or_build(SharedVariant::NewFlashblocks, &latest, counted(&builds))
+            .expect("notification expected");
+        // A subscription catching up is sent its own notification of the older state
+        let older_json = shared
+            .get_or_build(SharedVariant::NewFlashblocks, &older, counted(&builds))
+            .expect("notification expected");
+        assert_eq!(older_json.0.get(), "0");
+        assert_eq!(builds.load(Ordering::SeqCst), 2);
+
+        let again = shared
+            .get_or_build(SharedVariant::NewFlashblocks, &latest, counted(&builds))
+            .expect("notification expected");
+        assert!(Arc::ptr_eq(&latest_json.0, &again.0));
+        assert_eq!(builds.load(Ordering::SeqCst), 2);
+    }
+}
@@ -0,0 +1,52 @@
+//! RPC implementation for fee estimation based on pending flashblocks.
+
+use std::sync::Arc;
+
+use alloy_eips::BlockNumberOrTag;
+use alloy_primitives::U256;
+use base_reth_flashblocks::{FlashblocksAPI, PendingBlocksAPI};
+use jsonrpsee::core::{RpcResult, async_trait};
+use op_alloy_network::Optimism;
+use reth_rpc_eth_api::{
+    EthApiTypes,
+    helpers::{EthFees, FullEthApi},
+};
+use tracing::debug;
+
+use crate::FlashblocksFeeApiServer;
+
+/// Implementation of the flashblocks fee estimation RPC API.
+#[derive(Debug)]
+pub struct FlashblocksFeeApiImpl<Eth, FB> {
+    eth_api: Eth,
+    flashblocks_state: Arc<FB>,
+}
+
+impl<Eth, FB> FlashblocksFeeApiImpl<Eth, FB> {
+    /// Creates a new flashblocks fee estimation API instance.
+    pub const fn new(eth_api: Eth, flashblocks_state: Arc<FB>) -> Self {
+        Self { eth_api, flashblocks_state }
+    }
+}
+
+#[async_trait]
+impl<Eth, FB> FlashblocksFeeApiServer for FlashblocksFeeApiImpl<Eth, FB>
+where
+    Eth: FullEthApi<NetworkTypes = Optimism> + EthApiTypes + Send + Sync + 'static,
+    FB: FlashblocksAPI + Send + Sync + 'static,
+    jsonrpsee_types::error::ErrorObject<'static>: From<Eth::Error>,
+{
+    async fn estimate_next_base_fee(&self) -> RpcResult<U256> {
+        debug!(message = "rpc::estimate_next_base_fee");
+
+        if let Some(next_base_fee) = self.flashblocks_state.get_pending_blocks().get_next_base_fee()
+        {
+            return Ok(U256::from(next_base_fee));
+        }
+
+        // The last base fee of the history is the one of the block following it
+        let history =
+            EthFees::fee_history(&self.eth_api, 1, BlockNumberOrTag::Latest, None).await?;
+        Ok(U256::from(history.base_fee_per_gas.last().copied().unwrap_or_default()))
+    }
+}
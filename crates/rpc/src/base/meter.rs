@@ -3,9 +3,13 @@ use std::{sync::Arc, time::Instant};
 use alloy_consensus::{BlockHeader, Transaction as _, transaction::SignerRecoverable};
 use alloy_primitives::{B256, U256};
 use base_bundles::{BundleExtensions, BundleTxs, ParsedBundle, TransactionResult};
+use base_reth_flashblocks::PendingBlocks;
 use eyre::{Result as EyreResult, eyre};
-use reth::revm::db::State;
-use reth_evm::{ConfigureEvm, execute::BlockBuilder};
+use reth::revm::{
+    database::StateProviderDatabase,
+    db::{CacheDB, State},
+};
+use reth_evm::{ConfigureEvm, execute::BlockBuilder, overrides::apply_state_overrides};
 use reth_optimism_chainspec::OpChainSpec;
 use reth_optimism_evm::{OpEvmConfig, OpNextBlockEnvAttributes};
 use reth_primitives_traits::SealedHeader;
@@ -32,13 +36,6 @@ pub fn meter_bundle<SP>(
 where
     SP: reth_provider::StateProvider,
 {
-    // Get bundle hash
-    let bundle_hash = bundle.bundle_hash();
-
-    // Create state database
-    let state_db = reth::revm::database::StateProviderDatabase::new(state_provider);
-    let mut db = State::builder().with_database(state_db).with_bundle_update().build();
-
     // Set up next block attributes
     // Use bundle.min_timestamp if provided, otherwise use header timestamp + BLOCK_TIME
     let timestamp = bundle.min_timestamp.unwrap_or_else(|| header.timestamp() + BLOCK_TIME);
@@ -51,6 +48,72 @@ where
         extra_data: header.extra_data().clone(),
     };
 
+    let state_db = StateProviderDatabase::new(state_provider);
+    execute_bundle(CacheDB::new(state_db), None, chain_spec, bundle, header, attributes)
+}
+
+/// Simulates and meters a bundle of transactions on top of the pending flashblocks state
+///
+/// Takes a state provider for the canonical block the pending state is built on, and the header
+/// of the parent of the latest pending block. The transactions are executed after those of the
+/// latest pending block, within the same block, with the pending state layered over the canonical
+/// state through the pending database cache and state overrides.
+///
+/// Returns the same tuple as [`meter_bundle`].
+pub fn meter_bundle_on_pending_state<SP>(
+    state_provider: SP,
+    chain_spec: Arc<OpChainSpec>,
+    bundle: ParsedBundle,
+    parent: &SealedHeader,
+    pending_blocks: &PendingBlocks,
+) -> EyreResult<(Vec<TransactionResult>, u64, U256, B256, u128)>
+where
+    SP: reth_provider::StateProvider,
+{
+    // The bundle executes in the latest pending block, so its attributes are reused
+    let pending_header = pending_blocks.latest_header();
+    let attributes = OpNextBlockEnvAttributes {
+        timestamp: pending_header.timestamp(),
+        suggested_fee_recipient: pending_header.beneficiary(),
+        prev_randao: pending_header.mix_hash().unwrap_or(B256::random()),
+        gas_limit: pending_header.gas_limit(),
+        parent_beacon_block_root: pending_header.parent_beacon_block_root(),
+        extra_data: pending_header.extra_data().clone(),
+    };
+
+    let state_db = StateProviderDatabase::new(state_provider);
+    let db = CacheDB { cache: pending_blocks.get_db_cache(), db: state_db };
+    execute_bundle(db, Some(pending_blocks), chain_spec, bundle, parent, attributes)
+}
+
+/// Executes the transactions of a bundle in sequence in the block built on top of `parent` with
+/// the given attributes, measuring gas usage and execution time. With `pending_blocks`, the
+/// transactions follow those of the latest pending block, in the same block.
+fn execute_bundle<SP>(
+    cache_db: CacheDB<StateProviderDatabase<SP>>,
+    pending_blocks: Option<&PendingBlocks>,
+    chain_spec: Arc<OpChainSpec>,
+    bundle: ParsedBundle,
+    parent: &SealedHeader,
+    attributes: OpNextBlockEnvAttributes,
+) -> EyreResult<(Vec<TransactionResult>, u64, U256, B256, u128)>
+where
+    SP: reth_provider::StateProvider,
+{
+    // Get bundle hash
+    let bundle_hash = bundle.bundle_hash();
+
+    // Create state database
+    let mut db = State::builder().with_database(cache_db).with_bundle_update().build();
+    if let Some(state_overrides) = pending_blocks.and_then(|p| p.get_state_overrides()) {
+        apply_state_overrides(state_overrides, &mut db)
+            .map_err(|e| eyre!("Failed to apply pending state overrides: {}", e))?;
+    }
+
+    // Gas already used in the block by the pending transactions
+    let pending_gas_used = pending_blocks.map_or(0, |p| p.latest_header().gas_used());
+    let block_gas_limit = attributes.gas_limit;
+
     // Execute transactions
     let mut results = Vec::new();
     let mut total_gas_used = 0u64;
@@ -59,13 +122,26 @@ where
     let execution_start = Instant::now();
     {
         let evm_config = OpEvmConfig::optimism(chain_spec);
-        let mut builder = evm_config.builder_for_next_block(&mut db, header, attributes)?;
+        let mut builder = evm_config.builder_for_next_block(&mut db, parent, attributes)?;
 
-        builder.apply_pre_execution_changes()?;
+        // The pending block was opened by its first flashblock, which already applied them
+        if pending_blocks.is_none() {
+            builder.apply_pre_execution_changes()?;
+        }
 
         for tx in bundle.transactions() {
             let tx_start = Instant::now();
             let tx_hash = tx.tx_hash();
+
+            let available_gas = block_gas_limit.saturating_sub(pending_gas_used + total_gas_used);
+            if tx.gas_limit() > available_gas {
+                return Err(eyre!(
+                    "Transaction {} gas limit {} exceeds the block's available gas {}",
+                    tx_hash,
+                    tx.gas_limit(),
+                    available_gas
+                ));
+            }
             let from = tx.recover_signer()?;
             let to = tx.to();
             let value = tx.value();
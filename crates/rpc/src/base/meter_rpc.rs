@@ -1,22 +1,38 @@
+use std::{fmt, sync::Arc};
+
 use alloy_consensus::Header;
 use alloy_eips::BlockNumberOrTag;
 use alloy_primitives::{B256, U256};
 use base_bundles::{Bundle, MeterBundleResponse, ParsedBundle};
+use base_reth_flashblocks::{FlashblocksAPI, PendingBlocks};
 use jsonrpsee::core::{RpcResult, async_trait};
 use reth::providers::BlockReaderIdExt;
 use reth_optimism_chainspec::OpChainSpec;
 use reth_optimism_primitives::OpBlock;
+use reth_primitives_traits::SealedHeader;
 use reth_provider::{BlockReader, ChainSpecProvider, HeaderProvider, StateProviderFactory};
-use tracing::{error, info};
+use tracing::{debug, error, info};
 
 use super::{
-    block::meter_block, meter::meter_bundle, traits::MeteringApiServer, types::MeterBlockResponse,
+    block::meter_block,
+    meter::{meter_bundle, meter_bundle_on_pending_state},
+    traits::MeteringApiServer,
+    types::MeterBlockResponse,
 };
 
 /// Implementation of the metering RPC API
-#[derive(Debug)]
 pub struct MeteringApiImpl<Provider> {
     provider: Provider,
+    flashblocks_state: Option<Arc<dyn FlashblocksAPI + Send + Sync>>,
+}
+
+impl<Provider: fmt::Debug> fmt::Debug for MeteringApiImpl<Provider> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MeteringApiImpl")
+            .field("provider", &self.provider)
+            .field("flashblocks_enabled", &self.flashblocks_state.is_some())
+            .finish()
+    }
 }
 
 impl<Provider> MeteringApiImpl<Provider>
@@ -30,7 +46,16 @@ where
 {
     /// Creates a new instance of MeteringApi
     pub const fn new(provider: Provider) -> Self {
-        Self { provider }
+        Self { provider, flashblocks_state: None }
+    }
+
+    /// Enables metering bundles on top of the pending flashblocks state.
+    pub fn with_flashblocks_state(
+        mut self,
+        flashblocks_state: Arc<dyn FlashblocksAPI + Send + Sync>,
+    ) -> Self {
+        self.flashblocks_state = Some(flashblocks_state);
+        self
     }
 }
 
@@ -47,31 +72,59 @@ where
         + Sync
         + 'static,
 {
-    async fn meter_bundle(&self, bundle: Bundle) -> RpcResult<MeterBundleResponse> {
+    async fn meter_bundle(
+        &self,
+        bundle: Bundle,
+        state_block: Option<BlockNumberOrTag>,
+    ) -> RpcResult<MeterBundleResponse> {
         info!(
             num_transactions = &bundle.txs.len(),
             block_number = &bundle.block_number,
+            state_block = ?state_block,
             "Starting bundle metering"
         );
 
-        // Get the latest header
-        let header = self
-            .provider
-            .sealed_header_by_number_or_tag(BlockNumberOrTag::Latest)
-            .map_err(|e| {
-                jsonrpsee::types::ErrorObjectOwned::owned(
-                    jsonrpsee::types::ErrorCode::InternalError.code(),
-                    format!("Failed to get latest header: {}", e),
+        let pending_blocks = match state_block.unwrap_or(BlockNumberOrTag::Latest) {
+            BlockNumberOrTag::Latest => None,
+            BlockNumberOrTag::Pending => {
+                let Some(flashblocks_state) = &self.flashblocks_state else {
+                    return Err(jsonrpsee::types::ErrorObjectOwned::owned(
+                        jsonrpsee::types::ErrorCode::InvalidParams.code(),
+                        "Pending metering requires flashblocks to be enabled".to_string(),
+                        None::<()>,
+                    ));
+                };
+                let pending_blocks = Option::clone(&flashblocks_state.get_pending_blocks());
+                if pending_blocks.is_none() {
+                    debug!("No pending block yet, metering bundle on the latest block");
+                }
+                pending_blocks
+            }
+            other => {
+                return Err(jsonrpsee::types::ErrorObjectOwned::owned(
+                    jsonrpsee::types::ErrorCode::InvalidParams.code(),
+                    format!("Unsupported state block: {}", other),
                     None::<()>,
-                )
-            })?
-            .ok_or_else(|| {
-                jsonrpsee::types::ErrorObjectOwned::owned(
-                    jsonrpsee::types::ErrorCode::InternalError.code(),
-                    "Latest block not found".to_string(),
+                ));
+            }
+        };
+
+        // The bundle is simulated at the pending block's timestamp, which it must not precede
+        if let Some(pending_blocks) = &pending_blocks
+            && let Some(min_timestamp) = bundle.min_timestamp
+        {
+            let pending_timestamp = pending_blocks.latest_header().timestamp;
+            if min_timestamp > pending_timestamp {
+                return Err(jsonrpsee::types::ErrorObjectOwned::owned(
+                    jsonrpsee::types::ErrorCode::InvalidParams.code(),
+                    format!(
+                        "Bundle min timestamp {} is after the pending block timestamp {}",
+                        min_timestamp, pending_timestamp
+                    ),
                     None::<()>,
-                )
-            })?;
+                ));
+            }
+        }
 
         let parsed_bundle = ParsedBundle::try_from(bundle).map_err(|e| {
             jsonrpsee::types::ErrorObjectOwned::owned(
@@ -81,27 +134,84 @@ where
             )
         })?;
 
-        // Get state provider for the block
-        let state_provider = self.provider.state_by_block_hash(header.hash()).map_err(|e| {
-            error!(error = %e, "Failed to get state provider");
-            jsonrpsee::types::ErrorObjectOwned::owned(
-                jsonrpsee::types::ErrorCode::InternalError.code(),
-                format!("Failed to get state provider: {}", e),
-                None::<()>,
-            )
-        })?;
+        let (metered, state_block_number, state_flashblock_index) = match pending_blocks {
+            Some(pending_blocks) => {
+                let block_number = pending_blocks.latest_block_number();
+                let parent = self.pending_parent_header(&pending_blocks)?;
 
-        // Meter bundle using utility function
-        let (results, total_gas_used, total_gas_fees, bundle_hash, total_execution_time) =
-            meter_bundle(state_provider, self.provider.chain_spec(), parsed_bundle, &header)
-                .map_err(|e| {
-                    error!(error = %e, "Bundle metering failed");
-                    jsonrpsee::types::ErrorObjectOwned::owned(
-                        jsonrpsee::types::ErrorCode::InternalError.code(),
-                        format!("Bundle metering failed: {}", e),
-                        None::<()>,
-                    )
-                })?;
+                // The pending state is layered over the canonical block it was built on
+                let state_provider = self
+                    .provider
+                    .state_by_block_number_or_tag(pending_blocks.canonical_block_number())
+                    .map_err(|e| {
+                        error!(error = %e, "Failed to get state provider");
+                        jsonrpsee::types::ErrorObjectOwned::owned(
+                            jsonrpsee::types::ErrorCode::InternalError.code(),
+                            format!("Failed to get state provider: {}", e),
+                            None::<()>,
+                        )
+                    })?;
+
+                let metered = meter_bundle_on_pending_state(
+                    state_provider,
+                    self.provider.chain_spec(),
+                    parsed_bundle,
+                    &parent,
+                    &pending_blocks,
+                );
+                (metered, block_number, Some(pending_blocks.latest_flashblock_index()))
+            }
+            None => {
+                // Get the latest header
+                let header = self
+                    .provider
+                    .sealed_header_by_number_or_tag(BlockNumberOrTag::Latest)
+                    .map_err(|e| {
+                        jsonrpsee::types::ErrorObjectOwned::owned(
+                            jsonrpsee::types::ErrorCode::InternalError.code(),
+                            format!("Failed to get latest header: {}", e),
+                            None::<()>,
+                        )
+                    })?
+                    .ok_or_else(|| {
+                        jsonrpsee::types::ErrorObjectOwned::owned(
+                            jsonrpsee::types::ErrorCode::InternalError.code(),
+                            "Latest block not found".to_string(),
+                            None::<()>,
+                        )
+                    })?;
+
+                // Get state provider for the block
+                let state_provider =
+                    self.provider.state_by_block_hash(header.hash()).map_err(|e| {
+                        error!(error = %e, "Failed to get state provider");
+                        jsonrpsee::types::ErrorObjectOwned::owned(
+                            jsonrpsee::types::ErrorCode::InternalError.code(),
+                            format!("Failed to get state provider: {}", e),
+                            None::<()>,
+                        )
+                    })?;
+
+                // Meter bundle using utility function
+                let metered = meter_bundle(
+                    state_provider,
+                    self.provider.chain_spec(),
+                    parsed_bundle,
+                    &header,
+                );
+                (metered, header.number, None)
+            }
+        };
+
+        let (results, total_gas_used, total_gas_fees, bundle_hash, total_execution_time) = metered
+            .map_err(|e| {
+                error!(error = %e, "Bundle metering failed");
+                jsonrpsee::types::ErrorObjectOwned::owned(
+                    jsonrpsee::types::ErrorCode::InternalError.code(),
+                    format!("Bundle metering failed: {}", e),
+                    None::<()>,
+                )
+            })?;
 
         // Calculate average gas price
         let bundle_gas_price = if total_gas_used > 0 {
@@ -115,6 +225,8 @@ where
             num_transactions = results.len(),
             total_gas_used = total_gas_used,
             total_execution_time_us = total_execution_time,
+            state_block_number,
+            state_flashblock_index = ?state_flashblock_index,
             "Bundle metering completed successfully"
         );
 
@@ -125,8 +237,8 @@ where
             eth_sent_to_coinbase: U256::from(0),
             gas_fees: total_gas_fees,
             results,
-            state_block_number: header.number,
-            state_flashblock_index: None,
+            state_block_number,
+            state_flashblock_index,
             total_gas_used,
             total_execution_time_us: total_execution_time,
         })
@@ -221,6 +333,33 @@ where
         + Sync
         + 'static,
 {
+    /// Returns the header of the parent of the latest pending block, which is either an earlier
+    /// pending block or the canonical block the pending state is built on
+    fn pending_parent_header(&self, pending_blocks: &PendingBlocks) -> RpcResult<SealedHeader> {
+        let parent_number = pending_blocks.latest_block_number() - 1;
+        if let Some(header) = pending_blocks.get_header(parent_number) {
+            let (header, hash) = header.into_parts();
+            return Ok(SealedHeader::new(header, hash));
+        }
+
+        self.provider
+            .sealed_header(parent_number)
+            .map_err(|e| {
+                jsonrpsee::types::ErrorObjectOwned::owned(
+                    jsonrpsee::types::ErrorCode::InternalError.code(),
+                    format!("Failed to get pending parent header: {}", e),
+                    None::<()>,
+                )
+            })?
+            .ok_or_else(|| {
+                jsonrpsee::types::ErrorObjectOwned::owned(
+                    jsonrpsee::types::ErrorCode::InternalError.code(),
+                    format!("Pending parent block {} not found", parent_number),
+                    None::<()>,
+                )
+            })
+    }
+
     /// Internal helper to meter a block's execution
     fn meter_block_internal(&self, block: &OpBlock) -> RpcResult<MeterBlockResponse> {
         meter_block(self.provider.clone(), self.provider.chain_spec(), block).map_err(|e| {
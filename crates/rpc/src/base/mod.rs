@@ -1,7 +1,11 @@
 pub(crate) mod block;
+pub(crate) mod fanout;
+pub(crate) mod fee_rpc;
 pub(crate) mod meter;
 pub(crate) mod meter_rpc;
 pub(crate) mod pubsub;
+pub(crate) mod quota;
+pub(crate) mod snapshot_rpc;
 pub(crate) mod traits;
 pub(crate) mod transaction_rpc;
 pub(crate) mod types;
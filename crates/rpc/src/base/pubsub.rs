@@ -6,27 +6,51 @@
 
 use std::sync::Arc;
 
-use alloy_primitives::B256;
-use alloy_rpc_types_eth::{Filter, Log, pubsub::Params};
-use base_reth_flashblocks::FlashblocksAPI;
+use alloy_consensus::Transaction as _;
+use alloy_primitives::{Address, BlockNumber, TxHash, map::foldhash::HashSet};
+use alloy_rpc_types_eth::{Filter, Header as RPCHeader, Log, pubsub::Params};
+use base_reth_flashblocks::{
+    CanonicalBlock, FlashblockId, FlashblockReplay, FlashblocksAPI, Invalidation,
+    InvalidationReason, PendingBlocks, ProcessedFlashblock,
+};
+use futures_util::stream::{self, PollNext};
 use jsonrpsee::{
     PendingSubscriptionSink, SubscriptionSink,
     core::{SubscriptionResult, async_trait},
     proc_macros::rpc,
     server::SubscriptionMessage,
 };
-use op_alloy_network::Optimism;
+use jsonrpsee_types::{
+    ErrorObjectOwned,
+    error::{INTERNAL_ERROR_CODE, INVALID_PARAMS_CODE},
+};
+use op_alloy_network::TransactionResponse;
 use op_alloy_rpc_types::Transaction;
 use reth_rpc::eth::EthPubSub as RethEthPubSub;
 use reth_rpc_eth_api::{
-    EthApiTypes, RpcBlock, RpcNodeCore, RpcTransaction,
-    pubsub::EthPubSubApiServer as RethEthPubSubApiServer,
+    EthApiTypes, RpcNodeCore, RpcTransaction, pubsub::EthPubSubApiServer as RethEthPubSubApiServer,
+};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use tokio_stream::{
+    Stream, StreamExt,
+    wrappers::{BroadcastStream, errors::BroadcastStreamRecvError},
 };
-use serde::Serialize;
-use tokio_stream::{Stream, StreamExt, wrappers::BroadcastStream};
 use tracing::error;
 
-use crate::{BaseSubscriptionKind, ExtendedSubscriptionKind};
+use crate::{
+    AccountUpdate, BaseSubscriptionKind, ExtendedSubscriptionKind, FlashblockDelta, LagPolicy,
+    NewFlashblocksOptions, PendingInvalidation, PendingResponse, PendingSnapshot,
+    PreconfirmedTransaction, RawFlashblock, SUBSCRIPTION_LIMIT_CODE, SubscriptionLag,
+    SubscriptionLimits, TransactionAddressFilter,
+    base::{
+        fanout::{SharedJson, SharedNotifications, SharedVariant, serialize},
+        quota::{SubscriptionPermit, SubscriptionQuotas},
+    },
+    metrics::Metrics,
+};
+
+/// Error code returned when a subscription cursor is older than the flashblocks kept for replay.
+pub const CURSOR_EVICTED_CODE: i32 = -32001;
 
 /// Eth pub-sub RPC extension for flashblocks and standard subscriptions.
 ///
@@ -38,6 +62,9 @@ pub trait EthPubSubApi {
     ///
     /// Supports standard subscription types (newHeads, logs, newPendingTransactions, syncing)
     /// as well as Base-specific subscriptions (newFlashblocks, pendingLogs).
+    ///
+    /// Parameters are only deserialized once the kind is known, as some Base-specific
+    /// subscriptions accept parameters that standard subscriptions do not.
     #[subscription(
         name = "subscribe" => "subscription",
         unsubscribe = "unsubscribe",
@@ -46,7 +73,7 @@ pub trait EthPubSubApi {
     async fn subscribe(
         &self,
         kind: ExtendedSubscriptionKind,
-        params: Option<Params>,
+        params: Option<serde_json::Value>,
     ) -> SubscriptionResult;
 }
 
@@ -55,109 +82,444 @@ pub trait EthPubSubApi {
 ///
 /// This handles `eth_subscribe` RPC calls for both standard Ethereum subscriptions
 /// and Base-specific flashblocks subscriptions.
+///
+/// Base-specific subscriptions that fall behind the pending state are handled according to their
+/// [`LagPolicy`], after being sent a [`SubscriptionLag`] notice.
 #[derive(Clone, Debug)]
 pub struct EthPubSub<Eth, FB> {
     /// Reth's standard EthPubSub for handling standard subscription types
     inner: RethEthPubSub<Eth>,
     /// Flashblocks state for accessing pending blocks stream
     flashblocks_state: Arc<FB>,
+    /// What Base-specific subscriptions do when they fall behind the pending state
+    lag_policy: LagPolicy,
+    /// Active Base-specific subscriptions, counted against their limits
+    quotas: Arc<SubscriptionQuotas>,
+    /// Notifications serialized once for all the subscriptions of the same variant
+    shared: Arc<SharedNotifications>,
+    metrics: Metrics,
 }
 
 impl<Eth, FB> EthPubSub<Eth, FB> {
     /// Creates a new instance with the given eth API and flashblocks state.
     pub fn new(eth_api: Eth, flashblocks_state: Arc<FB>) -> Self {
-        Self { inner: RethEthPubSub::new(eth_api), flashblocks_state }
+        Self {
+            inner: RethEthPubSub::new(eth_api),
+            flashblocks_state,
+            lag_policy: LagPolicy::default(),
+            quotas: Arc::new(SubscriptionQuotas::default()),
+            shared: Arc::new(SharedNotifications::default()),
+            metrics: Metrics::default(),
+        }
+    }
+
+    /// Sets what Base-specific subscriptions do when they fall behind the pending state.
+    pub const fn with_lag_policy(mut self, lag_policy: LagPolicy) -> Self {
+        self.lag_policy = lag_policy;
+        self
+    }
+
+    /// Sets the limits on Base-specific subscriptions.
+    pub fn with_subscription_limits(mut self, limits: SubscriptionLimits) -> Self {
+        self.quotas = Arc::new(SubscriptionQuotas::new(limits));
+        self
+    }
+
+    /// Pipes the notifications of a subscription to its sink in a new task, which keeps the
+    /// subscription counted against the limits until it ends.
+    fn spawn_subscription<T, St>(
+        &self,
+        sink: SubscriptionSink,
+        stream: St,
+        permit: SubscriptionPermit,
+    ) where
+        St: Stream<Item = Notification<T>> + Unpin + Send + 'static,
+        T: Serialize + Send + 'static,
+    {
+        self.spawn_shared_subscription(sink, stream.map(Notification::into_shared), permit);
+    }
+
+    /// Pipes the already serialized notifications of a subscription to its sink in a new task,
+    /// like [`Self::spawn_subscription`].
+    fn spawn_shared_subscription<St>(
+        &self,
+        sink: SubscriptionSink,
+        stream: St,
+        permit: SubscriptionPermit,
+    ) where
+        St: Stream<Item = Notification<SharedJson>> + Unpin + Send + 'static,
+    {
+        let max_message_size = self.quotas.limits().max_message_size;
+        let metrics = self.metrics.clone();
+        tokio::spawn(async move {
+            pipe_from_stream(sink, stream, max_message_size, metrics).await;
+            drop(permit);
+        });
+    }
+
+    /// Returns the lag handler of a new subscription.
+    fn lag_handler(&self) -> LagHandler {
+        LagHandler { policy: self.lag_policy, metrics: self.metrics.clone() }
+    }
+
+    /// Returns a stream of the pending state of every new flashblock. With a cursor, the
+    /// flashblocks after it that are still in the history are replayed first. Fails if the
+    /// flashblock of the cursor was evicted from the history.
+    fn flashblock_updates(
+        flashblocks_state: &FB,
+        cursor: Option<FlashblockId>,
+    ) -> Result<impl Stream<Item = PendingUpdate> + Unpin + use<Eth, FB>, ErrorObjectOwned>
+    where
+        FB: FlashblocksAPI,
+    {
+        // Subscribe before reading the history, so that no flashblock is missed in between
+        let live = live_flashblock_updates(flashblocks_state);
+
+        let replay = match cursor {
+            Some(cursor) => {
+                flashblocks_state.flashblock_history().after(cursor).ok_or_else(|| {
+                    let oldest = flashblocks_state.flashblock_history().oldest();
+                    ErrorObjectOwned::owned(
+                        CURSOR_EVICTED_CODE,
+                        format!(
+                            "flashblock {} of block {} is no longer available for replay",
+                            cursor.1, cursor.0
+                        ),
+                        oldest.map(PendingSnapshot::from),
+                    )
+                })?
+            }
+            None => Vec::new(),
+        };
+
+        // Live flashblocks that were already replayed are skipped
+        let mut delivered = replay.last().map(|replay| replay.flashblock_id()).or(cursor);
+        let live = live.filter(move |update| {
+            let PendingUpdate::Flashblock(pending_blocks) = update else {
+                return true;
+            };
+            if let Some(last) = delivered
+                && pending_blocks.latest_flashblock_id() <= last
+            {
+                return false;
+            }
+            delivered = None;
+            true
+        });
+
+        Ok(tokio_stream::iter(replay.into_iter().map(PendingUpdate::Replayed)).chain(live))
+    }
+
+    /// Returns a stream that yields the pending block of every flashblock update as an RPC
+    /// block, together with the flashblock it was built from
+    fn new_flashblocks_stream(
+        updates: impl Stream<Item = PendingUpdate> + Unpin,
+        shared: Arc<SharedNotifications>,
+        lag: LagHandler,
+    ) -> impl Stream<Item = Notification<SharedJson>> + Unpin {
+        lag.deliver(updates, move |update, _| match update {
+            PendingUpdate::Flashblock(pending_blocks) => shared.get_or_build(
+                SharedVariant::NewFlashblocks,
+                &pending_blocks,
+                |pending_blocks| {
+                    Some(PendingResponse::pending(
+                        pending_blocks.get_latest_block(true),
+                        pending_blocks,
+                    ))
+                },
+            ),
+            // Replayed flashblocks are only sent to the subscription that resumed from a cursor
+            PendingUpdate::Replayed(replay) => serialize(PendingResponse {
+                inner: replay.block(true),
+                pending_snapshot: Some(replay.flashblock_id().into()),
+            }),
+            _ => None,
+        })
     }
 
-    /// Returns a stream that yields all new flashblocks as RPC blocks
-    fn new_flashblocks_stream(flashblocks_state: Arc<FB>) -> impl Stream<Item = RpcBlock<Optimism>>
+    /// Returns a stream that yields the changes made to the pending block by every flashblock
+    /// update, relative to the given cursor for the first one
+    fn new_flashblock_deltas_stream(
+        updates: impl Stream<Item = PendingUpdate> + Unpin,
+        cursor: Option<FlashblockId>,
+        lag: LagHandler,
+    ) -> impl Stream<Item = Notification<FlashblockDelta>> + Unpin {
+        let mut last_flashblock = cursor;
+        lag.deliver(updates, move |update, resync| {
+            // A resync sends the whole pending block again
+            let after = last_flashblock.filter(|_| !resync);
+            let deltas = match update {
+                PendingUpdate::Flashblock(pending_blocks) => {
+                    flashblock_deltas(&pending_blocks, after)
+                }
+                PendingUpdate::Replayed(replay) => vec![replayed_flashblock_delta(&replay, after)],
+                _ => Vec::new(),
+            };
+            if let Some(delta) = deltas.last() {
+                last_flashblock = Some((delta.block_number, delta.index));
+            }
+            deltas
+        })
+    }
+
+    /// Returns a stream that yields the transactions matching the filter, with their receipts,
+    /// once when they are first preconfirmed
+    fn preconfirmed_transactions_stream(
+        flashblocks_state: Arc<FB>,
+        filter: TransactionAddressFilter,
+        lag: LagHandler,
+    ) -> impl Stream<Item = Notification<Vec<PreconfirmedTransaction>>> + Unpin
     where
         FB: FlashblocksAPI + Send + Sync + 'static,
     {
-        BroadcastStream::new(flashblocks_state.subscribe_to_flashblocks()).filter_map(|result| {
-            let pending_blocks = match result {
-                Ok(blocks) => blocks,
-                Err(err) => {
-                    error!(
-                        message = "Error in flashblocks stream",
-                        error = %err
-                    );
-                    return None;
+        // Transactions preconfirmed before the subscription are not returned
+        let mut last_flashblock =
+            flashblocks_state.get_pending_blocks().as_ref().map(|p| p.latest_flashblock_id());
+        lag.deliver(live_flashblock_updates(flashblocks_state.as_ref()), move |update, resync| {
+            let PendingUpdate::Flashblock(pending_blocks) = update else {
+                return None;
+            };
+
+            // A resync sends all pending transactions again
+            let transactions = match last_flashblock.filter(|_| !resync) {
+                Some(after) => pending_blocks.get_transactions_after(after),
+                None => pending_blocks.get_pending_transactions(),
+            };
+            last_flashblock = Some(pending_blocks.latest_flashblock_id());
+
+            let preconfirmed: Vec<PreconfirmedTransaction> = transactions
+                .into_iter()
+                .filter(|tx| filter.matches(tx.from(), tx.to()))
+                .filter_map(|transaction| {
+                    let receipt = pending_blocks.get_receipt(transaction.tx_hash())?;
+                    Some(PreconfirmedTransaction { transaction, receipt })
+                })
+                .collect();
+            if preconfirmed.is_empty() { None } else { Some(preconfirmed) }
+        })
+    }
+
+    /// Returns a stream that yields the balance and nonce of the watched accounts after every
+    /// preconfirmed transaction that touches them, followed by a confirmation or a revert notice
+    /// once the transaction leaves the pending state
+    fn pending_account_updates_stream(
+        flashblocks_state: Arc<FB>,
+        addresses: Vec<Address>,
+        lag: LagHandler,
+    ) -> impl Stream<Item = Notification<Vec<AccountUpdate>>> + Unpin
+    where
+        FB: FlashblocksAPI + Send + Sync + 'static,
+    {
+        // Transactions preconfirmed before the subscription are not returned
+        let mut last_flashblock =
+            flashblocks_state.get_pending_blocks().as_ref().map(|p| p.latest_flashblock_id());
+        // Notified transactions that are still pending, with their block and the accounts they
+        // touched
+        let mut unresolved: Vec<(TxHash, BlockNumber, Vec<Address>)> = Vec::new();
+        // The latest canonical block received since the subscription started
+        let mut canonical_tip: Option<BlockNumber> = None;
+        lag.deliver(canonical_updates(flashblocks_state.as_ref()), move |update, resync| {
+            let pending_blocks = match update {
+                PendingUpdate::Flashblock(pending_blocks) => pending_blocks,
+                PendingUpdate::Canonical(block) => {
+                    canonical_tip = canonical_tip.max(Some(block.number));
+                    let confirmed = confirmed_updates(&mut unresolved, &block);
+                    return if confirmed.is_empty() { None } else { Some(confirmed) };
                 }
+                _ => return None,
             };
-            Some(pending_blocks.get_latest_block(true))
+
+            // Confirmations may have been missed, so the pending transactions are tracked again
+            // from the full snapshot sent below
+            if resync {
+                unresolved.clear();
+            }
+
+            let mut updates = Vec::new();
+            unresolved.retain(|(tx_hash, block_number, touched)| {
+                if !is_reverted(&pending_blocks, canonical_tip, *tx_hash, *block_number) {
+                    return true;
+                }
+                updates.extend(touched.iter().map(|address| AccountUpdate::Reverted {
+                    address: *address,
+                    tx_hash: *tx_hash,
+                    block_number: *block_number,
+                }));
+                false
+            });
+
+            // A resync sends the updates of all pending transactions again
+            let transactions = match last_flashblock.filter(|_| !resync) {
+                Some(after) => pending_blocks.get_transactions_after(after),
+                None => pending_blocks.get_pending_transactions(),
+            };
+            last_flashblock = Some(pending_blocks.latest_flashblock_id());
+
+            for transaction in transactions {
+                let tx_hash = transaction.tx_hash();
+                // The state of transactions pruned to save memory is no longer known
+                let Some(state) = pending_blocks.get_transaction_state(&tx_hash) else {
+                    continue;
+                };
+                let block_number = transaction.block_number.unwrap_or_default();
+                let flashblock_index = transaction
+                    .transaction_index
+                    .and_then(|index| {
+                        pending_blocks
+                            .get_transaction_flashblock_index(block_number, index as usize)
+                    })
+                    .unwrap_or_else(|| pending_blocks.latest_flashblock_index());
+
+                let mut touched = Vec::new();
+                for address in &addresses {
+                    let Some(account) = state.get(address).filter(|account| account.is_touched())
+                    else {
+                        continue;
+                    };
+                    touched.push(*address);
+                    updates.push(AccountUpdate::Preconfirmed {
+                        address: *address,
+                        balance: account.info.balance,
+                        nonce: account.info.nonce,
+                        tx_hash,
+                        block_number,
+                        flashblock_index,
+                    });
+                }
+                if !touched.is_empty() && !unresolved.iter().any(|(hash, ..)| *hash == tx_hash) {
+                    unresolved.push((tx_hash, block_number, touched));
+                }
+            }
+
+            if updates.is_empty() { None } else { Some(updates) }
         })
     }
 
-    /// Returns a stream that yields logs from pending flashblocks matching the filter
+    /// Returns a stream that yields logs from pending flashblocks matching the filter. Each
+    /// notification only contains the logs of flashblocks received since the previous one, or
+    /// the logs sent earlier that were dropped from the pending state, marked as removed.
     fn pending_logs_stream(
         flashblocks_state: Arc<FB>,
         filter: Filter,
-    ) -> impl Stream<Item = Vec<Log>>
+        lag: LagHandler,
+    ) -> impl Stream<Item = Notification<Vec<Log>>> + Unpin
     where
         FB: FlashblocksAPI + Send + Sync + 'static,
     {
-        BroadcastStream::new(flashblocks_state.subscribe_to_flashblocks()).filter_map(
-            move |result| {
-                let pending_blocks = match result {
-                    Ok(blocks) => blocks,
-                    Err(err) => {
-                        error!(
-                            message = "Error in flashblocks stream for pending logs",
-                            error = %err
-                        );
-                        return None;
+        let mut last_flashblock = None;
+        lag.deliver(pending_updates(flashblocks_state.as_ref()), move |update, resync| {
+            let logs = match update {
+                PendingUpdate::Flashblock(pending_blocks) => {
+                    // A resync sends all pending logs again
+                    if resync {
+                        last_flashblock = None;
                     }
-                };
-                let logs = pending_blocks.get_pending_logs(&filter);
-                if logs.is_empty() { None } else { Some(logs) }
-            },
-        )
+                    let logs = pending_blocks.get_pending_logs_after(&filter, last_flashblock);
+                    last_flashblock = Some(pending_blocks.latest_flashblock_id());
+                    logs
+                }
+                PendingUpdate::Invalidation(invalidation) => {
+                    let logs = removed_logs(&invalidation, &filter, last_flashblock);
+                    // The pending state was cleared, so all logs of the next one are new
+                    if invalidation.reason == InvalidationReason::SequenceGap {
+                        last_flashblock = None;
+                    }
+                    logs
+                }
+                PendingUpdate::Replayed(_)
+                | PendingUpdate::Processed(_)
+                | PendingUpdate::Canonical(_)
+                | PendingUpdate::Lagged(_) => {
+                    return None;
+                }
+            };
+            if logs.is_empty() { None } else { Some(logs) }
+        })
+    }
+
+    /// Returns a stream that yields the preconfirmed transactions dropped from the pending state
+    fn pending_invalidations_stream(
+        flashblocks_state: Arc<FB>,
+        lag: LagHandler,
+    ) -> impl Stream<Item = Notification<PendingInvalidation>> + Unpin
+    where
+        FB: FlashblocksAPI + Send + Sync + 'static,
+    {
+        let invalidations = BroadcastStream::new(flashblocks_state.subscribe_to_invalidations())
+            .map(|result| received(result, PendingUpdate::Invalidation));
+        lag.deliver(invalidations, |update, _| {
+            let PendingUpdate::Invalidation(invalidation) = update else {
+                return None;
+            };
+            Some(PendingInvalidation::from(invalidation.as_ref()))
+        })
+    }
+
+    /// Returns a stream that yields every flashblock received from upstream, with how it was
+    /// processed
+    fn new_raw_flashblocks_stream(
+        flashblocks_state: Arc<FB>,
+        lag: LagHandler,
+    ) -> impl Stream<Item = Notification<RawFlashblock>> + Unpin
+    where
+        FB: FlashblocksAPI + Send + Sync + 'static,
+    {
+        let processed =
+            BroadcastStream::new(flashblocks_state.subscribe_to_processed_flashblocks())
+                .map(|result| received(result, PendingUpdate::Processed));
+        lag.deliver(processed, |update, _| {
+            let PendingUpdate::Processed(processed) = update else {
+                return None;
+            };
+            Some(RawFlashblock::from(processed.as_ref()))
+        })
     }
 
     /// Returns a stream that yields full transactions from pending flashblocks
     fn new_flashblock_transactions_full_stream(
         flashblocks_state: Arc<FB>,
-    ) -> impl Stream<Item = Vec<Transaction>>
+        shared: Arc<SharedNotifications>,
+        lag: LagHandler,
+    ) -> impl Stream<Item = Notification<SharedJson>> + Unpin
     where
         FB: FlashblocksAPI + Send + Sync + 'static,
     {
-        BroadcastStream::new(flashblocks_state.subscribe_to_flashblocks()).filter_map(|result| {
-            let pending_blocks = match result {
-                Ok(blocks) => blocks,
-                Err(err) => {
-                    error!(
-                        message = "Error in flashblocks stream for transactions",
-                        error = %err
-                    );
-                    return None;
-                }
+        lag.deliver(live_flashblock_updates(flashblocks_state.as_ref()), move |update, _| {
+            let PendingUpdate::Flashblock(pending_blocks) = update else {
+                return None;
             };
-            let txs = pending_blocks.get_pending_transactions();
-            if txs.is_empty() { None } else { Some(txs) }
+            shared.get_or_build(
+                SharedVariant::FullTransactions,
+                &pending_blocks,
+                |pending_blocks| {
+                    let txs = pending_blocks.get_pending_transactions();
+                    if txs.is_empty() { None } else { Some(txs) }
+                },
+            )
         })
     }
 
     /// Returns a stream that yields transaction hashes from pending flashblocks
     fn new_flashblock_transactions_hash_stream(
         flashblocks_state: Arc<FB>,
-    ) -> impl Stream<Item = Vec<B256>>
+        shared: Arc<SharedNotifications>,
+        lag: LagHandler,
+    ) -> impl Stream<Item = Notification<SharedJson>> + Unpin
     where
         FB: FlashblocksAPI + Send + Sync + 'static,
     {
-        BroadcastStream::new(flashblocks_state.subscribe_to_flashblocks()).filter_map(|result| {
-            let pending_blocks = match result {
-                Ok(blocks) => blocks,
-                Err(err) => {
-                    error!(
-                        message = "Error in flashblocks stream for transaction hashes",
-                        error = %err
-                    );
-                    return None;
-                }
+        lag.deliver(live_flashblock_updates(flashblocks_state.as_ref()), move |update, _| {
+            let PendingUpdate::Flashblock(pending_blocks) = update else {
+                return None;
             };
-            let hashes = pending_blocks.get_pending_transaction_hashes();
-            if hashes.is_empty() { None } else { Some(hashes) }
+            shared.get_or_build(
+                SharedVariant::TransactionHashes,
+                &pending_blocks,
+                |pending_blocks| {
+                    let hashes = pending_blocks.get_pending_transaction_hashes();
+                    if hashes.is_empty() { None } else { Some(hashes) }
+                },
+            )
         })
     }
 }
@@ -177,10 +539,17 @@ where
         &self,
         pending: PendingSubscriptionSink,
         kind: ExtendedSubscriptionKind,
-        params: Option<Params>,
+        params: Option<serde_json::Value>,
     ) -> SubscriptionResult {
         // For standard subscription types, delegate to reth's implementation
         if let Some(standard_kind) = kind.as_standard() {
+            let params = match parse_params::<Params>(params) {
+                Ok(params) => params,
+                Err(err) => {
+                    pending.reject(err).await;
+                    return Ok(());
+                }
+            };
             return RethEthPubSubApiServer::subscribe(&self.inner, pending, standard_kind, params)
                 .await;
         }
@@ -190,16 +559,110 @@ where
             unreachable!("Standard subscription types should be delegated to inner");
         };
 
-        let sink = pending.accept().await?;
+        // Counted until the subscription ends, including if it is rejected below
+        let permit = match self.quotas.acquire(pending.connection_id(), base_kind) {
+            Ok(permit) => permit,
+            Err(err) => {
+                pending.reject(err).await;
+                return Ok(());
+            }
+        };
 
-        match base_kind {
-            BaseSubscriptionKind::NewFlashblocks => {
-                let stream = Self::new_flashblocks_stream(Arc::clone(&self.flashblocks_state));
+        // Preconfirmed transactions are filtered by address rather than by standard parameters
+        if base_kind == BaseSubscriptionKind::NewPreconfirmedTransactions {
+            let filter = match parse_params::<TransactionAddressFilter>(params) {
+                Ok(filter) => filter.unwrap_or_default(),
+                Err(err) => {
+                    pending.reject(err).await;
+                    return Ok(());
+                }
+            };
+
+            let sink = pending.accept().await?;
+            let stream = Self::preconfirmed_transactions_stream(
+                Arc::clone(&self.flashblocks_state),
+                filter,
+                self.lag_handler(),
+            );
+            self.spawn_subscription(sink, stream, permit);
+            return Ok(());
+        }
 
-                tokio::spawn(async move {
-                    pipe_from_stream(sink, stream).await;
-                });
+        if base_kind == BaseSubscriptionKind::NewFlashblocks {
+            let options = match parse_params::<NewFlashblocksParams>(params) {
+                Ok(params) => params.map(NewFlashblocksOptions::from).unwrap_or_default(),
+                Err(err) => {
+                    pending.reject(err).await;
+                    return Ok(());
+                }
+            };
+            let cursor = options.cursor.map(|cursor| cursor.flashblock_id());
+            let updates = match Self::flashblock_updates(&self.flashblocks_state, cursor) {
+                Ok(updates) => updates,
+                Err(err) => {
+                    pending.reject(err).await;
+                    return Ok(());
+                }
+            };
+
+            let sink = pending.accept().await?;
+            if options.delta {
+                let stream =
+                    Self::new_flashblock_deltas_stream(updates, cursor, self.lag_handler());
+                self.spawn_subscription(sink, stream, permit);
+            } else {
+                let stream = Self::new_flashblocks_stream(
+                    updates,
+                    Arc::clone(&self.shared),
+                    self.lag_handler(),
+                );
+                self.spawn_shared_subscription(sink, stream, permit);
             }
+            return Ok(());
+        }
+
+        if base_kind == BaseSubscriptionKind::PendingAccountUpdates {
+            let mut addresses = match parse_params::<Vec<Address>>(params) {
+                Ok(Some(addresses)) if !addresses.is_empty() => addresses,
+                Ok(_) => {
+                    pending
+                        .reject(ErrorObjectOwned::owned(
+                            INVALID_PARAMS_CODE,
+                            "expected a non-empty list of addresses to watch",
+                            None::<()>,
+                        ))
+                        .await;
+                    return Ok(());
+                }
+                Err(err) => {
+                    pending.reject(err).await;
+                    return Ok(());
+                }
+            };
+            addresses.sort_unstable();
+            addresses.dedup();
+
+            let sink = pending.accept().await?;
+            let stream = Self::pending_account_updates_stream(
+                Arc::clone(&self.flashblocks_state),
+                addresses,
+                self.lag_handler(),
+            );
+            self.spawn_subscription(sink, stream, permit);
+            return Ok(());
+        }
+
+        let params = match parse_params::<Params>(params) {
+            Ok(params) => params,
+            Err(err) => {
+                pending.reject(err).await;
+                return Ok(());
+            }
+        };
+
+        let sink = pending.accept().await?;
+
+        match base_kind {
             BaseSubscriptionKind::PendingLogs => {
                 // Extract filter from params, default to empty filter (match all)
                 let filter = match params {
@@ -207,11 +670,13 @@ where
                     _ => Filter::default(),
                 };
 
-                let stream = Self::pending_logs_stream(Arc::clone(&self.flashblocks_state), filter);
+                let stream = Self::pending_logs_stream(
+                    Arc::clone(&self.flashblocks_state),
+                    filter,
+                    self.lag_handler(),
+                );
 
-                tokio::spawn(async move {
-                    pipe_from_stream(sink, stream).await;
-                });
+                self.spawn_subscription(sink, stream, permit);
             }
             BaseSubscriptionKind::NewFlashblockTransactions => {
                 // Extract full_transactions param, default to false (hash only)
@@ -221,35 +686,446 @@ where
                 };
 
                 if full {
-                    let stream = Self::new_flashblock_transactions_full_stream(Arc::clone(
-                        &self.flashblocks_state,
-                    ));
-                    tokio::spawn(async move {
-                        pipe_from_stream(sink, stream).await;
-                    });
+                    let stream = Self::new_flashblock_transactions_full_stream(
+                        Arc::clone(&self.flashblocks_state),
+                        Arc::clone(&self.shared),
+                        self.lag_handler(),
+                    );
+                    self.spawn_shared_subscription(sink, stream, permit);
                 } else {
-                    let stream = Self::new_flashblock_transactions_hash_stream(Arc::clone(
-                        &self.flashblocks_state,
-                    ));
-                    tokio::spawn(async move {
-                        pipe_from_stream(sink, stream).await;
-                    });
+                    let stream = Self::new_flashblock_transactions_hash_stream(
+                        Arc::clone(&self.flashblocks_state),
+                        Arc::clone(&self.shared),
+                        self.lag_handler(),
+                    );
+                    self.spawn_shared_subscription(sink, stream, permit);
                 }
             }
+            BaseSubscriptionKind::PendingInvalidations => {
+                let stream = Self::pending_invalidations_stream(
+                    Arc::clone(&self.flashblocks_state),
+                    self.lag_handler(),
+                );
+                self.spawn_subscription(sink, stream, permit);
+            }
+            BaseSubscriptionKind::NewRawFlashblocks => {
+                let stream = Self::new_raw_flashblocks_stream(
+                    Arc::clone(&self.flashblocks_state),
+                    self.lag_handler(),
+                );
+                self.spawn_subscription(sink, stream, permit);
+            }
+            BaseSubscriptionKind::NewFlashblocks
+            | BaseSubscriptionKind::NewPreconfirmedTransactions
+            | BaseSubscriptionKind::PendingAccountUpdates => {
+                unreachable!("Subscriptions with custom parameters are handled above");
+            }
         }
 
         Ok(())
     }
 }
 
+/// Parameters of a `newFlashblocks` subscription, either the delta flag alone or the full
+/// options.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum NewFlashblocksParams {
+    Delta(bool),
+    Options(NewFlashblocksOptions),
+}
+
+impl From<NewFlashblocksParams> for NewFlashblocksOptions {
+    fn from(params: NewFlashblocksParams) -> Self {
+        match params {
+            NewFlashblocksParams::Delta(delta) => Self { delta, cursor: None },
+            NewFlashblocksParams::Options(options) => options,
+        }
+    }
+}
+
+/// Deserializes the parameters of a subscription.
+fn parse_params<T: DeserializeOwned>(
+    params: Option<serde_json::Value>,
+) -> Result<Option<T>, ErrorObjectOwned> {
+    params
+        .map(serde_json::from_value)
+        .transpose()
+        .map_err(|err| ErrorObjectOwned::owned(INVALID_PARAMS_CODE, err.to_string(), None::<()>))
+}
+
+/// An update of the pending state.
+enum PendingUpdate {
+    /// A new flashblock was applied.
+    Flashblock(Arc<PendingBlocks>),
+    /// A flashblock kept in the history was replayed to a subscription resuming from a cursor.
+    Replayed(Arc<FlashblockReplay>),
+    /// Preconfirmed transactions were dropped.
+    Invalidation(Arc<Invalidation>),
+    /// A canonical block was reconciled against the pending state.
+    Canonical(Arc<CanonicalBlock>),
+    /// A flashblock was received from upstream and processed.
+    Processed(Arc<ProcessedFlashblock>),
+    /// The subscriber fell behind and the given number of updates were dropped.
+    Lagged(u64),
+}
+
+/// Converts an item received from a broadcast channel into an update.
+fn received<T>(
+    result: Result<T, BroadcastStreamRecvError>,
+    update: fn(T) -> PendingUpdate,
+) -> PendingUpdate {
+    match result {
+        Ok(item) => update(item),
+        Err(BroadcastStreamRecvError::Lagged(count)) => PendingUpdate::Lagged(count),
+    }
+}
+
+/// Returns a stream of the pending state of every new flashblock.
+fn live_flashblock_updates<FB: FlashblocksAPI>(
+    flashblocks_state: &FB,
+) -> impl Stream<Item = PendingUpdate> + Unpin + use<FB> {
+    BroadcastStream::new(flashblocks_state.subscribe_to_flashblocks())
+        .map(|result| received(result, PendingUpdate::Flashblock))
+}
+
+/// Returns a stream of the flashblock updates of the pending state and the canonical blocks
+/// reconciled against it, in the order they happened.
+fn canonical_updates<FB: FlashblocksAPI>(
+    flashblocks_state: &FB,
+) -> impl Stream<Item = PendingUpdate> + Unpin + use<FB> {
+    let flashblocks = live_flashblock_updates(flashblocks_state);
+    let canonical_blocks = BroadcastStream::new(flashblocks_state.subscribe_to_canonical_blocks())
+        .map(|result| received(result, PendingUpdate::Canonical));
+
+    // A canonical block is always sent before the flashblocks that follow it, so it is received
+    // first when both are ready
+    stream::select_with_strategy(canonical_blocks, flashblocks, |_: &mut ()| PollNext::Left)
+}
+
+/// Returns a stream of the flashblock updates and invalidations of the pending state, in the
+/// order they happened.
+fn pending_updates<FB: FlashblocksAPI>(
+    flashblocks_state: &FB,
+) -> impl Stream<Item = PendingUpdate> + Unpin + use<FB> {
+    let flashblocks = live_flashblock_updates(flashblocks_state);
+    let invalidations = BroadcastStream::new(flashblocks_state.subscribe_to_invalidations())
+        .map(|result| received(result, PendingUpdate::Invalidation));
+
+    // An invalidation is always sent before the flashblocks that follow it, so it is received
+    // first when both are ready
+    stream::select_with_strategy(invalidations, flashblocks, |_: &mut ()| PollNext::Left)
+}
+
+/// A notification of a Base-specific subscription.
+#[derive(Serialize)]
+#[serde(untagged)]
+enum Notification<T> {
+    /// An update matching the subscription.
+    Update(T),
+    /// The subscriber fell behind and missed updates.
+    Lagged { lagged: SubscriptionLag },
+    /// The subscription is closed because of the error.
+    Closed { error: ErrorObjectOwned },
+}
+
+impl<T: Serialize> Notification<T> {
+    /// Serializes the update of the notification once, before it is sent. A notification that
+    /// can't be serialized closes the subscription with an error.
+    fn into_shared(self) -> Notification<SharedJson> {
+        match self {
+            Self::Update(update) => match serialize(update) {
+                Some(json) => Notification::Update(json),
+                None => Notification::Closed {
+                    error: ErrorObjectOwned::owned(
+                        INTERNAL_ERROR_CODE,
+                        "failed to serialize notification",
+                        None::<()>,
+                    ),
+                },
+            },
+            Self::Lagged { lagged } => Notification::Lagged { lagged },
+            Self::Closed { error } => Notification::Closed { error },
+        }
+    }
+}
+
+impl<T> Notification<T> {
+    /// Returns true if the subscription is closed after this notification.
+    fn closes_subscription(&self) -> bool {
+        match self {
+            Self::Update(_) => false,
+            Self::Lagged { lagged } => lagged.policy == LagPolicy::Disconnect,
+            Self::Closed { .. } => true,
+        }
+    }
+}
+
+/// Applies the lag policy of a subscription to its updates.
+#[derive(Clone, Debug)]
+struct LagHandler {
+    policy: LagPolicy,
+    metrics: Metrics,
+}
+
+impl LagHandler {
+    /// Returns the notifications of a subscription for its updates. `notify` returns the
+    /// notifications of an update, none if nothing changed for the subscriber. It is passed
+    /// `true` when it should send a full snapshot rather than what changed since its previous
+    /// notification, until the next flashblock update.
+    fn deliver<T, I>(
+        self,
+        updates: impl Stream<Item = PendingUpdate> + Unpin,
+        mut notify: impl FnMut(PendingUpdate, bool) -> I,
+    ) -> impl Stream<Item = Notification<T>> + Unpin
+    where
+        I: IntoIterator<Item = T>,
+    {
+        // The last flashblock received, and the number of updates missed since
+        let mut last_flashblock: Option<FlashblockId> = None;
+        let mut missed = 0;
+        let mut resync = false;
+        futures_util::StreamExt::flat_map(updates, move |update| {
+            let mut notifications = Vec::new();
+            let resumed_at = match &update {
+                PendingUpdate::Lagged(count) => {
+                    if missed == 0 {
+                        self.metrics.subscription_lagged.increment(1);
+                    }
+                    self.metrics.subscription_missed_updates.increment(*count);
+                    missed += count;
+
+                    // The notice is sent right away, as no update follows
+                    if self.policy == LagPolicy::Disconnect {
+                        self.metrics.subscription_lag_disconnects.increment(1);
+                        notifications.push(self.lagged(missed, last_flashblock, None));
+                    }
+                    return stream::iter(notifications);
+                }
+                PendingUpdate::Flashblock(pending_blocks) => {
+                    Some(pending_blocks.latest_flashblock_id())
+                }
+                PendingUpdate::Replayed(replay) => Some(replay.flashblock_id()),
+                PendingUpdate::Processed(processed) => {
+                    Some((processed.flashblock.metadata.block_number, processed.flashblock.index))
+                }
+                PendingUpdate::Invalidation(_) | PendingUpdate::Canonical(_) => None,
+            };
+
+            if missed > 0 {
+                notifications.push(self.lagged(missed, last_flashblock, resumed_at));
+                resync = self.policy == LagPolicy::Resync;
+                missed = 0;
+            }
+            notifications.extend(notify(update, resync).into_iter().map(Notification::Update));
+            if resumed_at.is_some() {
+                last_flashblock = resumed_at;
+                resync = false;
+            }
+            stream::iter(notifications)
+        })
+    }
+
+    /// Returns the notice of a subscription that missed updates between the given flashblocks.
+    fn lagged<T>(
+        &self,
+        missed: u64,
+        after: Option<FlashblockId>,
+        resumed_at: Option<FlashblockId>,
+    ) -> Notification<T> {
+        Notification::Lagged {
+            lagged: SubscriptionLag {
+                policy: self.policy,
+                missed,
+                after: after.map(PendingSnapshot::from),
+                resumed_at: resumed_at.map(PendingSnapshot::from),
+            },
+        }
+    }
+}
+
+/// Returns the logs matching the filter of the invalidated transactions that were sent up to the
+/// given flashblock, marked as removed.
+fn removed_logs(
+    invalidation: &Invalidation,
+    filter: &Filter,
+    last_flashblock: Option<FlashblockId>,
+) -> Vec<Log> {
+    let Some(last_flashblock) = last_flashblock else {
+        return Vec::new();
+    };
+    let pending_blocks = &invalidation.pending_blocks;
+    let dropped: HashSet<TxHash> = invalidation.transactions.iter().copied().collect();
+
+    pending_blocks
+        .get_pending_logs(filter)
+        .into_iter()
+        .filter(|log| log.transaction_hash.is_some_and(|hash| dropped.contains(&hash)))
+        .filter(|log| {
+            let block_number = log.block_number.unwrap_or_default();
+            let flashblock_index = log.transaction_index.and_then(|index| {
+                pending_blocks.get_transaction_flashblock_index(block_number, index as usize)
+            });
+            flashblock_index.is_some_and(|index| (block_number, index) <= last_flashblock)
+        })
+        .map(|log| Log { removed: true, ..log })
+        .collect()
+}
+
+/// Returns the confirmations of the notified transactions included in a canonical block, and
+/// stops tracking them.
+fn confirmed_updates(
+    unresolved: &mut Vec<(TxHash, BlockNumber, Vec<Address>)>,
+    block: &CanonicalBlock,
+) -> Vec<AccountUpdate> {
+    let mut updates = Vec::new();
+    if unresolved.is_empty() {
+        return updates;
+    }
+
+    let included: HashSet<TxHash> = block.transactions.iter().copied().collect();
+    unresolved.retain(|(tx_hash, _, touched)| {
+        if !included.contains(tx_hash) {
+            return true;
+        }
+        updates.extend(touched.iter().map(|address| AccountUpdate::Confirmed {
+            address: *address,
+            tx_hash: *tx_hash,
+            block_number: block.number,
+        }));
+        false
+    });
+    updates
+}
+
+/// Returns true if a transaction preconfirmed in the given block was dropped without becoming
+/// canonical. A transaction that became canonical is confirmed by the canonical block, which is
+/// received before the pending state that follows it.
+fn is_reverted(
+    pending_blocks: &PendingBlocks,
+    canonical_tip: Option<BlockNumber>,
+    tx_hash: TxHash,
+    block_number: BlockNumber,
+) -> bool {
+    if pending_blocks.has_executed_transaction(&tx_hash) {
+        return false;
+    }
+    if block_number >= pending_blocks.earliest_block_number() {
+        // The block is still pending, but was rebuilt without the transaction
+        return !pending_blocks.get_transaction_hashes_for_block(block_number).contains(&tx_hash);
+    }
+    // The block is canonical, but its confirmation may not have been received yet
+    canonical_tip.is_some_and(|tip| tip >= block_number)
+}
+
+/// Returns the changes made to the pending blocks by the flashblocks after `after`: what was added
+/// to the block of `after` since, then every later pending block in full. Without `after`, only the
+/// latest pending block is sent in full.
+fn flashblock_deltas(
+    pending_blocks: &PendingBlocks,
+    after: Option<FlashblockId>,
+) -> Vec<FlashblockDelta> {
+    let latest = pending_blocks.latest_block_number();
+    let Some((after_block, after_index)) = after.filter(|(number, _)| *number <= latest) else {
+        return block_delta(pending_blocks, latest, None).into_iter().collect();
+    };
+
+    // Blocks before the earliest pending block are canonical by now
+    (after_block.max(pending_blocks.earliest_block_number())..=latest)
+        .filter_map(|block_number| {
+            if block_number != after_block {
+                return block_delta(pending_blocks, block_number, None);
+            }
+            if pending_blocks.get_latest_flashblock_index(block_number) == Some(after_index) {
+                return None;
+            }
+
+            // The whole block is sent again if the flashblock of `after` is no longer known
+            let delivered = pending_blocks
+                .get_flashblock_snapshot(block_number, after_index)
+                .map(|snapshot| snapshot.transaction_count());
+            block_delta(pending_blocks, block_number, delivered)
+        })
+        .collect()
+}
+
+/// Returns the transactions of a pending block after the first `delivered` ones, with the fields
+/// of its latest flashblock. The full header is included if the subscriber has not received any
+/// flashblock of the block yet.
+fn block_delta(
+    pending_blocks: &PendingBlocks,
+    block_number: BlockNumber,
+    delivered: Option<usize>,
+) -> Option<FlashblockDelta> {
+    let header = pending_blocks.get_header(block_number)?;
+    let index = pending_blocks.get_latest_flashblock_index(block_number)?;
+
+    let transactions: Vec<Transaction> = pending_blocks
+        .get_transactions_for_block(block_number)
+        .into_iter()
+        .skip(delivered.unwrap_or_default())
+        .collect();
+    let receipts =
+        transactions.iter().filter_map(|tx| pending_blocks.get_receipt(tx.tx_hash())).collect();
+
+    Some(FlashblockDelta {
+        block_number,
+        index,
+        header: delivered.is_none().then(|| RPCHeader::from_consensus(header.clone(), None, None)),
+        hash: header.hash(),
+        state_root: header.state_root,
+        receipts_root: header.receipts_root,
+        logs_bloom: header.logs_bloom,
+        gas_used: header.gas_used,
+        transactions,
+        receipts,
+    })
+}
+
+/// Returns the changes made to the pending block by a replayed flashblock, relative to the given
+/// flashblock.
+fn replayed_flashblock_delta(
+    replay: &FlashblockReplay,
+    after: Option<FlashblockId>,
+) -> FlashblockDelta {
+    let (block_number, index) = replay.flashblock_id();
+
+    // Transactions of the block added after the flashblock the subscriber already received
+    let added = after
+        .filter(|(number, _)| *number == block_number)
+        .and_then(|(_, index)| replay.transactions_after(index));
+    let header = added.is_none().then(|| replay.block(false).header);
+    let (transactions, receipts) = added.unwrap_or_else(|| replay.transactions());
+
+    let latest_header = replay.header();
+    FlashblockDelta {
+        block_number,
+        index,
+        header,
+        hash: latest_header.hash(),
+        state_root: latest_header.state_root,
+        receipts_root: latest_header.receipts_root,
+        logs_bloom: latest_header.logs_bloom,
+        gas_used: latest_header.gas_used,
+        transactions,
+        receipts,
+    }
+}
+
 /// Pipes all stream items to the subscription sink.
 ///
-/// This function runs until the stream ends, the client disconnects, or a serialization error occurs.
-/// All exit conditions result in graceful termination.
-async fn pipe_from_stream<T, St>(sink: SubscriptionSink, mut stream: St)
-where
-    St: Stream<Item = T> + Unpin,
-    T: Serialize,
+/// This function runs until the stream ends, the client disconnects, a notice closing the
+/// subscription is sent, or a serialization error occurs. An update larger than
+/// `max_message_size` once serialized closes the subscription with an error instead. Updates are
+/// serialized beforehand, so they are written to the message as is. All exit conditions result in
+/// graceful termination.
+async fn pipe_from_stream<St>(
+    sink: SubscriptionSink,
+    mut stream: St,
+    max_message_size: usize,
+    metrics: Metrics,
+) where
+    St: Stream<Item = Notification<SharedJson>> + Unpin,
 {
     loop {
         tokio::select! {
@@ -258,10 +1134,26 @@ where
 
             maybe_item = stream.next() => {
                 // stream ended
-                let Some(item) = maybe_item else {
+                let Some(mut item) = maybe_item else {
                     return;
                 };
 
+                if let Notification::Update(json) = &item
+                    && json.len() > max_message_size
+                {
+                    metrics.oversized_notifications.increment(1);
+                    item = Notification::Closed {
+                        error: ErrorObjectOwned::owned(
+                            SUBSCRIPTION_LIMIT_CODE,
+                            format!(
+                                "notification of {} bytes exceeds the maximum of {max_message_size} bytes",
+                                json.len()
+                            ),
+                            None::<()>,
+                        ),
+                    };
+                }
+
                 let msg = match SubscriptionMessage::new(
                     sink.method_name(),
                     sink.subscription_id(),
@@ -279,10 +1171,140 @@ where
                 };
 
                 // if it fails, client disconnected
-                if sink.send(msg).await.is_err() {
+                if sink.send(msg).await.is_err() || item.closes_subscription() {
                     return;
                 }
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use alloy_rpc_types_engine::PayloadId;
+    use base_flashtypes::{ExecutionPayloadFlashblockDeltaV1, Flashblock, Metadata};
+    use base_reth_flashblocks::FlashblockStatus;
+    use tokio::sync::broadcast;
+
+    use super::*;
+
+    fn processed(index: u64) -> Arc<ProcessedFlashblock> {
+        Arc::new(ProcessedFlashblock {
+            flashblock: Flashblock {
+                payload_id: PayloadId::new([0; 8]),
+                index,
+                base: None,
+                diff: ExecutionPayloadFlashblockDeltaV1::default(),
+                metadata: Metadata { block_number: 1 },
+            },
+            status: FlashblockStatus::Accepted,
+        })
+    }
+
+    /// Returns the notifications of a subscriber to a channel buffering a single update, which
+    /// receives the first flashblock and then falls behind three more. Notifications carry the
+    /// flashblock index and whether a full snapshot was asked for.
+    async fn lagged_notifications(policy: LagPolicy) -> Vec<Notification<(u64, bool)>> {
+        let (sender, receiver) = broadcast::channel(1);
+        let updates =
+            BroadcastStream::new(receiver).map(|result| received(result, PendingUpdate::Processed));
+        let lag = LagHandler { policy, metrics: Metrics::default() };
+        let mut notifications = lag.deliver(updates, |update, resync| {
+            let PendingUpdate::Processed(processed) = update else {
+                return None;
+            };
+            Some((processed.flashblock.index, resync))
+        });
+
+        sender.send(processed(0)).unwrap();
+        let mut received = vec![notifications.next().await.unwrap()];
+        for index in 1..4 {
+            sender.send(processed(index)).unwrap();
+        }
+        drop(sender);
+
+        while let Some(notification) = notifications.next().await {
+            let closes = notification.closes_subscription();
+            received.push(notification);
+            if closes {
+                break;
+            }
+        }
+        received
+    }
+
+    #[tokio::test]
+    async fn test_gap_policy_notifies_missed_range() {
+        let notifications = lagged_notifications(LagPolicy::Gap).await;
+
+        assert_eq!(notifications.len(), 3);
+        assert!(matches!(notifications[0], Notification::Update((0, false))));
+        let Notification::Lagged { lagged } = &notifications[1] else {
+            panic!("expected a lag notice");
+        };
+        assert_eq!(
+            *lagged,
+            SubscriptionLag {
+                policy: LagPolicy::Gap,
+                missed: 2,
+                after: Some((1, 0).into()),
+                resumed_at: Some((1, 3).into()),
+            }
+        );
+        assert!(matches!(notifications[2], Notification::Update((3, false))));
+    }
+
+    #[tokio::test]
+    async fn test_resync_policy_sends_full_snapshot() {
+        let notifications = lagged_notifications(LagPolicy::Resync).await;
+
+        assert_eq!(notifications.len(), 3);
+        let Notification::Lagged { lagged } = &notifications[1] else {
+            panic!("expected a lag notice");
+        };
+        assert_eq!(lagged.policy, LagPolicy::Resync);
+        assert_eq!(lagged.missed, 2);
+        assert!(matches!(notifications[2], Notification::Update((3, true))));
+    }
+
+    #[tokio::test]
+    async fn test_disconnect_policy_closes_subscription() {
+        let notifications = lagged_notifications(LagPolicy::Disconnect).await;
+
+        assert_eq!(notifications.len(), 2);
+        let Notification::Lagged { lagged } = &notifications[1] else {
+            panic!("expected a lag notice");
+        };
+        assert_eq!(
+            *lagged,
+            SubscriptionLag {
+                policy: LagPolicy::Disconnect,
+                missed: 2,
+                after: Some((1, 0).into()),
+                resumed_at: None,
+            }
+        );
+        assert!(notifications[1].closes_subscription());
+    }
+
+    #[test]
+    fn test_canonical_block_confirms_included_transactions() {
+        let alice = Address::with_last_byte(1);
+        let bob = Address::with_last_byte(2);
+        let included = TxHash::with_last_byte(1);
+        let pending = TxHash::with_last_byte(2);
+        let mut unresolved = vec![(included, 1, vec![alice, bob]), (pending, 1, vec![alice])];
+
+        let block = CanonicalBlock { number: 2, transactions: vec![included] };
+        let updates = confirmed_updates(&mut unresolved, &block);
+
+        assert_eq!(
+            updates,
+            vec![
+                AccountUpdate::Confirmed { address: alice, tx_hash: included, block_number: 2 },
+                AccountUpdate::Confirmed { address: bob, tx_hash: included, block_number: 2 },
+            ]
+        );
+        assert_eq!(unresolved, vec![(pending, 1, vec![alice])]);
+    }
+}
@@ -0,0 +1,150 @@
+//! Limits on the Base-specific subscriptions served by `eth_subscribe`.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use jsonrpsee::ConnectionId;
+use jsonrpsee_types::ErrorObjectOwned;
+
+use crate::{
+    BaseSubscriptionKind,
+    metrics::{Metrics, SubscriptionKindMetrics},
+};
+
+/// Error code returned when a subscription limit is exceeded.
+pub const SUBSCRIPTION_LIMIT_CODE: i32 = -32005;
+
+/// Default maximum number of Base-specific subscriptions of a single connection.
+pub const DEFAULT_MAX_SUBSCRIPTIONS_PER_CONNECTION: usize = 32;
+
+/// Default maximum number of Base-specific subscriptions across all connections.
+pub const DEFAULT_MAX_SUBSCRIPTIONS: usize = 4_096;
+
+/// Default maximum size of a serialized subscription notification, the default maximum response
+/// size of the RPC server.
+pub const DEFAULT_MAX_SUBSCRIPTION_MESSAGE_SIZE: usize = 10 * 1024 * 1024;
+
+/// Limits on the Base-specific subscriptions.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SubscriptionLimits {
+    /// Maximum number of subscriptions of a single connection.
+    pub max_per_connection: usize,
+    /// Maximum number of subscriptions across all connections.
+    pub max_total: usize,
+    /// Maximum size of a serialized notification, in bytes. A subscription is closed with an
+    /// error rather than sent a larger notification.
+    pub max_message_size: usize,
+}
+
+impl Default for SubscriptionLimits {
+    fn default() -> Self {
+        Self {
+            max_per_connection: DEFAULT_MAX_SUBSCRIPTIONS_PER_CONNECTION,
+            max_total: DEFAULT_MAX_SUBSCRIPTIONS,
+            max_message_size: DEFAULT_MAX_SUBSCRIPTION_MESSAGE_SIZE,
+        }
+    }
+}
+
+/// Counts the active Base-specific subscriptions against the limits.
+#[derive(Debug, Default)]
+pub(crate) struct SubscriptionQuotas {
+    limits: SubscriptionLimits,
+    active: Mutex<ActiveSubscriptions>,
+    metrics: Metrics,
+}
+
+#[derive(Debug, Default)]
+struct ActiveSubscriptions {
+    total: usize,
+    per_connection: HashMap<ConnectionId, usize>,
+    per_kind: HashMap<BaseSubscriptionKind, usize>,
+}
+
+impl SubscriptionQuotas {
+    /// Creates quotas enforcing the given limits.
+    pub(crate) fn new(limits: SubscriptionLimits) -> Self {
+        Self { limits, ..Default::default() }
+    }
+
+    /// Returns the limits enforced.
+    pub(crate) const fn limits(&self) -> &SubscriptionLimits {
+        &self.limits
+    }
+
+    /// Counts a new subscription of the connection, until the returned permit is dropped. Fails
+    /// if the connection or the node already has the maximum number of subscriptions.
+    pub(crate) fn acquire(
+        self: &Arc<Self>,
+        connection_id: ConnectionId,
+        kind: BaseSubscriptionKind,
+    ) -> Result<SubscriptionPermit, ErrorObjectOwned> {
+        let mut active = self.active.lock().expect("subscription quotas lock poisoned");
+
+        let connection_count = active.per_connection.get(&connection_id).copied().unwrap_or(0);
+        let exceeded = if connection_count >= self.limits.max_per_connection {
+            Some(format!(
+                "connection already has the maximum of {} flashblocks subscriptions",
+                self.limits.max_per_connection
+            ))
+        } else if active.total >= self.limits.max_total {
+            Some(format!(
+                "node already serves the maximum of {} flashblocks subscriptions",
+                self.limits.max_total
+            ))
+        } else {
+            None
+        };
+        if let Some(message) = exceeded {
+            self.metrics.rejected_subscriptions.increment(1);
+            return Err(ErrorObjectOwned::owned(SUBSCRIPTION_LIMIT_CODE, message, None::<()>));
+        }
+
+        active.total += 1;
+        *active.per_connection.entry(connection_id).or_default() += 1;
+        let kind_count = active.per_kind.entry(kind).or_default();
+        *kind_count += 1;
+        record_active(kind, *kind_count);
+
+        Ok(SubscriptionPermit { quotas: Arc::clone(self), connection_id, kind })
+    }
+
+    fn release(&self, connection_id: ConnectionId, kind: BaseSubscriptionKind) {
+        let mut active = self.active.lock().expect("subscription quotas lock poisoned");
+
+        active.total = active.total.saturating_sub(1);
+        if let Some(count) = active.per_connection.get_mut(&connection_id) {
+            *count = count.saturating_sub(1);
+            if *count == 0 {
+                active.per_connection.remove(&connection_id);
+            }
+        }
+        if let Some(count) = active.per_kind.get_mut(&kind) {
+            *count = count.saturating_sub(1);
+            record_active(kind, *count);
+        }
+    }
+}
+
+/// Records the number of active subscriptions of a kind.
+fn record_active(kind: BaseSubscriptionKind, count: usize) {
+    SubscriptionKindMetrics::new_with_labels(&[("kind", kind.as_str())])
+        .active_subscriptions
+        .set(count as f64);
+}
+
+/// An active subscription counted against the limits, released when dropped.
+#[derive(Debug)]
+pub(crate) struct SubscriptionPermit {
+    quotas: Arc<SubscriptionQuotas>,
+    connection_id: ConnectionId,
+    kind: BaseSubscriptionKind,
+}
+
+impl Drop for SubscriptionPermit {
+    fn drop(&mut self) {
+        self.quotas.release(self.connection_id, self.kind);
+    }
+}
@@ -0,0 +1,163 @@
+//! RPC implementation for querying pending state as of a specific flashblock.
+
+use std::sync::Arc;
+
+use alloy_eips::BlockNumberOrTag;
+use alloy_primitives::{Address, BlockNumber, Bytes, TxHash, U256};
+use alloy_rpc_types::state::{EvmOverrides, StateOverride, StateOverridesBuilder};
+use base_reth_flashblocks::{FlashblockSnapshot, FlashblocksAPI, PendingBlocks};
+use jsonrpsee::core::{RpcResult, async_trait};
+use jsonrpsee_types::{ErrorObjectOwned, error::INVALID_PARAMS_CODE};
+use op_alloy_network::Optimism;
+use op_alloy_rpc_types::OpTransactionRequest;
+use reth_rpc_eth_api::{
+    EthApiTypes, RpcReceipt,
+    helpers::{EthCall, EthState, FullEthApi},
+};
+use tracing::debug;
+
+use crate::FlashblockSnapshotApiServer;
+
+/// Implementation of the flashblock snapshot RPC API.
+#[derive(Debug)]
+pub struct FlashblockSnapshotApiImpl<Eth, FB> {
+    eth_api: Eth,
+    flashblocks_state: Arc<FB>,
+}
+
+impl<Eth, FB> FlashblockSnapshotApiImpl<Eth, FB> {
+    /// Creates a new flashblock snapshot API instance.
+    pub const fn new(eth_api: Eth, flashblocks_state: Arc<FB>) -> Self {
+        Self { eth_api, flashblocks_state }
+    }
+}
+
+impl<Eth, FB> FlashblockSnapshotApiImpl<Eth, FB>
+where
+    FB: FlashblocksAPI,
+{
+    /// Loads the pending state and the snapshot for the requested flashblock.
+    fn load_snapshot(
+        &self,
+        block_number: BlockNumberOrTag,
+        flashblock_index: u64,
+    ) -> RpcResult<(Arc<PendingBlocks>, Arc<FlashblockSnapshot>)> {
+        let pending_blocks = Option::clone(&self.flashblocks_state.get_pending_blocks())
+            .ok_or_else(|| invalid_params("no pending flashblocks state available"))?;
+
+        let block_number: BlockNumber = match block_number {
+            BlockNumberOrTag::Number(number) => number,
+            BlockNumberOrTag::Pending => pending_blocks.latest_block_number(),
+            other => {
+                return Err(invalid_params(format!("unsupported block tag: {other}")));
+            }
+        };
+
+        let snapshot = pending_blocks
+            .get_flashblock_snapshot(block_number, flashblock_index)
+            .ok_or_else(|| {
+                invalid_params(format!(
+                    "no snapshot for block {block_number} flashblock index {flashblock_index}"
+                ))
+            })?;
+
+        Ok((pending_blocks, snapshot))
+    }
+}
+
+#[async_trait]
+impl<Eth, FB> FlashblockSnapshotApiServer for FlashblockSnapshotApiImpl<Eth, FB>
+where
+    Eth: FullEthApi<NetworkTypes = Optimism> + EthApiTypes + Send + Sync + 'static,
+    FB: FlashblocksAPI + Send + Sync + 'static,
+    jsonrpsee_types::error::ErrorObject<'static>: From<Eth::Error>,
+{
+    async fn call_at_flashblock(
+        &self,
+        transaction: OpTransactionRequest,
+        block_number: BlockNumberOrTag,
+        flashblock_index: u64,
+        state_overrides: Option<StateOverride>,
+    ) -> RpcResult<Bytes> {
+        debug!(
+            message = "rpc::call_at_flashblock",
+            block_number = ?block_number,
+            flashblock_index,
+        );
+
+        let (pending_blocks, snapshot) = self.load_snapshot(block_number, flashblock_index)?;
+        let snapshot_overrides = pending_blocks
+            .get_state_overrides_at_flashblock(snapshot.block_number(), snapshot.index())
+            .unwrap_or_default();
+
+        // Apply user's overrides on top of the snapshot
+        let final_overrides = StateOverridesBuilder::new(snapshot_overrides)
+            .extend(state_overrides.unwrap_or_default())
+            .build();
+
+        EthCall::call(
+            &self.eth_api,
+            transaction,
+            Some(pending_blocks.canonical_block_number().into()),
+            EvmOverrides::new(Some(final_overrides), None),
+        )
+        .await
+        .map_err(Into::into)
+    }
+
+    async fn get_balance_at_flashblock(
+        &self,
+        address: Address,
+        block_number: BlockNumberOrTag,
+        flashblock_index: u64,
+    ) -> RpcResult<U256> {
+        debug!(
+            message = "rpc::get_balance_at_flashblock",
+            address = %address,
+            block_number = ?block_number,
+            flashblock_index,
+        );
+
+        let (pending_blocks, snapshot) = self.load_snapshot(block_number, flashblock_index)?;
+        if let Some(balance) = pending_blocks.get_balance_at_flashblock(
+            address,
+            snapshot.block_number(),
+            snapshot.index(),
+        ) {
+            return Ok(balance);
+        }
+
+        EthState::balance(
+            &self.eth_api,
+            address,
+            Some(pending_blocks.canonical_block_number().into()),
+        )
+        .await
+        .map_err(Into::into)
+    }
+
+    async fn get_transaction_receipt_at_flashblock(
+        &self,
+        tx_hash: TxHash,
+        block_number: BlockNumberOrTag,
+        flashblock_index: u64,
+    ) -> RpcResult<Option<RpcReceipt<Optimism>>> {
+        debug!(
+            message = "rpc::get_transaction_receipt_at_flashblock",
+            tx_hash = %tx_hash,
+            block_number = ?block_number,
+            flashblock_index,
+        );
+
+        let (pending_blocks, snapshot) = self.load_snapshot(block_number, flashblock_index)?;
+        Ok(pending_blocks.get_receipt_at_flashblock(
+            tx_hash,
+            snapshot.block_number(),
+            snapshot.index(),
+        ))
+    }
+}
+
+fn invalid_params(message: impl Into<String>) -> ErrorObjectOwned {
+    ErrorObjectOwned::owned(INVALID_PARAMS_CODE, message.into(), None::<()>)
+}
@@ -1,9 +1,13 @@
 //! Traits for the RPC module.
 
 use alloy_eips::BlockNumberOrTag;
-use alloy_primitives::{B256, TxHash};
+use alloy_primitives::{Address, B256, Bytes, TxHash, U256};
+use alloy_rpc_types_eth::state::StateOverride;
 use base_bundles::{Bundle, MeterBundleResponse};
 use jsonrpsee::{core::RpcResult, proc_macros::rpc};
+use op_alloy_network::Optimism;
+use op_alloy_rpc_types::OpTransactionRequest;
+use reth_rpc_eth_api::RpcReceipt;
 
 use crate::{MeterBlockResponse, TransactionStatusResponse};
 
@@ -11,8 +15,16 @@ use crate::{MeterBlockResponse, TransactionStatusResponse};
 #[rpc(server, namespace = "base")]
 pub trait MeteringApi {
     /// Simulates and meters a bundle of transactions
+    ///
+    /// The bundle is executed on top of the latest block, or within the latest pending block when
+    /// `pending` is passed as the state block. Pending metering requires flashblocks to be enabled,
+    /// and falls back to the latest block until the first pending block is received.
     #[method(name = "meterBundle")]
-    async fn meter_bundle(&self, bundle: Bundle) -> RpcResult<MeterBundleResponse>;
+    async fn meter_bundle(
+        &self,
+        bundle: Bundle,
+        state_block: Option<BlockNumberOrTag>,
+    ) -> RpcResult<MeterBundleResponse>;
 
     /// Handler for: `base_meterBlockByHash`
     ///
@@ -51,3 +63,54 @@ pub trait TransactionStatusApi {
     #[method(name = "transactionStatus")]
     async fn transaction_status(&self, tx_hash: TxHash) -> RpcResult<TransactionStatusResponse>;
 }
+
+/// RPC API for querying pending state as of a specific flashblock
+#[rpc(server, namespace = "base")]
+pub trait FlashblockSnapshotApi {
+    /// Handler for: `base_callAtFlashblock`
+    ///
+    /// Executes a call against the pending state exactly as it was after the given flashblock
+    /// was applied. `pending` may be passed as the block number to target the latest pending block.
+    #[method(name = "callAtFlashblock")]
+    async fn call_at_flashblock(
+        &self,
+        transaction: OpTransactionRequest,
+        block_number: BlockNumberOrTag,
+        flashblock_index: u64,
+        state_overrides: Option<StateOverride>,
+    ) -> RpcResult<Bytes>;
+
+    /// Handler for: `base_getBalanceAtFlashblock`
+    ///
+    /// Returns the balance of an address after the given flashblock was applied.
+    #[method(name = "getBalanceAtFlashblock")]
+    async fn get_balance_at_flashblock(
+        &self,
+        address: Address,
+        block_number: BlockNumberOrTag,
+        flashblock_index: u64,
+    ) -> RpcResult<U256>;
+
+    /// Handler for: `base_getTransactionReceiptAtFlashblock`
+    ///
+    /// Returns the receipt of a transaction if it was included at or before the given flashblock.
+    #[method(name = "getTransactionReceiptAtFlashblock")]
+    async fn get_transaction_receipt_at_flashblock(
+        &self,
+        tx_hash: TxHash,
+        block_number: BlockNumberOrTag,
+        flashblock_index: u64,
+    ) -> RpcResult<Option<RpcReceipt<Optimism>>>;
+}
+
+/// RPC API for fee estimation based on pending flashblocks
+#[rpc(server, namespace = "base")]
+pub trait FlashblocksFeeApi {
+    /// Handler for: `base_estimateNextBaseFee`
+    ///
+    /// Predicts the base fee of the block following the latest pending block from the gas used
+    /// by the pending block so far. Falls back to the block following the latest canonical block
+    /// when there is no pending state.
+    #[method(name = "estimateNextBaseFee")]
+    async fn estimate_next_base_fee(&self) -> RpcResult<U256>;
+}
@@ -1,7 +1,15 @@
 //! Types for the transaction status rpc
 
-use alloy_primitives::B256;
-use alloy_rpc_types_eth::pubsub::SubscriptionKind;
+use std::str::FromStr;
+
+use alloy_primitives::{Address, B256, BlockNumber, Bloom, TxHash, U256};
+use alloy_rpc_types_eth::{Header, pubsub::SubscriptionKind};
+use base_flashtypes::Flashblock;
+use base_reth_flashblocks::{
+    FlashblockId, FlashblockStatus, Invalidation, InvalidationReason, PendingBlocks,
+    ProcessedFlashblock,
+};
+use op_alloy_rpc_types::{OpTransactionReceipt, Transaction};
 use serde::{Deserialize, Serialize};
 
 /// The status of a transaction.
@@ -20,6 +28,302 @@ pub struct TransactionStatusResponse {
     pub status: Status,
 }
 
+/// The confirmation level a transaction sent with `eth_sendRawTransactionSync` has reached.
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(rename_all = "camelCase")]
+pub enum ConfirmationLevel {
+    /// Included in a flashblock by the sequencer.
+    #[default]
+    Preconfirmed,
+    /// Included in the canonical chain (unsafe head).
+    Canonical,
+    /// Included in a block at or below the safe head.
+    Safe,
+}
+
+/// Response of `eth_sendRawTransactionSync`, containing the receipt of the transaction together
+/// with the confirmation level it reached.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SendRawTransactionSyncResponse<R> {
+    /// The transaction receipt.
+    #[serde(flatten)]
+    pub receipt: R,
+    /// The confirmation level the transaction reached.
+    pub confirmation_level: ConfirmationLevel,
+}
+
+/// Identifies the pending state an RPC response was served from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingSnapshot {
+    /// The number of the latest pending block.
+    pub block_number: BlockNumber,
+    /// The index of the latest flashblock applied to the pending block.
+    pub flashblock_index: u64,
+}
+
+impl PendingSnapshot {
+    /// Returns the id of the latest flashblock of the snapshot.
+    pub const fn flashblock_id(&self) -> FlashblockId {
+        (self.block_number, self.flashblock_index)
+    }
+}
+
+impl From<FlashblockId> for PendingSnapshot {
+    fn from((block_number, flashblock_index): FlashblockId) -> Self {
+        Self { block_number, flashblock_index }
+    }
+}
+
+impl From<&PendingBlocks> for PendingSnapshot {
+    fn from(pending_blocks: &PendingBlocks) -> Self {
+        Self {
+            block_number: pending_blocks.latest_block_number(),
+            flashblock_index: pending_blocks.latest_flashblock_index(),
+        }
+    }
+}
+
+/// An RPC response that may have been served from the pending state. When it was, the response
+/// carries the pending snapshot it was read from.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingResponse<T> {
+    /// The response.
+    #[serde(flatten)]
+    pub inner: T,
+    /// The pending snapshot the response was served from, if it was served from pending state.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pending_snapshot: Option<PendingSnapshot>,
+}
+
+impl<T> PendingResponse<T> {
+    /// Creates a response served from the canonical chain.
+    pub const fn canonical(inner: T) -> Self {
+        Self { inner, pending_snapshot: None }
+    }
+
+    /// Creates a response served from the given pending state.
+    pub fn pending(inner: T, pending_blocks: &PendingBlocks) -> Self {
+        Self { inner, pending_snapshot: Some(pending_blocks.into()) }
+    }
+}
+
+/// Options of a `newFlashblocks` subscription.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct NewFlashblocksOptions {
+    /// Whether to send [`FlashblockDelta`]s rather than full pending blocks.
+    #[serde(default)]
+    pub delta: bool,
+    /// The last flashblock received before reconnecting. The flashblocks received by the node
+    /// since then are replayed before live ones.
+    #[serde(default)]
+    pub cursor: Option<PendingSnapshot>,
+}
+
+/// Changes made to the pending block by the flashblocks received since the previous notification
+/// of a `newFlashblocks` subscription in delta mode.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FlashblockDelta {
+    /// Number of the pending block.
+    pub block_number: BlockNumber,
+    /// Index of the newest flashblock applied to the pending block.
+    pub index: u64,
+    /// Full header of the pending block, only included in the first notification for a block.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub header: Option<Header>,
+    /// Hash of the pending block.
+    pub hash: B256,
+    /// State root of the pending block.
+    pub state_root: B256,
+    /// Receipts root of the pending block.
+    pub receipts_root: B256,
+    /// Logs bloom of the pending block.
+    pub logs_bloom: Bloom,
+    /// Gas used by the pending block.
+    pub gas_used: u64,
+    /// Transactions added to the pending block.
+    pub transactions: Vec<Transaction>,
+    /// Receipts of the transactions added to the pending block.
+    pub receipts: Vec<OpTransactionReceipt>,
+}
+
+/// Address filter of a `newPreconfirmedTransactions` subscription.
+///
+/// A transaction matches if it was sent from one of the `from` addresses or to one of the `to`
+/// addresses. A filter without any address matches every transaction.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct TransactionAddressFilter {
+    /// Senders to match.
+    #[serde(default)]
+    pub from: Vec<Address>,
+    /// Recipients to match.
+    #[serde(default)]
+    pub to: Vec<Address>,
+}
+
+impl TransactionAddressFilter {
+    /// Returns true if a transaction with the given sender and recipient matches the filter.
+    pub fn matches(&self, from: Address, to: Option<Address>) -> bool {
+        if self.from.is_empty() && self.to.is_empty() {
+            return true;
+        }
+
+        self.from.contains(&from) || to.is_some_and(|to| self.to.contains(&to))
+    }
+}
+
+/// A transaction preconfirmed in a flashblock, together with its receipt.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PreconfirmedTransaction {
+    /// The preconfirmed transaction.
+    pub transaction: Transaction,
+    /// The receipt of the transaction in the pending state.
+    pub receipt: OpTransactionReceipt,
+}
+
+/// A change to a watched account notified by a `pendingAccountUpdates` subscription.
+///
+/// Every transaction that touches a watched account is first notified as
+/// [`Preconfirmed`](Self::Preconfirmed), and later either as [`Confirmed`](Self::Confirmed) once
+/// its block is canonical or as [`Reverted`](Self::Reverted) if it was dropped from the pending
+/// state without becoming canonical.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "camelCase", rename_all_fields = "camelCase")]
+pub enum AccountUpdate {
+    /// The account was changed by a transaction preconfirmed in a flashblock.
+    Preconfirmed {
+        /// The watched account.
+        address: Address,
+        /// Balance of the account after the transaction.
+        balance: U256,
+        /// Nonce of the account after the transaction.
+        nonce: u64,
+        /// Hash of the transaction.
+        tx_hash: TxHash,
+        /// Number of the pending block that includes the transaction.
+        block_number: BlockNumber,
+        /// Index of the flashblock that includes the transaction.
+        flashblock_index: u64,
+    },
+    /// The transaction that changed the account was included in the canonical chain.
+    Confirmed {
+        /// The watched account.
+        address: Address,
+        /// Hash of the transaction.
+        tx_hash: TxHash,
+        /// Number of the canonical block that includes the transaction.
+        block_number: BlockNumber,
+    },
+    /// The transaction that changed the account was dropped from the pending state, so the
+    /// preconfirmed change no longer applies.
+    Reverted {
+        /// The watched account.
+        address: Address,
+        /// Hash of the transaction.
+        tx_hash: TxHash,
+        /// Number of the pending block that included the transaction.
+        block_number: BlockNumber,
+    },
+}
+
+/// Preconfirmed transactions dropped from the pending state, notified by a
+/// `pendingInvalidations` subscription.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingInvalidation {
+    /// Why the transactions were dropped.
+    pub reason: InvalidationReason,
+    /// The canonical block that triggered the invalidation, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub canonical_block_number: Option<BlockNumber>,
+    /// Hashes of the dropped transactions.
+    pub transactions: Vec<TxHash>,
+}
+
+impl From<&Invalidation> for PendingInvalidation {
+    fn from(invalidation: &Invalidation) -> Self {
+        Self {
+            reason: invalidation.reason,
+            canonical_block_number: invalidation.canonical_block_number,
+            transactions: invalidation.transactions.clone(),
+        }
+    }
+}
+
+/// A flashblock exactly as sent by the builder, with how the node processed it, notified by a
+/// `newRawFlashblocks` subscription.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RawFlashblock {
+    /// The flashblock received from upstream.
+    #[serde(flatten)]
+    pub flashblock: Flashblock,
+    /// How the flashblock was processed.
+    #[serde(flatten)]
+    pub status: FlashblockStatus,
+}
+
+impl From<&ProcessedFlashblock> for RawFlashblock {
+    fn from(processed: &ProcessedFlashblock) -> Self {
+        Self { flashblock: processed.flashblock.clone(), status: processed.status.clone() }
+    }
+}
+
+/// What a Base subscription does when the subscriber falls so far behind that buffered pending
+/// state updates are dropped.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum LagPolicy {
+    /// Notify the missed range, then continue from where the subscription left off.
+    #[default]
+    Gap,
+    /// Notify the missed range, then send a full snapshot of the pending state rather than only
+    /// what changed since the previous notification.
+    Resync,
+    /// Notify the missed range, then close the subscription.
+    Disconnect,
+}
+
+impl FromStr for LagPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "gap" => Ok(Self::Gap),
+            "resync" => Ok(Self::Resync),
+            "disconnect" => Ok(Self::Disconnect),
+            _ => Err(format!("unknown lag policy {s:?}, expected gap, resync or disconnect")),
+        }
+    }
+}
+
+/// Notice sent to a Base subscription that fell behind the pending state and missed updates,
+/// notified as `{"lagged": ...}` in place of a regular notification.
+///
+/// The missed flashblocks are the ones between `after` and `resumedAt`, both excluded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubscriptionLag {
+    /// What the subscription does next.
+    pub policy: LagPolicy,
+    /// Number of pending state updates that were dropped.
+    pub missed: u64,
+    /// The last flashblock received before falling behind, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub after: Option<PendingSnapshot>,
+    /// The flashblock the subscription resumes from. Absent when the subscription is closed or
+    /// resumes with an invalidation.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resumed_at: Option<PendingSnapshot>,
+}
+
 /// Extended subscription kind that includes both standard Ethereum subscription types
 /// and flashblocks-specific types.
 ///
@@ -51,12 +355,25 @@ pub enum BaseSubscriptionKind {
     /// pending block state. Each flashblock represents an incremental update to the pending
     /// block, so multiple notifications may be emitted for the same block height as new
     /// flashblocks arrive.
+    ///
+    /// Accepts an optional boolean parameter:
+    /// - `true`: Returns a [`FlashblockDelta`] with only what changed since the previous
+    ///   notification
+    /// - `false` (default): Returns the full pending block, with the `pendingSnapshot` it was
+    ///   built from
+    ///
+    /// or [`NewFlashblocksOptions`] with a `cursor` to resume from after reconnecting. The
+    /// flashblocks after the cursor are replayed before live ones, as long as the node still
+    /// keeps the flashblock of the cursor.
     NewFlashblocks,
     /// Pending logs subscription.
     ///
     /// Returns logs from flashblocks pending state that match the given filter criteria.
     /// Unlike standard `logs` subscription which only includes logs from confirmed blocks,
     /// this includes logs from the current pending flashblock state.
+    ///
+    /// Logs that were sent and then dropped from the pending state are sent again with
+    /// `removed: true`.
     PendingLogs,
     /// New flashblock transactions subscription.
     ///
@@ -68,6 +385,52 @@ pub enum BaseSubscriptionKind {
     /// - `true`: Returns full transaction objects
     /// - `false` (default): Returns only transaction hashes
     NewFlashblockTransactions,
+    /// Preconfirmed transactions subscription.
+    ///
+    /// Returns each transaction matching a [`TransactionAddressFilter`] exactly once, together
+    /// with its receipt, as soon as it is preconfirmed in a flashblock. Only transactions
+    /// preconfirmed after the subscription was created are returned.
+    ///
+    /// Accepts an optional object parameter with `from` and `to` address lists. Without it,
+    /// every preconfirmed transaction is returned.
+    NewPreconfirmedTransactions,
+    /// Pending account updates subscription.
+    ///
+    /// Returns an [`AccountUpdate`] with the balance and nonce of a watched account after every
+    /// preconfirmed transaction that touches it, followed by a confirmation once the transaction
+    /// is canonical or a revert notice if it was dropped. Confirmations and reverts are notified
+    /// with the next flashblock after the canonical block.
+    ///
+    /// Requires a parameter with the list of addresses to watch.
+    PendingAccountUpdates,
+    /// Pending invalidations subscription.
+    ///
+    /// Returns a [`PendingInvalidation`] whenever preconfirmed transactions are dropped from the
+    /// pending state, because of a reorg, a mismatch with the canonical chain or missed
+    /// flashblocks.
+    PendingInvalidations,
+    /// Raw flashblocks subscription.
+    ///
+    /// Returns every flashblock received from upstream as a [`RawFlashblock`], with the
+    /// `payload_id`, `index`, `base`, `diff` and `metadata` sent by the builder and whether the
+    /// node accepted it, ignored it as a duplicate or rejected it, with the reason. Meant for
+    /// debugging the quality of the upstream flashblocks.
+    NewRawFlashblocks,
+}
+
+impl BaseSubscriptionKind {
+    /// Returns the name of the subscription kind, as passed to `eth_subscribe`.
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::NewFlashblocks => "newFlashblocks",
+            Self::PendingLogs => "pendingLogs",
+            Self::NewFlashblockTransactions => "newFlashblockTransactions",
+            Self::NewPreconfirmedTransactions => "newPreconfirmedTransactions",
+            Self::PendingAccountUpdates => "pendingAccountUpdates",
+            Self::PendingInvalidations => "pendingInvalidations",
+            Self::NewRawFlashblocks => "newRawFlashblocks",
+        }
+    }
 }
 
 impl ExtendedSubscriptionKind {
@@ -134,3 +497,17 @@ pub struct MeterBlockTransactions {
     /// Execution time in microseconds
     pub execution_time_us: u128,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lag_policy_from_str() {
+        assert_eq!("gap".parse::<LagPolicy>(), Ok(LagPolicy::Gap));
+        assert_eq!("resync".parse::<LagPolicy>(), Ok(LagPolicy::Resync));
+        assert_eq!("disconnect".parse::<LagPolicy>(), Ok(LagPolicy::Disconnect));
+        assert!("Gap".parse::<LagPolicy>().is_err());
+        assert!("".parse::<LagPolicy>().is_err());
+    }
+}
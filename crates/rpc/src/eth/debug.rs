@@ -0,0 +1,316 @@
+//! Debug API overrides for flashblocks.
+
+use std::sync::Arc;
+
+use alloy_consensus::{Header, Transaction as _, transaction::Recovered};
+use alloy_eips::BlockId;
+use alloy_primitives::TxHash;
+use alloy_rpc_types::state::StateOverridesBuilder;
+use alloy_rpc_types_trace::geth::{
+    GethDebugBuiltInTracerType, GethDebugTracerType, GethDebugTracingCallOptions,
+    GethDebugTracingOptions, GethTrace,
+};
+use base_reth_flashblocks::{FlashblocksAPI, PendingBlocks, PendingBlocksAPI};
+use jsonrpsee::{
+    Extensions,
+    core::{RpcResult, async_trait},
+    proc_macros::rpc,
+};
+use jsonrpsee_types::{
+    ErrorObjectOwned,
+    error::{INTERNAL_ERROR_CODE, INVALID_PARAMS_CODE},
+};
+use op_alloy_network::TransactionResponse;
+use op_alloy_rpc_types::OpTransactionRequest;
+use reth::{
+    revm::{DatabaseCommit, database::StateProviderDatabase, db::CacheDB},
+    rpc::api::DebugApiServer,
+};
+use reth_evm::{ConfigureEvm, Evm, EvmEnvFor, overrides::apply_state_overrides};
+use reth_optimism_chainspec::OpChainSpec;
+use reth_optimism_evm::{OpEvmConfig, OpNextBlockEnvAttributes};
+use reth_optimism_primitives::OpTransactionSigned;
+use reth_provider::{ChainSpecProvider, HeaderProvider, StateProviderBox, StateProviderFactory};
+use revm_inspectors::tracing::{TracingInspector, TracingInspectorConfig};
+use tracing::debug;
+
+use crate::{PinnedPendingBlocks, metrics::Metrics};
+
+/// Debug API override trait for flashblocks integration.
+#[rpc(server, namespace = "debug")]
+pub trait DebugApiOverride {
+    /// Traces a call with flashblock state support.
+    #[method(name = "traceCall", with_extensions)]
+    async fn debug_trace_call(
+        &self,
+        request: OpTransactionRequest,
+        block_id: Option<BlockId>,
+        opts: Option<GethDebugTracingCallOptions>,
+    ) -> RpcResult<GethTrace>;
+
+    /// Traces a transaction, including transactions only preconfirmed in flashblocks.
+    #[method(name = "traceTransaction", with_extensions)]
+    async fn debug_trace_transaction(
+        &self,
+        tx_hash: TxHash,
+        opts: Option<GethDebugTracingOptions>,
+    ) -> RpcResult<GethTrace>;
+}
+
+/// Extended Debug API with flashblocks support.
+#[derive(Debug)]
+pub struct DebugApiExt<Debug, Provider, FB> {
+    debug_api: Debug,
+    provider: Provider,
+    flashblocks_state: Arc<FB>,
+    metrics: Metrics,
+}
+
+impl<Debug, Provider, FB> DebugApiExt<Debug, Provider, FB> {
+    /// Creates a new extended Debug API instance with flashblocks support. The provider is used
+    /// to replay preconfirmed transactions on top of the canonical state.
+    pub fn new(debug_api: Debug, provider: Provider, flashblocks_state: Arc<FB>) -> Self {
+        Self { debug_api, provider, flashblocks_state, metrics: Metrics::default() }
+    }
+}
+
+#[async_trait]
+impl<Debug, Provider, FB> DebugApiOverrideServer for DebugApiExt<Debug, Provider, FB>
+where
+    Debug: DebugApiServer<OpTransactionRequest> + Send + Sync + 'static,
+    Provider: StateProviderFactory
+        + ChainSpecProvider<ChainSpec = OpChainSpec>
+        + HeaderProvider<Header = Header>
+        + Send
+        + Sync
+        + 'static,
+    FB: FlashblocksAPI + Send + Sync + 'static,
+{
+    async fn debug_trace_call(
+        &self,
+        ext: &Extensions,
+        request: OpTransactionRequest,
+        block_id: Option<BlockId>,
+        opts: Option<GethDebugTracingCallOptions>,
+    ) -> RpcResult<GethTrace> {
+        debug!(
+            message = "rpc::debug_trace_call",
+            block_id = ?block_id,
+        );
+
+        if !block_id.unwrap_or_default().is_pending() {
+            return self.debug_api.debug_trace_call(request, block_id, opts).await;
+        }
+
+        // Trace on top of the canonical parent with the pending state applied as overrides
+        self.metrics.debug_trace_call.increment(1);
+        let (block_id, pending_overrides) = {
+            let pending_blocks = self.pending_blocks(ext);
+            (
+                pending_blocks.get_canonical_block_number().into(),
+                pending_blocks.get_state_overrides(),
+            )
+        };
+
+        let mut opts = opts.unwrap_or_default();
+        opts.state_overrides = Some(
+            StateOverridesBuilder::new(pending_overrides.unwrap_or_default())
+                .extend(opts.state_overrides.unwrap_or_default())
+                .build(),
+        );
+
+        self.debug_api.debug_trace_call(request, Some(block_id), Some(opts)).await
+    }
+
+    async fn debug_trace_transaction(
+        &self,
+        ext: &Extensions,
+        tx_hash: TxHash,
+        opts: Option<GethDebugTracingOptions>,
+    ) -> RpcResult<GethTrace> {
+        debug!(
+            message = "rpc::debug_trace_transaction",
+            tx_hash = %tx_hash
+        );
+
+        // Check canonical chain first to avoid race condition where flashblocks
+        // state hasn't been cleared yet after canonical block commit
+        let canonical_trace = self.debug_api.debug_trace_transaction(tx_hash, opts.clone()).await;
+        if canonical_trace.is_ok() {
+            return canonical_trace;
+        }
+
+        let Some(pending_blocks) = self.pending_blocks(ext) else {
+            return canonical_trace;
+        };
+        if pending_blocks.get_transaction_by_hash(tx_hash).is_none() {
+            return canonical_trace;
+        };
+
+        self.metrics.debug_trace_transaction.increment(1);
+        self.trace_pending_transaction(&pending_blocks, tx_hash, opts.unwrap_or_default())
+    }
+}
+
+impl<Debug, Provider, FB> DebugApiExt<Debug, Provider, FB>
+where
+    FB: FlashblocksAPI,
+{
+    /// Returns the pending state pinned for the call or its batch, or else the latest one.
+    fn pending_blocks(&self, ext: &Extensions) -> Option<Arc<PendingBlocks>> {
+        match ext.get::<PinnedPendingBlocks>() {
+            Some(pinned) => pinned.pending_blocks(),
+            None => Option::clone(&self.flashblocks_state.get_pending_blocks()),
+        }
+    }
+}
+
+impl<Debug, Provider, FB> DebugApiExt<Debug, Provider, FB>
+where
+    Provider: StateProviderFactory
+        + ChainSpecProvider<ChainSpec = OpChainSpec>
+        + HeaderProvider<Header = Header>,
+{
+    /// Traces a preconfirmed transaction on top of the state its pending block was built on, the
+    /// canonical parent with the changes of the earlier pending blocks applied, after replaying
+    /// the transactions preceding it in its own block.
+    fn trace_pending_transaction(
+        &self,
+        pending_blocks: &PendingBlocks,
+        tx_hash: TxHash,
+        opts: GethDebugTracingOptions,
+    ) -> RpcResult<GethTrace> {
+        let block_number = pending_blocks
+            .get_transaction_by_hash(tx_hash)
+            .and_then(|transaction| transaction.inner.block_number)
+            .ok_or_else(|| invalid_params("transaction is not pending"))?;
+        let header = pending_blocks
+            .get_header(block_number)
+            .ok_or_else(|| invalid_params("pending block of the transaction was pruned"))?;
+        let parent = match pending_blocks.get_header(block_number - 1) {
+            Some(parent) => parent.into_inner(),
+            None => self
+                .provider
+                .header_by_number(block_number - 1)
+                .map_err(internal_error)?
+                .ok_or_else(|| internal_error("parent of the pending block not found"))?,
+        };
+
+        let transactions = pending_blocks.get_transactions_for_block(block_number);
+        let position = transactions
+            .iter()
+            .position(|transaction| transaction.tx_hash() == tx_hash)
+            .ok_or_else(|| invalid_params("pending block of the transaction was pruned"))?;
+
+        let state_provider = self
+            .provider
+            .state_by_block_number_or_tag(pending_blocks.canonical_block_number())
+            .map_err(internal_error)?;
+        let mut db = CacheDB::new(StateProviderDatabase::new(state_provider));
+        apply_state_overrides(
+            pending_blocks.get_state_overrides_before_block(block_number),
+            &mut db,
+        )
+        .map_err(internal_error)?;
+
+        let evm_config = OpEvmConfig::optimism(self.provider.chain_spec());
+        let attributes = OpNextBlockEnvAttributes {
+            timestamp: header.timestamp,
+            suggested_fee_recipient: header.beneficiary,
+            prev_randao: header.mix_hash,
+            gas_limit: header.gas_limit,
+            parent_beacon_block_root: header.parent_beacon_block_root,
+            extra_data: header.extra_data.clone(),
+        };
+        let evm_env = evm_config.next_evm_env(&parent, &attributes).map_err(internal_error)?;
+
+        // The recorded state of a preceding transaction is committed as is, it is only
+        // re-executed when that state was pruned
+        for transaction in &transactions[..position] {
+            let state = match pending_blocks.get_transaction_state(&transaction.tx_hash()) {
+                Some(state) => state,
+                None => {
+                    evm_config
+                        .evm_with_env(&mut db, evm_env.clone())
+                        .transact(&transaction.inner.inner)
+                        .map_err(internal_error)?
+                        .state
+                }
+            };
+            db.commit(state);
+        }
+
+        trace_transaction(&evm_config, evm_env, &mut db, &transactions[position].inner.inner, opts)
+    }
+}
+
+/// Runs the requested tracer on a transaction executed on top of the given database. Supports the
+/// opcode logger, `callTracer` and `prestateTracer`.
+fn trace_transaction(
+    evm_config: &OpEvmConfig,
+    evm_env: EvmEnvFor<OpEvmConfig>,
+    db: &mut CacheDB<StateProviderDatabase<StateProviderBox>>,
+    transaction: &Recovered<OpTransactionSigned>,
+    opts: GethDebugTracingOptions,
+) -> RpcResult<GethTrace> {
+    let GethDebugTracingOptions { config, tracer, tracer_config, .. } = opts;
+
+    match tracer {
+        None => {
+            let mut inspector =
+                TracingInspector::new(TracingInspectorConfig::from_geth_config(&config));
+            let result = evm_config
+                .evm_with_env_and_inspector(&mut *db, evm_env, &mut inspector)
+                .transact(transaction)
+                .map_err(internal_error)?
+                .result;
+            let gas_used = result.gas_used();
+            let return_value = result.into_output().unwrap_or_default();
+            Ok(inspector.into_geth_builder().geth_traces(gas_used, return_value, config).into())
+        }
+        Some(GethDebugTracerType::BuiltInTracer(GethDebugBuiltInTracerType::CallTracer)) => {
+            let call_config =
+                tracer_config.into_call_config().map_err(|e| invalid_params(e.to_string()))?;
+            let mut inspector =
+                TracingInspector::new(TracingInspectorConfig::from_geth_call_config(&call_config));
+            let result = evm_config
+                .evm_with_env_and_inspector(&mut *db, evm_env, &mut inspector)
+                .transact(transaction)
+                .map_err(internal_error)?
+                .result;
+            Ok(inspector
+                .with_transaction_gas_limit(transaction.gas_limit())
+                .into_geth_builder()
+                .geth_call_traces(call_config, result.gas_used())
+                .into())
+        }
+        Some(GethDebugTracerType::BuiltInTracer(GethDebugBuiltInTracerType::PreStateTracer)) => {
+            let prestate_config =
+                tracer_config.into_pre_state_config().map_err(|e| invalid_params(e.to_string()))?;
+            let mut inspector = TracingInspector::new(
+                TracingInspectorConfig::from_geth_prestate_config(&prestate_config),
+            );
+            let result_and_state = evm_config
+                .evm_with_env_and_inspector(&mut *db, evm_env, &mut inspector)
+                .transact(transaction)
+                .map_err(internal_error)?;
+            let frame = inspector
+                .with_transaction_gas_limit(transaction.gas_limit())
+                .into_geth_builder()
+                .geth_prestate_traces(&result_and_state, &prestate_config, &*db)
+                .map_err(internal_error)?;
+            Ok(frame.into())
+        }
+        Some(tracer) => Err(invalid_params(format!(
+            "tracer {tracer:?} is not supported for pending transactions"
+        ))),
+    }
+}
+
+fn internal_error(error: impl ToString) -> ErrorObjectOwned {
+    ErrorObjectOwned::owned(INTERNAL_ERROR_CODE, error.to_string(), None::<()>)
+}
+
+fn invalid_params(message: impl Into<String>) -> ErrorObjectOwned {
+    ErrorObjectOwned::owned(INVALID_PARAMS_CODE, message.into(), None::<()>)
+}
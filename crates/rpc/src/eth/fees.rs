@@ -0,0 +1,114 @@
+//! Fee estimation from transactions preconfirmed in flashblocks.
+
+use alloy_eips::BlockId;
+use alloy_primitives::BlockNumber;
+use alloy_rpc_types_eth::FeeHistory;
+use base_reth_flashblocks::PendingBlocks;
+use op_alloy_network::TransactionResponse;
+use reth::rpc::server_types::eth::EthApiError;
+
+/// Percentile of pending priority fees, weighted by gas used, suggested as the priority fee.
+pub(crate) const PENDING_TIP_PERCENTILE: f64 = 60.0;
+
+/// Returns the priority fees paid by the non-deposit transactions of a pending block together
+/// with their gas used, sorted by priority fee.
+fn block_tips(pending_blocks: &PendingBlocks, block_number: BlockNumber) -> Vec<(u128, u64)> {
+    let Some(header) = pending_blocks.get_header(block_number) else {
+        return Vec::new();
+    };
+    let base_fee = header.base_fee_per_gas.unwrap_or_default() as u128;
+
+    let mut tips: Vec<(u128, u64)> = pending_blocks
+        .get_transactions_for_block(block_number)
+        .iter()
+        .filter(|tx| !tx.inner.inner.is_deposit())
+        .filter_map(|tx| pending_blocks.get_receipt(tx.tx_hash()))
+        .map(|receipt| {
+            (receipt.inner.effective_gas_price.saturating_sub(base_fee), receipt.inner.gas_used)
+        })
+        .collect();
+    tips.sort_unstable_by_key(|(tip, _)| *tip);
+    tips
+}
+
+/// Returns the priority fees at the given percentiles of gas used in a pending block, in the same
+/// way as the rewards of `eth_feeHistory`.
+pub(crate) fn block_rewards(
+    pending_blocks: &PendingBlocks,
+    block_number: BlockNumber,
+    percentiles: &[f64],
+) -> Vec<u128> {
+    let tips = block_tips(pending_blocks, block_number);
+    if tips.is_empty() {
+        return vec![0; percentiles.len()];
+    }
+
+    let gas_used: u64 = tips.iter().map(|(_, gas_used)| gas_used).sum();
+    let mut index = 0;
+    let mut cumulative_gas_used = tips[0].1;
+    percentiles
+        .iter()
+        .map(|percentile| {
+            let threshold = (gas_used as f64 * percentile / 100.0) as u64;
+            while cumulative_gas_used < threshold && index < tips.len() - 1 {
+                index += 1;
+                cumulative_gas_used += tips[index].1;
+            }
+            tips[index].0
+        })
+        .collect()
+}
+
+/// Returns the priority fee suggested by the transactions in the latest pending block.
+pub(crate) fn suggested_priority_fee(pending_blocks: &PendingBlocks) -> u128 {
+    block_rewards(pending_blocks, pending_blocks.latest_block_number(), &[PENDING_TIP_PERCENTILE])
+        [0]
+}
+
+/// Returns whether reward percentiles are valid for `eth_feeHistory`: within `[0, 100]` and
+/// monotonically increasing.
+pub(crate) fn valid_reward_percentiles(percentiles: &[f64]) -> bool {
+    percentiles.iter().all(|percentile| (0.0..=100.0).contains(percentile))
+        && percentiles.windows(2).all(|pair| pair[0] <= pair[1])
+}
+
+/// Extends a fee history ending at the block before `first_pending` with the pending blocks up to
+/// the latest one.
+///
+/// Returns an error if the header of one of those pending blocks is missing, as the history would
+/// otherwise skip a block.
+pub(crate) fn extend_fee_history(
+    mut history: FeeHistory,
+    pending_blocks: &PendingBlocks,
+    first_pending: BlockNumber,
+    reward_percentiles: Option<&[f64]>,
+) -> Result<FeeHistory, EthApiError> {
+    // The base fees of the block following the history are replaced by the pending blocks
+    history.base_fee_per_gas.pop();
+    let base_fee_per_blob_gas = history.base_fee_per_blob_gas.pop().unwrap_or_default();
+
+    let mut next_base_fee = None;
+    for block_number in first_pending..=pending_blocks.latest_block_number() {
+        let header = pending_blocks
+            .get_header(block_number)
+            .ok_or(EthApiError::HeaderNotFound(BlockId::number(block_number)))?;
+
+        history.base_fee_per_gas.push(header.base_fee_per_gas.unwrap_or_default() as u128);
+        history.gas_used_ratio.push(if header.gas_limit == 0 {
+            0.0
+        } else {
+            header.gas_used as f64 / header.gas_limit as f64
+        });
+        history.base_fee_per_blob_gas.push(base_fee_per_blob_gas);
+        history.blob_gas_used_ratio.push(0.0);
+        if let (Some(rewards), Some(percentiles)) = (history.reward.as_mut(), reward_percentiles) {
+            rewards.push(block_rewards(pending_blocks, block_number, percentiles));
+        }
+        next_base_fee = header.base_fee_per_gas;
+    }
+
+    let next_base_fee = pending_blocks.get_next_base_fee().or(next_base_fee).unwrap_or_default();
+    history.base_fee_per_gas.push(next_base_fee as u128);
+    history.base_fee_per_blob_gas.push(base_fee_per_blob_gas);
+    Ok(history)
+}
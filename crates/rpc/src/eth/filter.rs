@@ -0,0 +1,221 @@
+//! Flashblocks state of installed polling filters.
+
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use alloy_eips::BlockNumberOrTag;
+use alloy_primitives::{
+    BlockNumber, TxHash,
+    map::foldhash::{HashMap, HashMapExt, HashSet},
+};
+use alloy_rpc_types_eth::{Filter, FilterBlockOption, FilterChanges, FilterId, Log};
+use base_reth_flashblocks::PendingBlocks;
+use op_alloy_network::TransactionResponse;
+use op_alloy_rpc_types::Transaction;
+
+/// Time after which a filter that is not polled is dropped, matching how long reth keeps it.
+const STALE_FILTER_TTL: Duration = Duration::from_secs(5 * 60);
+
+/// Identifies a log by the transaction that emitted it and its index in the block.
+type LogKey = (TxHash, u64);
+
+fn log_key(log: &Log) -> LogKey {
+    (log.transaction_hash.unwrap_or_default(), log.log_index.unwrap_or_default())
+}
+
+/// Pending state delivered to a polling filter.
+#[derive(Debug)]
+enum PendingFilter {
+    /// A log filter, with the pending logs delivered that are not yet canonical.
+    Logs { filter: Filter, delivered: HashMap<LogKey, Log> },
+    /// A pending transaction filter, with the transactions delivered that are not yet canonical.
+    Transactions { full: bool, delivered: HashSet<TxHash> },
+}
+
+/// A tracked filter with the time it was last polled.
+#[derive(Debug)]
+struct TrackedFilter {
+    filter: PendingFilter,
+    last_poll: Instant,
+}
+
+impl TrackedFilter {
+    fn new(filter: PendingFilter) -> Self {
+        Self { filter, last_poll: Instant::now() }
+    }
+}
+
+/// Tracks the pending flashblock logs and transactions delivered to each polling filter, so that
+/// every poll only returns what is new since the last one.
+#[derive(Debug, Default)]
+pub(crate) struct PendingFilters {
+    filters: Mutex<HashMap<FilterId, TrackedFilter>>,
+}
+
+impl PendingFilters {
+    /// Tracks a log filter if it follows the chain head into the pending state.
+    pub(crate) fn install_logs(&self, id: FilterId, filter: Filter) {
+        if !includes_pending(&filter) {
+            return;
+        }
+
+        self.insert(id, PendingFilter::Logs { filter, delivered: HashMap::new() });
+    }
+
+    /// Tracks a pending transaction filter.
+    pub(crate) fn install_transactions(&self, id: FilterId, full: bool) {
+        self.insert(id, PendingFilter::Transactions { full, delivered: HashSet::default() });
+    }
+
+    /// Tracks a filter, dropping the filters that were not polled for long enough to have
+    /// expired.
+    fn insert(&self, id: FilterId, filter: PendingFilter) {
+        let mut filters = self.filters.lock().expect("pending filters lock poisoned");
+        filters.retain(|_, tracked| tracked.last_poll.elapsed() < STALE_FILTER_TTL);
+        filters.insert(id, TrackedFilter::new(filter));
+    }
+
+    /// Stops tracking a filter.
+    pub(crate) fn uninstall(&self, id: &FilterId) {
+        self.filters.lock().expect("pending filters lock poisoned").remove(id);
+    }
+
+    /// Returns the filter of a tracked log filter.
+    pub(crate) fn log_filter(&self, id: &FilterId) -> Option<Filter> {
+        match &self.filters.lock().expect("pending filters lock poisoned").get(id)?.filter {
+            PendingFilter::Logs { filter, .. } => Some(filter.clone()),
+            PendingFilter::Transactions { .. } => None,
+        }
+    }
+
+    /// Merges the canonical changes of a filter with the pending state delivered since the last
+    /// poll. `canonical_head` is the canonical block number read before the canonical changes,
+    /// and `is_canonical` tells whether a transaction was included in a canonical block.
+    pub(crate) fn merge_changes(
+        &self,
+        id: &FilterId,
+        changes: FilterChanges<Transaction>,
+        pending_blocks: Option<&PendingBlocks>,
+        canonical_head: BlockNumber,
+        is_canonical: impl Fn(TxHash) -> bool,
+    ) -> FilterChanges<Transaction> {
+        let mut filters = self.filters.lock().expect("pending filters lock poisoned");
+        let Some(tracked) = filters.get_mut(id) else { return changes };
+        tracked.last_poll = Instant::now();
+
+        match (&mut tracked.filter, changes) {
+            (PendingFilter::Logs { filter, delivered }, FilterChanges::Logs(logs)) => {
+                log_changes(filter, delivered, logs, pending_blocks, canonical_head)
+            }
+            (PendingFilter::Logs { filter, delivered }, FilterChanges::Empty) => {
+                log_changes(filter, delivered, Vec::new(), pending_blocks, canonical_head)
+            }
+            (PendingFilter::Transactions { full, delivered }, changes) => {
+                transaction_changes(*full, delivered, changes, pending_blocks, is_canonical)
+            }
+            (_, changes) => changes,
+        }
+    }
+}
+
+/// Returns whether a log filter follows the chain head, so that it also matches pending logs.
+fn includes_pending(filter: &Filter) -> bool {
+    matches!(
+        filter.block_option,
+        FilterBlockOption::Range { to_block: None | Some(BlockNumberOrTag::Pending), .. }
+    )
+}
+
+fn log_changes(
+    filter: &Filter,
+    delivered: &mut HashMap<LogKey, Log>,
+    canonical_logs: Vec<Log>,
+    pending_blocks: Option<&PendingBlocks>,
+    canonical_head: BlockNumber,
+) -> FilterChanges<Transaction> {
+    // Canonical logs already delivered while pending are not returned again
+    let mut logs: Vec<Log> = canonical_logs
+        .into_iter()
+        .filter(|log| delivered.remove(&log_key(log)).is_none())
+        .collect();
+
+    let pending_logs = pending_blocks
+        .map(|pending_blocks| pending_blocks.get_pending_logs(filter))
+        .unwrap_or_default();
+    let pending_keys: HashSet<LogKey> = pending_logs.iter().map(log_key).collect();
+
+    // Delivered logs that are neither pending nor canonical were dropped, unless their block was
+    // committed after the canonical changes were read
+    delivered.retain(|key, log| {
+        if pending_keys.contains(key) {
+            return true;
+        }
+
+        let block_number = log.block_number.unwrap_or_default();
+        let committed_since = block_number > canonical_head
+            && pending_blocks
+                .is_none_or(|pending_blocks| pending_blocks.earliest_block_number() > block_number);
+        if !committed_since {
+            logs.push(Log { removed: true, ..log.clone() });
+        }
+        committed_since
+    });
+
+    for log in pending_logs {
+        let key = log_key(&log);
+        if !delivered.contains_key(&key) {
+            delivered.insert(key, log.clone());
+            logs.push(log);
+        }
+    }
+
+    FilterChanges::Logs(logs)
+}
+
+fn transaction_changes(
+    full: bool,
+    delivered: &mut HashSet<TxHash>,
+    changes: FilterChanges<Transaction>,
+    pending_blocks: Option<&PendingBlocks>,
+    is_canonical: impl Fn(TxHash) -> bool,
+) -> FilterChanges<Transaction> {
+    let pending_transactions = pending_blocks
+        .map(|pending_blocks| pending_blocks.get_pending_transactions())
+        .unwrap_or_default();
+
+    // Transactions delivered from the mempool or flashblocks are not delivered again until they
+    // are canonical
+    let pending_hashes: HashSet<TxHash> =
+        pending_transactions.iter().map(|tx| tx.tx_hash()).collect();
+    delivered.retain(|hash| pending_hashes.contains(hash) || !is_canonical(*hash));
+
+    match changes {
+        FilterChanges::Hashes(hashes) => FilterChanges::Hashes(
+            hashes
+                .into_iter()
+                .chain(pending_transactions.iter().map(|tx| tx.tx_hash()))
+                .filter(|hash| delivered.insert(*hash))
+                .collect(),
+        ),
+        FilterChanges::Transactions(transactions) => FilterChanges::Transactions(
+            transactions
+                .into_iter()
+                .chain(pending_transactions)
+                .filter(|tx| delivered.insert(tx.tx_hash()))
+                .collect(),
+        ),
+        FilterChanges::Empty if full => FilterChanges::Transactions(
+            pending_transactions.into_iter().filter(|tx| delivered.insert(tx.tx_hash())).collect(),
+        ),
+        FilterChanges::Empty => FilterChanges::Hashes(
+            pending_transactions
+                .iter()
+                .map(|tx| tx.tx_hash())
+                .filter(|hash| delivered.insert(*hash))
+                .collect(),
+        ),
+        changes => changes,
+    }
+}
@@ -1 +1,5 @@
+pub(crate) mod debug;
+pub(crate) mod fees;
+pub(crate) mod filter;
+pub(crate) mod pin;
 pub(crate) mod rpc;
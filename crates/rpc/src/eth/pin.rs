@@ -0,0 +1,109 @@
+//! RPC middleware pinning the pending state read by a call or a batch.
+
+use std::{fmt, future::Future, sync::Arc};
+
+use base_reth_flashblocks::PendingBlocks;
+use jsonrpsee::{
+    core::middleware::{Batch, BatchEntry, Notification, RpcServiceT},
+    types::Request,
+};
+use tower::Layer;
+
+/// Loads the latest pending state, if any.
+type PendingBlocksLoader = Arc<dyn Fn() -> Option<Arc<PendingBlocks>> + Send + Sync>;
+
+/// The pending state pinned for a call. All calls of a batch share the same one.
+#[derive(Debug, Clone)]
+pub struct PinnedPendingBlocks(Option<Arc<PendingBlocks>>);
+
+impl PinnedPendingBlocks {
+    /// Returns the pinned pending state, or None if there was no pending state when the call was
+    /// received.
+    pub fn pending_blocks(&self) -> Option<Arc<PendingBlocks>> {
+        self.0.clone()
+    }
+}
+
+/// Layer pinning the pending state once per call or batch, so that every `pending` read of a
+/// batch is served from the same flashblock.
+#[derive(Clone)]
+pub struct PendingBlocksPinLayer {
+    load: PendingBlocksLoader,
+}
+
+impl fmt::Debug for PendingBlocksPinLayer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PendingBlocksPinLayer").finish_non_exhaustive()
+    }
+}
+
+impl PendingBlocksPinLayer {
+    /// Creates a layer pinning the pending state returned by `load`.
+    pub fn new(load: impl Fn() -> Option<Arc<PendingBlocks>> + Send + Sync + 'static) -> Self {
+        Self { load: Arc::new(load) }
+    }
+}
+
+impl<S> Layer<S> for PendingBlocksPinLayer {
+    type Service = PendingBlocksPinService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        PendingBlocksPinService { inner, load: self.load.clone() }
+    }
+}
+
+/// RPC service adding the [`PinnedPendingBlocks`] to the extensions of every call.
+#[derive(Clone)]
+pub struct PendingBlocksPinService<S> {
+    inner: S,
+    load: PendingBlocksLoader,
+}
+
+impl<S> fmt::Debug for PendingBlocksPinService<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PendingBlocksPinService").finish_non_exhaustive()
+    }
+}
+
+impl<S> PendingBlocksPinService<S> {
+    fn pin(&self) -> PinnedPendingBlocks {
+        PinnedPendingBlocks((self.load)())
+    }
+}
+
+impl<S> RpcServiceT for PendingBlocksPinService<S>
+where
+    S: RpcServiceT + Send + Sync + Clone + 'static,
+{
+    type MethodResponse = S::MethodResponse;
+    type NotificationResponse = S::NotificationResponse;
+    type BatchResponse = S::BatchResponse;
+
+    fn call<'a>(
+        &self,
+        mut request: Request<'a>,
+    ) -> impl Future<Output = Self::MethodResponse> + Send + 'a {
+        request.extensions_mut().insert(self.pin());
+        self.inner.call(request)
+    }
+
+    fn batch<'a>(
+        &self,
+        mut batch: Batch<'a>,
+    ) -> impl Future<Output = Self::BatchResponse> + Send + 'a {
+        let pinned = self.pin();
+        for entry in batch.iter_mut() {
+            if let Ok(BatchEntry::Call(request)) = entry {
+                request.extensions_mut().insert(pinned.clone());
+            }
+        }
+        self.inner.batch(batch)
+    }
+
+    fn notification<'a>(
+        &self,
+        notification: Notification<'a>,
+    ) -> impl Future<Output = Self::NotificationResponse> + Send + 'a {
+        self.inner.notification(notification)
+    }
+}
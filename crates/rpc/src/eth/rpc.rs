@@ -4,7 +4,7 @@ use std::{sync::Arc, time::Duration};
 
 use alloy_eips::{BlockId, BlockNumberOrTag};
 use alloy_primitives::{
-    Address, TxHash, U256,
+    Address, B256, BlockNumber, Bytes, TxHash, U64, U256, keccak256,
     map::foldhash::{HashSet, HashSetExt},
 };
 use alloy_rpc_types::{
@@ -12,81 +12,153 @@ use alloy_rpc_types::{
     simulate::{SimBlock, SimulatePayload, SimulatedBlock},
     state::{EvmOverrides, StateOverride, StateOverridesBuilder},
 };
-use alloy_rpc_types_eth::{Filter, Log};
-use base_reth_flashblocks::{FlashblocksAPI, PendingBlocksAPI};
+use alloy_rpc_types_eth::{
+    AccessListResult, FeeHistory, Filter, FilterChanges, FilterId, Index, Log,
+    PendingTransactionFilterKind,
+};
+use alloy_serde::JsonStorageKey;
+use base_reth_flashblocks::{
+    FlashblocksAPI, Inclusion, InclusionWaiter, PendingBlocks, PendingBlocksAPI,
+};
 use jsonrpsee::{
+    Extensions,
     core::{RpcResult, async_trait},
     proc_macros::rpc,
 };
-use jsonrpsee_types::{ErrorObjectOwned, error::INVALID_PARAMS_CODE};
-use op_alloy_network::Optimism;
+use jsonrpsee_types::{
+    ErrorObjectOwned,
+    error::{INVALID_PARAMS_CODE, SERVER_IS_BUSY_CODE},
+};
+use op_alloy_network::{Optimism, ReceiptResponse};
 use op_alloy_rpc_types::OpTransactionRequest;
 use reth::{
-    providers::CanonStateSubscriptions,
+    providers::{BlockIdReader, BlockNumReader, TransactionsProvider},
     rpc::{eth::EthFilter, server_types::eth::EthApiError},
 };
 use reth_rpc_eth_api::{
-    EthApiTypes, EthFilterApiServer, RpcBlock, RpcReceipt, RpcTransaction,
-    helpers::{EthBlocks, EthCall, EthState, EthTransactions, FullEthApi},
+    EthApiTypes, EthFilterApiServer, RpcBlock, RpcHeader, RpcReceipt, RpcTransaction,
+    helpers::{EthBlocks, EthCall, EthFees, EthState, EthTransactions, FullEthApi},
+};
+use tokio::time;
+use tracing::{debug, trace};
+
+use crate::{
+    ConfirmationLevel, PendingResponse, PinnedPendingBlocks, SendRawTransactionSyncResponse,
+    eth::{fees, filter::PendingFilters},
+    metrics::Metrics,
 };
-use tokio::{sync::broadcast::error::RecvError, time};
-use tokio_stream::{StreamExt, wrappers::BroadcastStream};
-use tracing::{debug, trace, warn};
 
-use crate::metrics::Metrics;
+/// Default max timeout for `eth_sendRawTransactionSync` in milliseconds.
+pub const DEFAULT_MAX_TIMEOUT_SEND_RAW_TX_SYNC_MS: u64 = 6_000;
 
-/// Max configured timeout for `eth_sendRawTransactionSync` in milliseconds.
-const MAX_TIMEOUT_SEND_RAW_TX_SYNC_MS: u64 = 6_000;
+/// Interval at which the safe head is checked while waiting for safe inclusion.
+const SAFE_HEAD_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Interval at which a receipt is re-read while waiting for it to be indexed.
+const RECEIPT_POLL_INTERVAL: Duration = Duration::from_millis(50);
 
 /// Eth API override trait for flashblocks integration.
 #[cfg_attr(not(test), rpc(server, namespace = "eth"))]
 #[cfg_attr(test, rpc(server, client, namespace = "eth"))]
 pub trait EthApiOverride {
     /// Returns block by number, with flashblock support for pending blocks.
-    #[method(name = "getBlockByNumber")]
+    #[method(name = "getBlockByNumber", with_extensions)]
     async fn block_by_number(
         &self,
         number: BlockNumberOrTag,
         full: bool,
-    ) -> RpcResult<Option<RpcBlock<Optimism>>>;
+    ) -> RpcResult<Option<PendingResponse<RpcBlock<Optimism>>>>;
+
+    /// Returns block by hash, with flashblock support for pending blocks.
+    #[method(name = "getBlockByHash", with_extensions)]
+    async fn block_by_hash(
+        &self,
+        hash: B256,
+        full: bool,
+    ) -> RpcResult<Option<PendingResponse<RpcBlock<Optimism>>>>;
+
+    /// Returns the number of transactions in a block, with flashblock support for pending blocks.
+    #[method(name = "getBlockTransactionCountByNumber", with_extensions)]
+    async fn block_transaction_count_by_number(
+        &self,
+        number: BlockNumberOrTag,
+    ) -> RpcResult<Option<U256>>;
+
+    /// Returns a transaction by block number and index, with flashblock support for pending
+    /// blocks.
+    #[method(name = "getTransactionByBlockNumberAndIndex", with_extensions)]
+    async fn transaction_by_block_number_and_index(
+        &self,
+        number: BlockNumberOrTag,
+        index: Index,
+    ) -> RpcResult<Option<PendingResponse<RpcTransaction<Optimism>>>>;
+
+    /// Returns a block header by number, with flashblock support for pending blocks.
+    #[method(name = "getHeaderByNumber", with_extensions)]
+    async fn header_by_number(
+        &self,
+        number: BlockNumberOrTag,
+    ) -> RpcResult<Option<PendingResponse<RpcHeader<Optimism>>>>;
+
+    /// Returns all receipts of a block, with flashblock support for pending blocks.
+    #[method(name = "getBlockReceipts", with_extensions)]
+    async fn block_receipts(
+        &self,
+        block_id: BlockId,
+    ) -> RpcResult<Option<Vec<RpcReceipt<Optimism>>>>;
 
     /// Returns transaction receipt, checking flashblocks first.
-    #[method(name = "getTransactionReceipt")]
+    #[method(name = "getTransactionReceipt", with_extensions)]
     async fn get_transaction_receipt(
         &self,
         tx_hash: TxHash,
-    ) -> RpcResult<Option<RpcReceipt<Optimism>>>;
+    ) -> RpcResult<Option<PendingResponse<RpcReceipt<Optimism>>>>;
 
     /// Returns account balance, with flashblock support for pending state.
-    #[method(name = "getBalance")]
+    #[method(name = "getBalance", with_extensions)]
     async fn get_balance(&self, address: Address, block_number: Option<BlockId>)
     -> RpcResult<U256>;
 
     /// Returns transaction count for an address.
-    #[method(name = "getTransactionCount")]
+    #[method(name = "getTransactionCount", with_extensions)]
     async fn get_transaction_count(
         &self,
         address: Address,
         block_number: Option<BlockId>,
     ) -> RpcResult<U256>;
 
+    /// Returns the value of a storage slot, with flashblock support for pending state.
+    #[method(name = "getStorageAt", with_extensions)]
+    async fn get_storage_at(
+        &self,
+        address: Address,
+        slot: JsonStorageKey,
+        block_number: Option<BlockId>,
+    ) -> RpcResult<B256>;
+
+    /// Returns the code at an address, with flashblock support for pending state.
+    #[method(name = "getCode", with_extensions)]
+    async fn get_code(&self, address: Address, block_number: Option<BlockId>) -> RpcResult<Bytes>;
+
     /// Returns transaction by hash, checking flashblocks first.
-    #[method(name = "getTransactionByHash")]
+    #[method(name = "getTransactionByHash", with_extensions)]
     async fn transaction_by_hash(
         &self,
         tx_hash: TxHash,
-    ) -> RpcResult<Option<RpcTransaction<Optimism>>>;
+    ) -> RpcResult<Option<PendingResponse<RpcTransaction<Optimism>>>>;
 
-    /// Sends a raw transaction and waits for inclusion in a flashblock.
+    /// Sends a raw transaction and waits until it reaches the given confirmation level, by
+    /// default inclusion in a flashblock.
     #[method(name = "sendRawTransactionSync")]
     async fn send_raw_transaction_sync(
         &self,
         transaction: alloy_primitives::Bytes,
         timeout_ms: Option<u64>,
-    ) -> RpcResult<RpcReceipt<Optimism>>;
+        confirmation_level: Option<ConfirmationLevel>,
+    ) -> RpcResult<SendRawTransactionSyncResponse<RpcReceipt<Optimism>>>;
 
     /// Executes a call with flashblock state support.
-    #[method(name = "call")]
+    #[method(name = "call", with_extensions)]
     async fn call(
         &self,
         transaction: OpTransactionRequest,
@@ -96,7 +168,7 @@ pub trait EthApiOverride {
     ) -> RpcResult<alloy_primitives::Bytes>;
 
     /// Estimates gas with flashblock state support.
-    #[method(name = "estimateGas")]
+    #[method(name = "estimateGas", with_extensions)]
     async fn estimate_gas(
         &self,
         transaction: OpTransactionRequest,
@@ -104,8 +176,17 @@ pub trait EthApiOverride {
         overrides: Option<StateOverride>,
     ) -> RpcResult<U256>;
 
+    /// Creates an access list for a transaction with flashblock state support.
+    #[method(name = "createAccessList", with_extensions)]
+    async fn create_access_list(
+        &self,
+        transaction: OpTransactionRequest,
+        block_number: Option<BlockId>,
+        state_override: Option<StateOverride>,
+    ) -> RpcResult<AccessListResult>;
+
     /// Simulates transactions with flashblock state support.
-    #[method(name = "simulateV1")]
+    #[method(name = "simulateV1", with_extensions)]
     async fn simulate_v1(
         &self,
         opts: SimulatePayload<OpTransactionRequest>,
@@ -113,8 +194,51 @@ pub trait EthApiOverride {
     ) -> RpcResult<Vec<SimulatedBlock<RpcBlock<Optimism>>>>;
 
     /// Returns logs matching the filter, including pending flashblock logs.
-    #[method(name = "getLogs")]
+    #[method(name = "getLogs", with_extensions)]
     async fn get_logs(&self, filter: Filter) -> RpcResult<Vec<Log>>;
+
+    /// Creates a log filter whose changes include logs from pending flashblocks.
+    #[method(name = "newFilter")]
+    async fn new_filter(&self, filter: Filter) -> RpcResult<FilterId>;
+
+    /// Creates a filter whose changes include transactions from pending flashblocks.
+    #[method(name = "newPendingTransactionFilter")]
+    async fn new_pending_transaction_filter(
+        &self,
+        kind: Option<PendingTransactionFilterKind>,
+    ) -> RpcResult<FilterId>;
+
+    /// Returns the changes of a filter since the last poll, including pending flashblock state.
+    #[method(name = "getFilterChanges", with_extensions)]
+    async fn filter_changes(
+        &self,
+        id: FilterId,
+    ) -> RpcResult<FilterChanges<RpcTransaction<Optimism>>>;
+
+    /// Returns all logs matching a filter, including pending flashblock logs.
+    #[method(name = "getFilterLogs", with_extensions)]
+    async fn filter_logs(&self, id: FilterId) -> RpcResult<Vec<Log>>;
+
+    /// Uninstalls a filter.
+    #[method(name = "uninstallFilter")]
+    async fn uninstall_filter(&self, id: FilterId) -> RpcResult<bool>;
+
+    /// Returns the suggested gas price, accounting for fees paid in pending flashblocks.
+    #[method(name = "gasPrice", with_extensions)]
+    async fn gas_price(&self) -> RpcResult<U256>;
+
+    /// Returns the suggested priority fee, accounting for fees paid in pending flashblocks.
+    #[method(name = "maxPriorityFeePerGas", with_extensions)]
+    async fn max_priority_fee_per_gas(&self) -> RpcResult<U256>;
+
+    /// Returns the fee history, with flashblock support for pending blocks.
+    #[method(name = "feeHistory", with_extensions)]
+    async fn fee_history(
+        &self,
+        block_count: U64,
+        newest_block: BlockNumberOrTag,
+        reward_percentiles: Option<Vec<f64>>,
+    ) -> RpcResult<FeeHistory>;
 }
 
 /// Extended Eth API with flashblocks support.
@@ -123,13 +247,28 @@ pub struct EthApiExt<Eth: EthApiTypes, FB> {
     eth_api: Eth,
     eth_filter: EthFilter<Eth>,
     flashblocks_state: Arc<FB>,
+    pending_filters: PendingFilters,
+    max_sync_timeout_ms: u64,
     metrics: Metrics,
 }
 
 impl<Eth: EthApiTypes, FB> EthApiExt<Eth, FB> {
     /// Creates a new extended Eth API instance with flashblocks support.
     pub fn new(eth_api: Eth, eth_filter: EthFilter<Eth>, flashblocks_state: Arc<FB>) -> Self {
-        Self { eth_api, eth_filter, flashblocks_state, metrics: Metrics::default() }
+        Self {
+            eth_api,
+            eth_filter,
+            flashblocks_state,
+            pending_filters: PendingFilters::default(),
+            max_sync_timeout_ms: DEFAULT_MAX_TIMEOUT_SEND_RAW_TX_SYNC_MS,
+            metrics: Metrics::default(),
+        }
+    }
+
+    /// Sets the max timeout accepted by `eth_sendRawTransactionSync`, in milliseconds.
+    pub const fn with_max_sync_timeout_ms(mut self, max_sync_timeout_ms: u64) -> Self {
+        self.max_sync_timeout_ms = max_sync_timeout_ms;
+        self
     }
 }
 
@@ -142,33 +281,178 @@ where
 {
     async fn block_by_number(
         &self,
+        ext: &Extensions,
         number: BlockNumberOrTag,
         full: bool,
-    ) -> RpcResult<Option<RpcBlock<Optimism>>> {
+    ) -> RpcResult<Option<PendingResponse<RpcBlock<Optimism>>>> {
         debug!(
             message = "rpc::block_by_number",
             block_number = ?number
         );
 
-        if number.is_pending() {
+        let block_id = if number.is_pending() {
             self.metrics.get_block_by_number.increment(1);
-            let pending_blocks = self.flashblocks_state.get_pending_blocks();
-            if pending_blocks.as_ref().is_some() {
-                return Ok(pending_blocks.get_block(full));
+            let pending_blocks = self.pending_blocks(ext);
+            if let Some(pending_blocks) = pending_blocks.as_deref() {
+                let block = pending_blocks.get_latest_block(full);
+                return Ok(Some(PendingResponse::pending(block, pending_blocks)));
             }
             // No pending state available — treat `pending` as `latest`
-            EthBlocks::rpc_block(&self.eth_api, BlockNumberOrTag::Latest.into(), full)
-                .await
-                .map_err(Into::into)
+            BlockNumberOrTag::Latest.into()
         } else {
-            EthBlocks::rpc_block(&self.eth_api, number.into(), full).await.map_err(Into::into)
+            number.into()
+        };
+
+        Ok(EthBlocks::rpc_block(&self.eth_api, block_id, full)
+            .await?
+            .map(PendingResponse::canonical))
+    }
+
+    async fn block_by_hash(
+        &self,
+        ext: &Extensions,
+        hash: B256,
+        full: bool,
+    ) -> RpcResult<Option<PendingResponse<RpcBlock<Optimism>>>> {
+        debug!(
+            message = "rpc::block_by_hash",
+            block_hash = %hash
+        );
+
+        // Check canonical chain first, pending blocks are only served until they are committed
+        if let Some(block) = EthBlocks::rpc_block(&self.eth_api, hash.into(), full).await? {
+            return Ok(Some(PendingResponse::canonical(block)));
         }
+
+        let pending_blocks = self.pending_blocks(ext);
+        if let Some(pending_blocks) = pending_blocks.as_deref()
+            && let Some(block) = pending_blocks.get_block_by_hash(hash, full)
+        {
+            self.metrics.get_block_by_hash.increment(1);
+            return Ok(Some(PendingResponse::pending(block, pending_blocks)));
+        }
+
+        Ok(None)
+    }
+
+    async fn block_transaction_count_by_number(
+        &self,
+        ext: &Extensions,
+        number: BlockNumberOrTag,
+    ) -> RpcResult<Option<U256>> {
+        debug!(
+            message = "rpc::block_transaction_count_by_number",
+            block_number = ?number
+        );
+
+        let block_id = if number.is_pending() {
+            self.metrics.get_block_transaction_count_by_number.increment(1);
+            let pending_blocks = self.pending_blocks(ext);
+            if let Some(block) = pending_blocks.get_block(false) {
+                return Ok(Some(U256::from(block.transactions.len())));
+            }
+            // No pending state available — treat `pending` as `latest`
+            BlockNumberOrTag::Latest.into()
+        } else {
+            number.into()
+        };
+
+        Ok(EthBlocks::block_transaction_count(&self.eth_api, block_id).await?.map(U256::from))
+    }
+
+    async fn transaction_by_block_number_and_index(
+        &self,
+        ext: &Extensions,
+        number: BlockNumberOrTag,
+        index: Index,
+    ) -> RpcResult<Option<PendingResponse<RpcTransaction<Optimism>>>> {
+        debug!(
+            message = "rpc::transaction_by_block_number_and_index",
+            block_number = ?number,
+            index = ?index
+        );
+
+        let block_id = if number.is_pending() {
+            self.metrics.get_transaction_by_block_number_and_index.increment(1);
+            let pending_blocks = self.pending_blocks(ext);
+            if let Some(pending_blocks) = pending_blocks.as_deref() {
+                return Ok(pending_blocks
+                    .get_latest_transaction_by_index(index.into())
+                    .map(|tx| PendingResponse::pending(tx, pending_blocks)));
+            }
+            // No pending state available — treat `pending` as `latest`
+            BlockNumberOrTag::Latest.into()
+        } else {
+            number.into()
+        };
+
+        Ok(EthTransactions::transaction_by_block_and_tx_index(
+            &self.eth_api,
+            block_id,
+            index.into(),
+        )
+        .await?
+        .map(PendingResponse::canonical))
+    }
+
+    async fn header_by_number(
+        &self,
+        ext: &Extensions,
+        number: BlockNumberOrTag,
+    ) -> RpcResult<Option<PendingResponse<RpcHeader<Optimism>>>> {
+        debug!(
+            message = "rpc::header_by_number",
+            block_number = ?number
+        );
+
+        let block_id = if number.is_pending() {
+            self.metrics.get_header_by_number.increment(1);
+            let pending_blocks = self.pending_blocks(ext);
+            if let Some(pending_blocks) = pending_blocks.as_deref() {
+                let header = pending_blocks.get_latest_block(false).header;
+                return Ok(Some(PendingResponse::pending(header, pending_blocks)));
+            }
+            // No pending state available — treat `pending` as `latest`
+            BlockNumberOrTag::Latest.into()
+        } else {
+            number.into()
+        };
+
+        Ok(EthBlocks::rpc_block_header(&self.eth_api, block_id)
+            .await?
+            .map(PendingResponse::canonical))
+    }
+
+    async fn block_receipts(
+        &self,
+        ext: &Extensions,
+        block_id: BlockId,
+    ) -> RpcResult<Option<Vec<RpcReceipt<Optimism>>>> {
+        debug!(
+            message = "rpc::block_receipts",
+            block_id = ?block_id
+        );
+
+        let block_id = if block_id.is_pending() {
+            self.metrics.get_block_receipts.increment(1);
+            let pending_blocks = self.pending_blocks(ext);
+            if let Some(receipts) = pending_blocks.get_block_receipts() {
+                return Ok(Some(receipts));
+            }
+            // No pending state available — treat `pending` as `latest`
+            BlockNumberOrTag::Latest.into()
+        } else {
+            block_id
+        };
+
+        EthBlocks::block_receipts(&self.eth_api, block_id).await.map_err(Into::into)
     }
 
     async fn get_transaction_receipt(
         &self,
+        ext: &Extensions,
         tx_hash: TxHash,
-    ) -> RpcResult<Option<RpcReceipt<Optimism>>> {
+    ) -> RpcResult<Option<PendingResponse<RpcReceipt<Optimism>>>> {
         debug!(
             message = "rpc::get_transaction_receipt",
             tx_hash = %tx_hash
@@ -179,14 +463,16 @@ where
         if let Some(canonical_receipt) =
             EthTransactions::transaction_receipt(&self.eth_api, tx_hash).await?
         {
-            return Ok(Some(canonical_receipt));
+            return Ok(Some(PendingResponse::canonical(canonical_receipt)));
         }
 
         // Fall back to flashblocks for pending transactions
-        let pending_blocks = self.flashblocks_state.get_pending_blocks();
-        if let Some(fb_receipt) = pending_blocks.get_transaction_receipt(tx_hash) {
+        let pending_blocks = self.pending_blocks(ext);
+        if let Some(pending_blocks) = pending_blocks.as_deref()
+            && let Some(fb_receipt) = pending_blocks.get_receipt(tx_hash)
+        {
             self.metrics.get_transaction_receipt.increment(1);
-            return Ok(Some(fb_receipt));
+            return Ok(Some(PendingResponse::pending(fb_receipt, pending_blocks)));
         }
 
         Ok(None)
@@ -194,6 +480,7 @@ where
 
     async fn get_balance(
         &self,
+        ext: &Extensions,
         address: Address,
         block_number: Option<BlockId>,
     ) -> RpcResult<U256> {
@@ -204,10 +491,15 @@ where
         let block_id = block_number.unwrap_or_default();
         if block_id.is_pending() {
             self.metrics.get_balance.increment(1);
-            let pending_blocks = self.flashblocks_state.get_pending_blocks();
+            let pending_blocks = self.pending_blocks(ext);
             if let Some(balance) = pending_blocks.get_balance(address) {
                 return Ok(balance);
             }
+
+            let canon_block = pending_blocks.get_canonical_block_number();
+            return EthState::balance(&self.eth_api, address, Some(canon_block.into()))
+                .await
+                .map_err(Into::into);
         }
 
         EthState::balance(&self.eth_api, address, block_number).await.map_err(Into::into)
@@ -215,6 +507,7 @@ where
 
     async fn get_transaction_count(
         &self,
+        ext: &Extensions,
         address: Address,
         block_number: Option<BlockId>,
     ) -> RpcResult<U256> {
@@ -226,25 +519,85 @@ where
         let block_id = block_number.unwrap_or_default();
         if block_id.is_pending() {
             self.metrics.get_transaction_count.increment(1);
-            let pending_blocks = self.flashblocks_state.get_pending_blocks();
+            let pending_blocks = self.pending_blocks(ext);
+            if let Some(nonce) = pending_blocks.get_nonce(address) {
+                return Ok(U256::from(nonce));
+            }
+
+            // Untouched by pending transactions, so the nonce is unchanged since the canonical
+            // block the pending state is built on
             let canon_block = pending_blocks.get_canonical_block_number();
-            let fb_count = pending_blocks.get_transaction_count(address);
+            return EthState::transaction_count(&self.eth_api, address, Some(canon_block.into()))
+                .await
+                .map_err(Into::into);
+        }
 
-            let canon_count =
-                EthState::transaction_count(&self.eth_api, address, Some(canon_block.into()))
-                    .await
-                    .map_err(Into::into)?;
+        EthState::transaction_count(&self.eth_api, address, block_number).await.map_err(Into::into)
+    }
+
+    async fn get_storage_at(
+        &self,
+        ext: &Extensions,
+        address: Address,
+        slot: JsonStorageKey,
+        block_number: Option<BlockId>,
+    ) -> RpcResult<B256> {
+        debug!(
+            message = "rpc::get_storage_at",
+            address = %address,
+            slot = ?slot,
+        );
 
-            return Ok(canon_count + fb_count);
+        let block_id = block_number.unwrap_or_default();
+        if block_id.is_pending() {
+            self.metrics.get_storage_at.increment(1);
+            let pending_blocks = self.pending_blocks(ext);
+            if let Some(value) = pending_blocks.get_storage_at(address, slot.as_b256()) {
+                return Ok(value);
+            }
+
+            let canon_block = pending_blocks.get_canonical_block_number();
+            return EthState::storage_at(&self.eth_api, address, slot, Some(canon_block.into()))
+                .await
+                .map_err(Into::into);
         }
 
-        EthState::transaction_count(&self.eth_api, address, block_number).await.map_err(Into::into)
+        EthState::storage_at(&self.eth_api, address, slot, block_number).await.map_err(Into::into)
+    }
+
+    async fn get_code(
+        &self,
+        ext: &Extensions,
+        address: Address,
+        block_number: Option<BlockId>,
+    ) -> RpcResult<Bytes> {
+        debug!(
+            message = "rpc::get_code",
+            address = %address,
+        );
+
+        let block_id = block_number.unwrap_or_default();
+        if block_id.is_pending() {
+            self.metrics.get_code.increment(1);
+            let pending_blocks = self.pending_blocks(ext);
+            if let Some(code) = pending_blocks.get_code(address) {
+                return Ok(code);
+            }
+
+            let canon_block = pending_blocks.get_canonical_block_number();
+            return EthState::get_code(&self.eth_api, address, Some(canon_block.into()))
+                .await
+                .map_err(Into::into);
+        }
+
+        EthState::get_code(&self.eth_api, address, block_number).await.map_err(Into::into)
     }
 
     async fn transaction_by_hash(
         &self,
+        ext: &Extensions,
         tx_hash: TxHash,
-    ) -> RpcResult<Option<RpcTransaction<Optimism>>> {
+    ) -> RpcResult<Option<PendingResponse<RpcTransaction<Optimism>>>> {
         debug!(
             message = "rpc::transaction_by_hash",
             tx_hash = %tx_hash
@@ -266,14 +619,16 @@ where
             })
             .transpose()?
         {
-            return Ok(Some(canonical_tx));
+            return Ok(Some(PendingResponse::canonical(canonical_tx)));
         }
 
         // Fall back to flashblocks for pending transactions
-        let pending_blocks = self.flashblocks_state.get_pending_blocks();
-        if let Some(fb_transaction) = pending_blocks.get_transaction_by_hash(tx_hash) {
+        let pending_blocks = self.pending_blocks(ext);
+        if let Some(pending_blocks) = pending_blocks.as_deref()
+            && let Some(fb_transaction) = pending_blocks.get_transaction_by_hash(tx_hash)
+        {
             self.metrics.get_transaction_by_hash.increment(1);
-            return Ok(Some(fb_transaction));
+            return Ok(Some(PendingResponse::pending(fb_transaction, pending_blocks)));
         }
 
         Ok(None)
@@ -283,21 +638,35 @@ where
         &self,
         transaction: alloy_primitives::Bytes,
         timeout_ms: Option<u64>,
-    ) -> RpcResult<RpcReceipt<Optimism>> {
-        debug!(message = "rpc::send_raw_transaction_sync");
+        confirmation_level: Option<ConfirmationLevel>,
+    ) -> RpcResult<SendRawTransactionSyncResponse<RpcReceipt<Optimism>>> {
+        debug!(
+            message = "rpc::send_raw_transaction_sync",
+            confirmation_level = ?confirmation_level,
+        );
 
+        let max_timeout_ms = self.max_sync_timeout_ms;
         let timeout_ms = match timeout_ms {
-            Some(ms) if ms > MAX_TIMEOUT_SEND_RAW_TX_SYNC_MS => {
+            Some(ms) if ms > max_timeout_ms => {
                 return Err(ErrorObjectOwned::owned(
                     INVALID_PARAMS_CODE,
-                    format!(
-                        "time out too long, timeout: {ms} ms, max: {MAX_TIMEOUT_SEND_RAW_TX_SYNC_MS} ms"
-                    ),
+                    format!("time out too long, timeout: {ms} ms, max: {max_timeout_ms} ms"),
                     None::<()>,
                 ));
             }
             Some(ms) => ms,
-            _ => MAX_TIMEOUT_SEND_RAW_TX_SYNC_MS,
+            _ => max_timeout_ms,
+        };
+
+        // Register before sending so that an inclusion right after submission is not missed
+        let Some(waiter) =
+            self.flashblocks_state.register_inclusion_waiter(keccak256(&transaction))
+        else {
+            return Err(ErrorObjectOwned::owned(
+                SERVER_IS_BUSY_CODE,
+                "too many transactions awaiting inclusion",
+                None::<()>,
+            ));
         };
 
         let tx_hash = match EthTransactions::send_raw_transaction(&self.eth_api, transaction).await
@@ -313,34 +682,21 @@ where
         );
 
         let timeout = Duration::from_millis(timeout_ms);
-        loop {
-            tokio::select! {
-                receipt = self.wait_for_flashblocks_receipt(tx_hash) => {
-                    if let Some(receipt) = receipt {
-                        return Ok(receipt);
-                    } else {
-                        continue
-                    }
-                }
-                receipt = self.wait_for_canonical_receipt(tx_hash) => {
-                        if let Some(receipt) = receipt {
-                            return Ok(receipt);
-                        } else {
-                            continue
-                        }
-                    }
-                _ = time::sleep(timeout) => {
-                    return Err(EthApiError::TransactionConfirmationTimeout {
-                        hash: tx_hash,
-                        duration: timeout,
-                    }.into());
-                }
+        let confirmation_level = confirmation_level.unwrap_or_default();
+        match time::timeout(timeout, self.wait_for_confirmation(waiter, confirmation_level)).await {
+            Ok(Ok(Some(response))) => Ok(response),
+            Ok(Err(e)) => Err(e.into()),
+            _ => Err(EthApiError::TransactionConfirmationTimeout {
+                hash: tx_hash,
+                duration: timeout,
             }
+            .into()),
         }
     }
 
     async fn call(
         &self,
+        ext: &Extensions,
         transaction: OpTransactionRequest,
         block_number: Option<BlockId>,
         state_overrides: Option<StateOverride>,
@@ -359,7 +715,7 @@ where
         // If the call is to pending block use cached override (if it exists)
         if block_id.is_pending() {
             self.metrics.call.increment(1);
-            let pending_blocks = self.flashblocks_state.get_pending_blocks();
+            let pending_blocks = self.pending_blocks(ext);
             block_id = pending_blocks.get_canonical_block_number().into();
             pending_overrides.state = pending_blocks.get_state_overrides();
         }
@@ -384,6 +740,7 @@ where
 
     async fn estimate_gas(
         &self,
+        ext: &Extensions,
         transaction: OpTransactionRequest,
         block_number: Option<BlockId>,
         overrides: Option<StateOverride>,
@@ -400,7 +757,7 @@ where
         // If the call is to pending block use cached override (if it exists)
         if block_id.is_pending() {
             self.metrics.estimate_gas.increment(1);
-            let pending_blocks = self.flashblocks_state.get_pending_blocks();
+            let pending_blocks = self.pending_blocks(ext);
             block_id = pending_blocks.get_canonical_block_number().into();
             pending_overrides.state = pending_blocks.get_state_overrides();
         }
@@ -415,8 +772,49 @@ where
             .map_err(Into::into)
     }
 
+    async fn create_access_list(
+        &self,
+        ext: &Extensions,
+        transaction: OpTransactionRequest,
+        block_number: Option<BlockId>,
+        state_override: Option<StateOverride>,
+    ) -> RpcResult<AccessListResult> {
+        debug!(
+            message = "rpc::create_access_list",
+            transaction = ?transaction,
+            block_number = ?block_number,
+            state_override = ?state_override,
+        );
+
+        let mut block_id = block_number.unwrap_or_default();
+        let mut pending_overrides = EvmOverrides::default();
+        // If the call is to pending block use cached override (if it exists)
+        if block_id.is_pending() {
+            self.metrics.create_access_list.increment(1);
+            let pending_blocks = self.pending_blocks(ext);
+            block_id = pending_blocks.get_canonical_block_number().into();
+            pending_overrides.state = pending_blocks.get_state_overrides();
+        }
+
+        let mut state_overrides_builder =
+            StateOverridesBuilder::new(pending_overrides.state.unwrap_or_default());
+        state_overrides_builder =
+            state_overrides_builder.extend(state_override.unwrap_or_default());
+        let final_overrides = state_overrides_builder.build();
+
+        EthCall::create_access_list_at(
+            &self.eth_api,
+            transaction,
+            Some(block_id),
+            Some(final_overrides),
+        )
+        .await
+        .map_err(Into::into)
+    }
+
     async fn simulate_v1(
         &self,
+        ext: &Extensions,
         opts: SimulatePayload<OpTransactionRequest>,
         block_number: Option<BlockId>,
     ) -> RpcResult<Vec<SimulatedBlock<RpcBlock<Eth::NetworkTypes>>>> {
@@ -431,7 +829,7 @@ where
         // If the call is to pending block use cached override (if it exists)
         if block_id.is_pending() {
             self.metrics.simulate_v1.increment(1);
-            let pending_blocks = self.flashblocks_state.get_pending_blocks();
+            let pending_blocks = self.pending_blocks(ext);
             block_id = pending_blocks.get_canonical_block_number().into();
             pending_overrides.state = pending_blocks.get_state_overrides();
         }
@@ -454,7 +852,7 @@ where
         EthCall::simulate_v1(&self.eth_api, payload, Some(block_id)).await.map_err(Into::into)
     }
 
-    async fn get_logs(&self, filter: Filter) -> RpcResult<Vec<Log>> {
+    async fn get_logs(&self, ext: &Extensions, filter: Filter) -> RpcResult<Vec<Log>> {
         debug!(
             message = "rpc::get_logs",
             address = ?filter.address
@@ -480,16 +878,31 @@ where
         self.metrics.get_logs.increment(1);
         let mut all_logs = Vec::new();
 
-        let pending_blocks = self.flashblocks_state.get_pending_blocks();
+        let pending_blocks = self.pending_blocks(ext);
+
+        // Historical logs end at the canonical block the pending state is built on, rather than
+        // the latest block, which may already include some of the pending blocks
+        let canon_block = pending_blocks.get_canonical_block_number();
+        let from_block = match from_block {
+            None | Some(BlockNumberOrTag::Latest) => Some(canon_block),
+            from_block => from_block,
+        };
+        let includes_historical = match (from_block, canon_block) {
+            (Some(BlockNumberOrTag::Pending), _) => false,
+            (Some(BlockNumberOrTag::Number(from)), BlockNumberOrTag::Number(canon)) => {
+                from <= canon
+            }
+            _ => true,
+        };
 
         let mut fetched_logs = HashSet::new();
         // Get historical logs if fromBlock is not pending
-        if !matches!(from_block, Some(BlockNumberOrTag::Pending)) {
-            // Create a filter for historical data (fromBlock to latest)
+        if includes_historical {
+            // Create a filter for historical data (fromBlock to the pending state's canonical block)
             let mut historical_filter = filter.clone();
             historical_filter.block_option = alloy_rpc_types_eth::FilterBlockOption::Range {
                 from_block,
-                to_block: Some(BlockNumberOrTag::Latest),
+                to_block: Some(canon_block),
             };
 
             let historical_logs: Vec<Log> = self.eth_filter.logs(historical_filter).await?;
@@ -512,6 +925,187 @@ where
 
         Ok(all_logs)
     }
+
+    async fn new_filter(&self, filter: Filter) -> RpcResult<FilterId> {
+        debug!(
+            message = "rpc::new_filter",
+            address = ?filter.address
+        );
+
+        let id = self.eth_filter.new_filter(filter.clone()).await?;
+        self.pending_filters.install_logs(id.clone(), filter);
+        Ok(id)
+    }
+
+    async fn new_pending_transaction_filter(
+        &self,
+        kind: Option<PendingTransactionFilterKind>,
+    ) -> RpcResult<FilterId> {
+        debug!(
+            message = "rpc::new_pending_transaction_filter",
+            kind = ?kind
+        );
+
+        let full = matches!(kind, Some(PendingTransactionFilterKind::Full));
+        let id = self.eth_filter.new_pending_transaction_filter(kind).await?;
+        self.pending_filters.install_transactions(id.clone(), full);
+        Ok(id)
+    }
+
+    async fn filter_changes(
+        &self,
+        ext: &Extensions,
+        id: FilterId,
+    ) -> RpcResult<FilterChanges<RpcTransaction<Optimism>>> {
+        debug!(
+            message = "rpc::filter_changes",
+            id = ?id
+        );
+
+        // Read before the canonical changes, so that blocks committed in between are recognized
+        let canonical_head =
+            self.eth_api.provider().best_block_number().map_err(EthApiError::from)?;
+        let changes = match self.eth_filter.filter_changes(id.clone()).await {
+            Ok(changes) => changes,
+            Err(err) => {
+                // The filter expired or was never installed
+                self.pending_filters.uninstall(&id);
+                return Err(err);
+            }
+        };
+
+        self.metrics.get_filter_changes.increment(1);
+        let pending_blocks = self.pending_blocks(ext);
+        let provider = self.eth_api.provider();
+        Ok(self.pending_filters.merge_changes(
+            &id,
+            changes,
+            pending_blocks.as_deref(),
+            canonical_head,
+            |hash| matches!(provider.transaction_by_hash(hash), Ok(Some(_))),
+        ))
+    }
+
+    async fn filter_logs(&self, ext: &Extensions, id: FilterId) -> RpcResult<Vec<Log>> {
+        debug!(
+            message = "rpc::filter_logs",
+            id = ?id
+        );
+
+        match self.pending_filters.log_filter(&id) {
+            Some(filter)
+                if filter.block_option.get_to_block() == Some(&BlockNumberOrTag::Pending) =>
+            {
+                self.metrics.get_filter_logs.increment(1);
+                self.get_logs(ext, filter).await
+            }
+            _ => self.eth_filter.filter_logs(id).await,
+        }
+    }
+
+    async fn uninstall_filter(&self, id: FilterId) -> RpcResult<bool> {
+        debug!(
+            message = "rpc::uninstall_filter",
+            id = ?id
+        );
+
+        self.pending_filters.uninstall(&id);
+        self.eth_filter.uninstall_filter(id).await
+    }
+
+    async fn gas_price(&self, ext: &Extensions) -> RpcResult<U256> {
+        debug!(message = "rpc::gas_price");
+
+        let gas_price = EthFees::gas_price(&self.eth_api).await?;
+        let pending_blocks = self.pending_blocks(ext);
+        let Some(pending_blocks) = pending_blocks.as_ref() else {
+            return Ok(gas_price);
+        };
+
+        self.metrics.gas_price.increment(1);
+        let priority_fee = EthFees::suggested_priority_fee(&self.eth_api)
+            .await?
+            .max(U256::from(fees::suggested_priority_fee(pending_blocks)));
+        let next_base_fee = pending_blocks.get_next_base_fee().unwrap_or_default();
+        Ok(gas_price.max(U256::from(next_base_fee) + priority_fee))
+    }
+
+    async fn max_priority_fee_per_gas(&self, ext: &Extensions) -> RpcResult<U256> {
+        debug!(message = "rpc::max_priority_fee_per_gas");
+
+        let priority_fee = EthFees::suggested_priority_fee(&self.eth_api).await?;
+        let pending_blocks = self.pending_blocks(ext);
+        let Some(pending_blocks) = pending_blocks.as_ref() else {
+            return Ok(priority_fee);
+        };
+
+        self.metrics.max_priority_fee_per_gas.increment(1);
+        Ok(priority_fee.max(U256::from(fees::suggested_priority_fee(pending_blocks))))
+    }
+
+    async fn fee_history(
+        &self,
+        ext: &Extensions,
+        block_count: U64,
+        newest_block: BlockNumberOrTag,
+        reward_percentiles: Option<Vec<f64>>,
+    ) -> RpcResult<FeeHistory> {
+        debug!(
+            message = "rpc::fee_history",
+            block_count = %block_count,
+            newest_block = ?newest_block,
+        );
+
+        let block_count = block_count.to::<u64>();
+        let pending_blocks = self.pending_blocks(ext);
+        let Some(pending_blocks) = pending_blocks.as_ref().filter(|_| newest_block.is_pending())
+        else {
+            return EthFees::fee_history(
+                &self.eth_api,
+                block_count,
+                newest_block,
+                reward_percentiles,
+            )
+            .await
+            .map_err(Into::into);
+        };
+
+        if block_count == 0 {
+            return Ok(FeeHistory::default());
+        }
+        if reward_percentiles.as_deref().is_some_and(|p| !fees::valid_reward_percentiles(p)) {
+            return Err(EthApiError::InvalidRewardPercentiles.into());
+        }
+
+        self.metrics.fee_history.increment(1);
+        let latest = pending_blocks.latest_block_number();
+        let pending_count = block_count.min(latest - pending_blocks.earliest_block_number() + 1);
+        let first_pending = latest + 1 - pending_count;
+
+        // Blocks before the pending blocks are served from the canonical chain
+        let history = if block_count > pending_count {
+            EthFees::fee_history(
+                &self.eth_api,
+                block_count - pending_count,
+                BlockNumberOrTag::Number(first_pending - 1),
+                reward_percentiles.clone(),
+            )
+            .await?
+        } else {
+            FeeHistory {
+                oldest_block: first_pending,
+                reward: reward_percentiles.as_ref().map(|_| Vec::new()),
+                ..Default::default()
+            }
+        };
+
+        Ok(fees::extend_fee_history(
+            history,
+            pending_blocks,
+            first_pending,
+            reward_percentiles.as_deref(),
+        )?)
+    }
 }
 
 impl<Eth, FB> EthApiExt<Eth, FB>
@@ -519,49 +1113,101 @@ where
     Eth: FullEthApi<NetworkTypes = Optimism> + Send + Sync + 'static,
     FB: FlashblocksAPI + Send + Sync + 'static,
 {
-    async fn wait_for_flashblocks_receipt(&self, tx_hash: TxHash) -> Option<RpcReceipt<Optimism>> {
-        let mut receiver = self.flashblocks_state.subscribe_to_flashblocks();
+    /// Returns the pending state pinned for the call or its batch, or else the latest one.
+    fn pending_blocks(&self, ext: &Extensions) -> Option<Arc<PendingBlocks>> {
+        match ext.get::<PinnedPendingBlocks>() {
+            Some(pinned) => pinned.pending_blocks(),
+            None => Option::clone(&self.flashblocks_state.get_pending_blocks()),
+        }
+    }
 
-        loop {
-            match receiver.recv().await {
-                Ok(pending_state) if pending_state.get_receipt(tx_hash).is_some() => {
+    /// Waits until a transaction reaches at least the given confirmation level, returning its
+    /// receipt and the level reached.
+    async fn wait_for_confirmation(
+        &self,
+        mut waiter: InclusionWaiter,
+        confirmation_level: ConfirmationLevel,
+    ) -> Result<Option<SendRawTransactionSyncResponse<RpcReceipt<Optimism>>>, Eth::Error> {
+        let tx_hash = waiter.tx_hash();
+        let (mut receipt, reached) = loop {
+            let Some(inclusion) = waiter.recv().await else { return Ok(None) };
+            match inclusion {
+                Inclusion::Preconfirmed(receipt)
+                    if confirmation_level == ConfirmationLevel::Preconfirmed =>
+                {
                     debug!(message = "found receipt in flashblock", tx_hash = %tx_hash);
-                    return pending_state.get_receipt(tx_hash);
+                    break (*receipt, ConfirmationLevel::Preconfirmed);
                 }
-                Ok(_) => {
-                    trace!(message = "flashblock does not contain receipt", tx_hash = %tx_hash);
+                Inclusion::Preconfirmed(_) => {
+                    trace!(
+                        message = "transaction preconfirmed, waiting for canonical inclusion",
+                        tx_hash = %tx_hash
+                    );
                 }
-                Err(RecvError::Closed) => {
-                    debug!(message = "flashblocks receipt queue closed");
-                    return None;
-                }
-                Err(RecvError::Lagged(_)) => {
-                    warn!("Flashblocks receipt queue lagged, maybe missing receipts");
+                Inclusion::Canonical(block_number) => {
+                    debug!(
+                        message = "found receipt in canonical state",
+                        tx_hash = %tx_hash,
+                        block_number = block_number,
+                    );
+                    let receipt = self.wait_for_receipt(tx_hash).await?;
+                    break (receipt, ConfirmationLevel::Canonical);
                 }
             }
+        };
+
+        if confirmation_level != ConfirmationLevel::Safe {
+            return Ok(Some(SendRawTransactionSyncResponse {
+                receipt,
+                confirmation_level: reached,
+            }));
+        }
+
+        // The block may be reorged out before the safe head reaches it, so the receipt is re-read
+        // once it does and only returned if it is still in the same block.
+        loop {
+            let Some(block_number) = receipt.block_number() else { return Ok(None) };
+            self.wait_for_safe_block(block_number).await;
+
+            let current = self.wait_for_receipt(tx_hash).await?;
+            if current.block_hash() == receipt.block_hash() {
+                return Ok(Some(SendRawTransactionSyncResponse {
+                    receipt: current,
+                    confirmation_level: ConfirmationLevel::Safe,
+                }));
+            }
+            debug!(
+                message = "transaction moved to another block, waiting for safe head again",
+                tx_hash = %tx_hash,
+                block_number = block_number,
+            );
+            receipt = current;
         }
     }
 
-    async fn wait_for_canonical_receipt(&self, tx_hash: TxHash) -> Option<RpcReceipt<Optimism>> {
-        let mut stream =
-            BroadcastStream::new(self.eth_api.provider().subscribe_to_canonical_state());
-
-        while let Some(Ok(canon_state)) = stream.next().await {
-            for (block_receipt, _) in canon_state.block_receipts() {
-                for (canonical_tx_hash, _) in &block_receipt.tx_receipts {
-                    if *canonical_tx_hash == tx_hash {
-                        debug!(
-                            message = "found receipt in canonical state",
-                            tx_hash = %tx_hash
-                        );
-                        return EthTransactions::transaction_receipt(&self.eth_api, tx_hash)
-                            .await
-                            .ok()
-                            .flatten();
-                    }
-                }
+    /// Reads the canonical receipt of a transaction, polling until it has been indexed.
+    async fn wait_for_receipt(&self, tx_hash: TxHash) -> Result<RpcReceipt<Optimism>, Eth::Error> {
+        let mut interval = time::interval(RECEIPT_POLL_INTERVAL);
+        loop {
+            interval.tick().await;
+            if let Some(receipt) =
+                EthTransactions::transaction_receipt(&self.eth_api, tx_hash).await?
+            {
+                return Ok(receipt);
+            }
+            trace!(message = "receipt not indexed yet", tx_hash = %tx_hash);
+        }
+    }
+
+    async fn wait_for_safe_block(&self, block_number: BlockNumber) {
+        let mut interval = time::interval(SAFE_HEAD_POLL_INTERVAL);
+        loop {
+            interval.tick().await;
+            let safe_block_number = self.eth_api.provider().safe_block_number().ok().flatten();
+            if safe_block_number.is_some_and(|safe| safe >= block_number) {
+                debug!(message = "block reached safe head", block_number = block_number);
+                return;
             }
         }
-        None
     }
 }
@@ -6,18 +6,37 @@
 mod base;
 pub use base::{
     block::meter_block,
-    meter::meter_bundle,
+    fee_rpc::FlashblocksFeeApiImpl,
+    meter::{meter_bundle, meter_bundle_on_pending_state},
     meter_rpc::MeteringApiImpl,
-    pubsub::{EthPubSub, EthPubSubApiServer},
-    traits::{MeteringApiServer, TransactionStatusApiServer},
+    pubsub::{CURSOR_EVICTED_CODE, EthPubSub, EthPubSubApiServer},
+    quota::{
+        DEFAULT_MAX_SUBSCRIPTION_MESSAGE_SIZE, DEFAULT_MAX_SUBSCRIPTIONS,
+        DEFAULT_MAX_SUBSCRIPTIONS_PER_CONNECTION, SUBSCRIPTION_LIMIT_CODE, SubscriptionLimits,
+    },
+    snapshot_rpc::FlashblockSnapshotApiImpl,
+    traits::{
+        FlashblockSnapshotApiServer, FlashblocksFeeApiServer, MeteringApiServer,
+        TransactionStatusApiServer,
+    },
     transaction_rpc::TransactionStatusApiImpl,
     types::{
-        BaseSubscriptionKind, ExtendedSubscriptionKind, MeterBlockResponse, MeterBlockTransactions,
-        Status, TransactionStatusResponse,
+        AccountUpdate, BaseSubscriptionKind, ConfirmationLevel, ExtendedSubscriptionKind,
+        FlashblockDelta, LagPolicy, MeterBlockResponse, MeterBlockTransactions,
+        NewFlashblocksOptions, PendingInvalidation, PendingResponse, PendingSnapshot,
+        PreconfirmedTransaction, RawFlashblock, SendRawTransactionSyncResponse, Status,
+        SubscriptionLag, TransactionAddressFilter, TransactionStatusResponse,
     },
 };
 
+// Serialized as is in `pendingInvalidations` and `newRawFlashblocks` notifications
+pub use base_reth_flashblocks::{FlashblockStatus, InvalidationReason};
+
 mod eth;
-pub use eth::rpc::{EthApiExt, EthApiOverrideServer};
+pub use eth::{
+    debug::{DebugApiExt, DebugApiOverrideServer},
+    pin::{PendingBlocksPinLayer, PendingBlocksPinService, PinnedPendingBlocks},
+    rpc::{DEFAULT_MAX_TIMEOUT_SEND_RAW_TX_SYNC_MS, EthApiExt, EthApiOverrideServer},
+};
 
 mod metrics;
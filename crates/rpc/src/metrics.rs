@@ -1,4 +1,4 @@
-use metrics::Counter;
+use metrics::{Counter, Gauge};
 use metrics_derive::Metrics;
 /// Metrics for the `base_reth_rpc` component.
 /// Conventions:
@@ -20,12 +20,55 @@ pub(crate) struct Metrics {
     #[metric(describe = "Count of times flashblocks get_balance is called")]
     pub get_balance: Counter,
 
+    #[metric(describe = "Count of times flashblocks get_storage_at is called")]
+    pub get_storage_at: Counter,
+
+    #[metric(describe = "Count of times flashblocks get_code is called")]
+    pub get_code: Counter,
+
     #[metric(describe = "Count of times flashblocks get_block_by_number is called")]
     pub get_block_by_number: Counter,
 
+    #[metric(describe = "Count of times flashblocks get_block_by_hash is called")]
+    pub get_block_by_hash: Counter,
+
+    #[metric(
+        describe = "Count of times flashblocks get_block_transaction_count_by_number is called"
+    )]
+    pub get_block_transaction_count_by_number: Counter,
+
+    #[metric(
+        describe = "Count of times flashblocks get_transaction_by_block_number_and_index is called"
+    )]
+    pub get_transaction_by_block_number_and_index: Counter,
+
+    #[metric(describe = "Count of times flashblocks get_header_by_number is called")]
+    pub get_header_by_number: Counter,
+
+    #[metric(describe = "Count of times flashblocks get_block_receipts is called")]
+    pub get_block_receipts: Counter,
+
     #[metric(describe = "Count of times flashblocks call is called")]
     pub call: Counter,
 
+    #[metric(describe = "Count of times flashblocks gas_price is called")]
+    pub gas_price: Counter,
+
+    #[metric(describe = "Count of times flashblocks max_priority_fee_per_gas is called")]
+    pub max_priority_fee_per_gas: Counter,
+
+    #[metric(describe = "Count of times flashblocks fee_history is called")]
+    pub fee_history: Counter,
+
+    #[metric(describe = "Count of times flashblocks create_access_list is called")]
+    pub create_access_list: Counter,
+
+    #[metric(describe = "Count of times flashblocks debug_trace_call is called")]
+    pub debug_trace_call: Counter,
+
+    #[metric(describe = "Count of times flashblocks debug_trace_transaction is called")]
+    pub debug_trace_transaction: Counter,
+
     #[metric(describe = "Count of times flashblocks estimate_gas is called")]
     pub estimate_gas: Counter,
 
@@ -34,4 +77,33 @@ pub(crate) struct Metrics {
 
     #[metric(describe = "Count of times flashblocks get_logs is called")]
     pub get_logs: Counter,
+
+    #[metric(describe = "Count of times flashblocks get_filter_changes is called")]
+    pub get_filter_changes: Counter,
+
+    #[metric(describe = "Count of times flashblocks get_filter_logs is called")]
+    pub get_filter_logs: Counter,
+
+    #[metric(describe = "Count of times a flashblocks subscriber fell behind and missed updates")]
+    pub subscription_lagged: Counter,
+
+    #[metric(describe = "Count of pending state updates dropped for lagging subscribers")]
+    pub subscription_missed_updates: Counter,
+
+    #[metric(describe = "Count of flashblocks subscriptions closed for falling behind")]
+    pub subscription_lag_disconnects: Counter,
+
+    #[metric(describe = "Count of flashblocks subscriptions rejected for exceeding a limit")]
+    pub rejected_subscriptions: Counter,
+
+    #[metric(describe = "Count of flashblocks subscriptions closed for an oversized notification")]
+    pub oversized_notifications: Counter,
+}
+
+/// Metrics of the Base-specific subscriptions of a kind, labeled with the kind.
+#[derive(Metrics, Clone)]
+#[metrics(scope = "reth_flashblocks")]
+pub(crate) struct SubscriptionKindMetrics {
+    #[metric(describe = "Number of active flashblocks subscriptions")]
+    pub active_subscriptions: Gauge,
 }
@@ -292,6 +292,40 @@ async fn test_eip7702_multiple_delegations_same_flashblock() -> Result<()> {
     Ok(())
 }
 
+/// Test that pending nonces reflect authorizations applied by another account's transaction
+#[tokio::test]
+async fn test_eip7702_authorization_increments_pending_nonce() -> Result<()> {
+    let setup = TestSetup::new().await?;
+    let chain_id = setup.chain_id();
+
+    let base_payload = create_base_flashblock(&setup);
+    setup.send_flashblock(base_payload).await?;
+
+    // Bob sponsors the transaction carrying Alice's authorization
+    let auth_alice =
+        build_authorization(chain_id, setup.account_contract_address, 0, setup.alice());
+    let increment_call = Minimal7702Account::incrementCall {};
+    let eip7702_tx = build_eip7702_tx(
+        chain_id,
+        0,
+        setup.alice().address,
+        U256::ZERO,
+        Bytes::from(increment_call.abi_encode()),
+        vec![auth_alice],
+        setup.bob(),
+    );
+
+    let eip7702_flashblock = create_eip7702_flashblock(eip7702_tx, BASE_CUMULATIVE_GAS + 50000);
+    setup.send_flashblock(eip7702_flashblock).await?;
+
+    // Alice never sent a transaction, but applying her authorization bumped her nonce
+    let provider = setup.provider();
+    assert_eq!(provider.get_transaction_count(setup.alice().address).pending().await?, 1);
+    assert_eq!(provider.get_transaction_count(setup.bob().address).pending().await?, 1);
+
+    Ok(())
+}
+
 /// Test that EIP-7702 transaction receipts are correctly returned from pending state
 #[tokio::test]
 async fn test_eip7702_pending_receipt() -> Result<()> {
@@ -0,0 +1,141 @@
+//! Fee estimation tests for pending state.
+//!
+//! These tests verify that the fee estimation RPCs account for the priority fees
+//! paid by transactions preconfirmed in flashblocks.
+
+use alloy_consensus::{SignableTransaction, TxEip1559};
+use alloy_eips::{BlockNumberOrTag, eip2718::Encodable2718};
+use alloy_primitives::{Address, B256, Bytes, TxKind, U256};
+use alloy_provider::Provider;
+use alloy_rpc_client::RpcClient;
+use alloy_rpc_types_eth::FeeHistory;
+use base_flashtypes::{
+    ExecutionPayloadBaseV1, ExecutionPayloadFlashblockDeltaV1, Flashblock, Metadata,
+};
+use base_reth_test_utils::{Account, FlashblocksHarness, L1_BLOCK_INFO_DEPOSIT_TX, SignerSync};
+use eyre::Result;
+
+const CHAIN_ID: u64 = 84532;
+const GWEI: u128 = 1_000_000_000;
+const TRANSFER_GAS: u64 = 21_000;
+
+/// Build and sign an ETH transfer paying the given priority fee
+fn build_transfer(account: &Account, to: Address, priority_fee: u128) -> Bytes {
+    let tx = TxEip1559 {
+        chain_id: CHAIN_ID,
+        nonce: 0,
+        gas_limit: TRANSFER_GAS,
+        max_fee_per_gas: priority_fee,
+        max_priority_fee_per_gas: priority_fee,
+        to: TxKind::Call(to),
+        value: U256::from(1),
+        access_list: Default::default(),
+        input: Bytes::new(),
+    };
+
+    let signature = account.signer().sign_hash_sync(&tx.signature_hash()).expect("signing works");
+    tx.into_signed(signature).encoded_2718().into()
+}
+
+fn create_base_flashblock() -> Flashblock {
+    Flashblock {
+        payload_id: alloy_rpc_types_engine::PayloadId::new([0; 8]),
+        index: 0,
+        base: Some(ExecutionPayloadBaseV1 {
+            parent_beacon_block_root: B256::default(),
+            parent_hash: B256::default(),
+            fee_recipient: Address::ZERO,
+            prev_randao: B256::default(),
+            block_number: 1,
+            gas_limit: 30_000_000,
+            timestamp: 0,
+            extra_data: Bytes::new(),
+            base_fee_per_gas: U256::ZERO,
+        }),
+        diff: ExecutionPayloadFlashblockDeltaV1 {
+            blob_gas_used: Some(0),
+            transactions: vec![L1_BLOCK_INFO_DEPOSIT_TX.clone()],
+            ..Default::default()
+        },
+        metadata: Metadata { block_number: 1 },
+    }
+}
+
+fn create_transfers_flashblock(transactions: Vec<Bytes>) -> Flashblock {
+    Flashblock {
+        payload_id: alloy_rpc_types_engine::PayloadId::new([0; 8]),
+        index: 1,
+        base: None,
+        diff: ExecutionPayloadFlashblockDeltaV1 {
+            gas_used: TRANSFER_GAS * transactions.len() as u64,
+            blob_gas_used: Some(0),
+            transactions,
+            ..Default::default()
+        },
+        metadata: Metadata { block_number: 1 },
+    }
+}
+
+/// Sends flashblocks with two transfers paying priority fees of 2 and 4 gwei
+async fn send_pending_transfers(harness: &FlashblocksHarness) -> Result<()> {
+    let accounts = harness.accounts();
+    harness.send_flashblock(create_base_flashblock()).await?;
+    harness
+        .send_flashblock(create_transfers_flashblock(vec![
+            build_transfer(&accounts.alice, accounts.bob.address, 2 * GWEI),
+            build_transfer(&accounts.charlie, accounts.bob.address, 4 * GWEI),
+        ]))
+        .await
+}
+
+#[tokio::test]
+async fn test_max_priority_fee_per_gas_includes_pending_tips() -> Result<()> {
+    let harness = FlashblocksHarness::new().await?;
+    let provider = harness.provider();
+
+    let canonical_priority_fee = provider.get_max_priority_fee_per_gas().await?;
+    assert!(canonical_priority_fee < 4 * GWEI);
+
+    send_pending_transfers(&harness).await?;
+
+    // Both transfers use the same gas, so the 60th percentile falls on the 4 gwei tip
+    assert_eq!(provider.get_max_priority_fee_per_gas().await?, 4 * GWEI);
+    assert!(provider.get_gas_price().await? >= 4 * GWEI);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_fee_history_includes_pending_block() -> Result<()> {
+    let harness = FlashblocksHarness::new().await?;
+    let provider = harness.provider();
+
+    send_pending_transfers(&harness).await?;
+
+    let history = provider.get_fee_history(2, BlockNumberOrTag::Pending, &[25.0, 75.0]).await?;
+    assert_eq!(history.oldest_block, 0);
+    assert_eq!(history.gas_used_ratio.len(), 2);
+    assert_eq!(history.base_fee_per_gas.len(), 3);
+
+    let rewards = history.reward.expect("rewards requested");
+    assert_eq!(rewards.len(), 2);
+    assert_eq!(rewards[1], vec![2 * GWEI, 4 * GWEI]);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_estimate_next_base_fee() -> Result<()> {
+    let harness = FlashblocksHarness::new().await?;
+    let provider = harness.provider();
+    let client = RpcClient::new_http(harness.rpc_url().parse()?);
+
+    send_pending_transfers(&harness).await?;
+
+    let next_base_fee: U256 = client.request_noparams("base_estimateNextBaseFee").await?;
+    let history: FeeHistory = provider.get_fee_history(1, BlockNumberOrTag::Pending, &[]).await?;
+    assert_eq!(history.oldest_block, 1);
+    assert_eq!(next_base_fee, U256::from(*history.base_fee_per_gas.last().unwrap()));
+
+    Ok(())
+}
@@ -3,20 +3,30 @@
 use std::str::FromStr;
 
 use DoubleCounter::DoubleCounterInstance;
-use alloy_consensus::Transaction;
-use alloy_eips::BlockNumberOrTag;
+use alloy_consensus::{Sealed, Transaction};
+use alloy_eips::{
+    BlockNumberOrTag,
+    eip2718::{Decodable2718, Encodable2718},
+};
 use alloy_primitives::{Address, B256, Bytes, TxHash, U256, address, b256, bytes};
 use alloy_provider::Provider;
 use alloy_rpc_client::RpcClient;
 use alloy_rpc_types::simulate::{SimBlock, SimulatePayload};
 use alloy_rpc_types_engine::PayloadId;
 use alloy_rpc_types_eth::{TransactionInput, error::EthRpcErrorCode};
+use alloy_rpc_types_trace::geth::{GethTrace, PreStateFrame};
+use base_bundles::{Bundle, MeterBundleResponse};
 use base_flashtypes::{
     ExecutionPayloadBaseV1, ExecutionPayloadFlashblockDeltaV1, Flashblock, Metadata,
 };
+use base_reth_rpc::{
+    CURSOR_EVICTED_CODE, ConfirmationLevel, DEFAULT_MAX_SUBSCRIPTIONS_PER_CONNECTION,
+    PendingResponse, PendingSnapshot, SUBSCRIPTION_LIMIT_CODE, SendRawTransactionSyncResponse,
+};
 use base_reth_test_utils::{DoubleCounter, FlashblocksHarness, L1_BLOCK_INFO_DEPOSIT_TX};
 use eyre::Result;
 use futures_util::{SinkExt, StreamExt};
+use op_alloy_consensus::OpTxEnvelope;
 use op_alloy_network::{Optimism, ReceiptResponse, TransactionResponse};
 use op_alloy_rpc_types::OpTransactionRequest;
 use reth::revm::context::TransactionType;
@@ -138,6 +148,19 @@ fn wrap_in_init_code(runtime_hex: &str) -> Bytes {
     Bytes::from(init_code)
 }
 
+/// Returns the L1 block info deposit with another source hash, so that it can open a later block.
+fn l1_block_info_deposit(source_hash: B256) -> Bytes {
+    let OpTxEnvelope::Deposit(deposit) =
+        OpTxEnvelope::decode_2718(&mut L1_BLOCK_INFO_DEPOSIT_TX.as_ref())
+            .expect("should be able to decode the L1 block info deposit")
+    else {
+        panic!("expected a deposit transaction")
+    };
+    let mut deposit = deposit.into_inner();
+    deposit.source_hash = source_hash;
+    OpTxEnvelope::Deposit(Sealed::new(deposit)).encoded_2718().into()
+}
+
 struct TestSetup {
     harness: FlashblocksHarness,
     txn_details: TransactionDetails,
@@ -298,7 +321,7 @@ impl TestSetup {
                 state_root: B256::default(),
                 receipts_root: B256::default(),
                 gas_used: 0,
-                block_hash: B256::default(),
+                block_hash: PENDING_BLOCK_HASH,
                 blob_gas_used: Some(0),
                 transactions: vec![
                     DEPOSIT_TX,
@@ -351,21 +374,27 @@ impl TestSetup {
         &self,
         tx: Bytes,
         timeout_ms: Option<u64>,
-    ) -> Result<RpcReceipt<Optimism>> {
+        confirmation_level: Option<ConfirmationLevel>,
+    ) -> Result<SendRawTransactionSyncResponse<RpcReceipt<Optimism>>> {
         let url = self.harness.rpc_url();
         let client = RpcClient::new_http(url.parse()?);
 
-        let receipt = client
-            .request::<_, RpcReceipt<Optimism>>("eth_sendRawTransactionSync", (tx, timeout_ms))
+        let response = client
+            .request::<_, SendRawTransactionSyncResponse<RpcReceipt<Optimism>>>(
+                "eth_sendRawTransactionSync",
+                (tx, timeout_ms, confirmation_level),
+            )
             .await?;
 
-        Ok(receipt)
+        Ok(response)
     }
 }
 
 // Test constants
 const TEST_ADDRESS: Address = address!("0x1234567890123456789012345678901234567890");
 const PENDING_BALANCE: u64 = 4660;
+const PENDING_BLOCK_HASH: B256 =
+    b256!("0x6b1f0e5d4c3b2a1908f7e6d5c4b3a2918f7e6d5c4b3a2918f7e6d5c4b3a2918");
 
 const DEPOSIT_SENDER: Address = address!("0xdeaddeaddeaddeaddeaddeaddeaddeaddead0001");
 const DEPOSIT_TX: Bytes = bytes!(
@@ -432,6 +461,155 @@ async fn test_get_pending_block() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn test_get_block_by_hash_pending() -> Result<()> {
+    let setup = TestSetup::new().await?;
+    let provider = setup.harness.provider();
+
+    assert!(provider.get_block_by_hash(PENDING_BLOCK_HASH).await?.is_none());
+
+    setup.send_test_payloads().await?;
+
+    let block =
+        provider.get_block_by_hash(PENDING_BLOCK_HASH).await?.expect("pending block expected");
+    assert_eq!(block.number(), 1);
+    assert_eq!(block.hash(), PENDING_BLOCK_HASH);
+    assert_eq!(block.transactions.hashes().len(), 10);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_get_block_transaction_count_pending() -> Result<()> {
+    let setup = TestSetup::new().await?;
+    let provider = setup.harness.provider();
+
+    setup.send_test_payloads().await?;
+
+    let count = provider.get_block_transaction_count_by_number(BlockNumberOrTag::Pending).await?;
+    assert_eq!(count, Some(10));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_get_transaction_by_block_number_and_index_pending() -> Result<()> {
+    let setup = TestSetup::new().await?;
+    let provider = setup.harness.provider();
+
+    setup.send_test_payloads().await?;
+
+    // L1Info deposit, DEPOSIT_TX, then alice's ETH transfer
+    let tx = provider
+        .get_transaction_by_block_number_and_index(BlockNumberOrTag::Pending, 2)
+        .await?
+        .expect("pending transaction expected");
+    assert_eq!(tx.tx_hash(), setup.txn_details.alice_eth_transfer_hash);
+    assert_eq!(tx.block_hash(), Some(PENDING_BLOCK_HASH));
+
+    let missing =
+        provider.get_transaction_by_block_number_and_index(BlockNumberOrTag::Pending, 10).await?;
+    assert!(missing.is_none());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_get_header_by_number_pending() -> Result<()> {
+    let setup = TestSetup::new().await?;
+    let client = RpcClient::new_http(setup.harness.rpc_url().parse()?);
+
+    setup.send_test_payloads().await?;
+
+    let header: Option<alloy_rpc_types_eth::Header> =
+        client.request("eth_getHeaderByNumber", (BlockNumberOrTag::Pending,)).await?;
+    let header = header.expect("pending header expected");
+    assert_eq!(header.number, 1);
+    assert_eq!(header.hash, PENDING_BLOCK_HASH);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_pending_responses_carry_snapshot() -> Result<()> {
+    let setup = TestSetup::new().await?;
+    let client = RpcClient::new_http(setup.harness.rpc_url().parse()?);
+
+    setup.send_test_payloads().await?;
+
+    let header: Option<PendingResponse<alloy_rpc_types_eth::Header>> =
+        client.request("eth_getHeaderByNumber", (BlockNumberOrTag::Pending,)).await?;
+    let header = header.expect("pending header expected");
+    assert_eq!(header.inner.number, 1);
+    assert_eq!(
+        header.pending_snapshot,
+        Some(PendingSnapshot { block_number: 1, flashblock_index: 1 })
+    );
+
+    let receipt: Option<PendingResponse<RpcReceipt<Optimism>>> = client
+        .request("eth_getTransactionReceipt", (setup.txn_details.alice_eth_transfer_hash,))
+        .await?;
+    let receipt = receipt.expect("pending receipt expected");
+    assert_eq!(receipt.inner.transaction_hash(), setup.txn_details.alice_eth_transfer_hash);
+    assert_eq!(
+        receipt.pending_snapshot,
+        Some(PendingSnapshot { block_number: 1, flashblock_index: 1 })
+    );
+
+    // Canonical responses are not annotated
+    let header: Option<PendingResponse<alloy_rpc_types_eth::Header>> =
+        client.request("eth_getHeaderByNumber", (BlockNumberOrTag::Latest,)).await?;
+    assert_eq!(header.expect("latest header expected").pending_snapshot, None);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_batch_is_served_from_one_snapshot() -> Result<()> {
+    let setup = TestSetup::new().await?;
+    let client = RpcClient::new_http(setup.harness.rpc_url().parse()?);
+
+    setup.send_test_payloads().await?;
+
+    let mut batch = client.new_batch();
+    let header = batch.add_call::<_, Option<PendingResponse<alloy_rpc_types_eth::Header>>>(
+        "eth_getHeaderByNumber",
+        &(BlockNumberOrTag::Pending,),
+    )?;
+    let receipt = batch.add_call::<_, Option<PendingResponse<RpcReceipt<Optimism>>>>(
+        "eth_getTransactionReceipt",
+        &(setup.txn_details.alice_eth_transfer_hash,),
+    )?;
+    batch.send().await?;
+
+    let header = header.await?.expect("pending header expected");
+    let receipt = receipt.await?.expect("pending receipt expected");
+    assert!(header.pending_snapshot.is_some());
+    assert_eq!(header.pending_snapshot, receipt.pending_snapshot);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_get_block_receipts_pending() -> Result<()> {
+    let setup = TestSetup::new().await?;
+    let provider = setup.harness.provider();
+
+    setup.send_test_payloads().await?;
+
+    let receipts = provider
+        .get_block_receipts(BlockNumberOrTag::Pending.into())
+        .await?
+        .expect("pending receipts expected");
+    assert_eq!(receipts.len(), 10);
+    assert_eq!(receipts[1].transaction_hash(), DEPOSIT_TX_HASH);
+    assert_eq!(receipts[2].transaction_hash(), setup.txn_details.alice_eth_transfer_hash);
+    // Receipts of transactions from earlier flashblocks carry the hash of the latest one
+    assert!(receipts.iter().all(|receipt| receipt.block_hash() == Some(PENDING_BLOCK_HASH)));
+
+    Ok(())
+}
+
 #[tokio::test]
 async fn test_get_balance_pending() -> Result<()> {
     let setup = TestSetup::new().await?;
@@ -447,6 +625,100 @@ async fn test_get_balance_pending() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn test_get_storage_at_pending() -> Result<()> {
+    let setup = TestSetup::new().await?;
+    let provider = setup.harness.provider();
+    let counter_address = setup.txn_details.counter_address;
+
+    setup.send_test_payloads().await?;
+
+    // The counter only exists in pending state
+    assert_eq!(provider.get_storage_at(counter_address, U256::ZERO).await?, U256::ZERO);
+
+    // Both counters start at 1 and are incremented once
+    assert_eq!(
+        provider.get_storage_at(counter_address, U256::ZERO).pending().await?,
+        U256::from(2)
+    );
+    assert_eq!(
+        provider.get_storage_at(counter_address, U256::from(1)).pending().await?,
+        U256::from(2)
+    );
+
+    // Slots never written by the freshly created contract are empty
+    assert_eq!(
+        provider.get_storage_at(counter_address, U256::from(2)).pending().await?,
+        U256::ZERO
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_get_code_pending() -> Result<()> {
+    let setup = TestSetup::new().await?;
+    let provider = setup.harness.provider();
+    let log_emitter_b_address = setup.txn_details.log_emitter_b_address;
+
+    setup.send_test_payloads().await?;
+
+    assert!(provider.get_code_at(log_emitter_b_address).await?.is_empty());
+
+    let pending_code = provider.get_code_at(log_emitter_b_address).pending().await?;
+    assert_eq!(pending_code, Bytes::from_str(LOG_EMITTER_B_RUNTIME)?);
+
+    // Accounts touched by pending transactions without code have none
+    let alice_code = provider.get_code_at(setup.harness.accounts().alice.address).pending().await?;
+    assert!(alice_code.is_empty());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_get_balance_at_flashblock() -> Result<()> {
+    let setup = TestSetup::new().await?;
+    let client = RpcClient::new_http(setup.harness.rpc_url().parse()?);
+
+    setup.send_test_payloads().await?;
+
+    let balance_at_base: U256 = client
+        .request("base_getBalanceAtFlashblock", (TEST_ADDRESS, BlockNumberOrTag::Number(1), 0))
+        .await?;
+    assert_eq!(balance_at_base, U256::ZERO);
+
+    let balance_at_second: U256 = client
+        .request("base_getBalanceAtFlashblock", (TEST_ADDRESS, BlockNumberOrTag::Pending, 1))
+        .await?;
+    assert_eq!(balance_at_second, U256::from(PENDING_BALANCE));
+
+    let receipt_at_base: Option<RpcReceipt<Optimism>> = client
+        .request(
+            "base_getTransactionReceiptAtFlashblock",
+            (setup.txn_details.alice_eth_transfer_hash, BlockNumberOrTag::Number(1), 0),
+        )
+        .await?;
+    assert!(receipt_at_base.is_none());
+
+    let receipt_at_second: Option<RpcReceipt<Optimism>> = client
+        .request(
+            "base_getTransactionReceiptAtFlashblock",
+            (setup.txn_details.alice_eth_transfer_hash, BlockNumberOrTag::Number(1), 1),
+        )
+        .await?;
+    assert!(receipt_at_second.is_some());
+
+    let missing_snapshot = client
+        .request::<_, U256>(
+            "base_getBalanceAtFlashblock",
+            (TEST_ADDRESS, BlockNumberOrTag::Number(1), 2),
+        )
+        .await;
+    assert!(missing_snapshot.is_err());
+
+    Ok(())
+}
+
 #[tokio::test]
 async fn test_get_transaction_by_hash_pending() -> Result<()> {
     let setup = TestSetup::new().await?;
@@ -523,6 +795,11 @@ async fn test_get_transaction_count() -> Result<()> {
     assert_eq!(provider.get_transaction_count(deployer_addr).pending().await?, 6);
     // Alice has: big ETH transfer (0), balance transfer to TEST_ADDRESS (1) = nonce 2
     assert_eq!(provider.get_transaction_count(alice_addr).pending().await?, 2);
+    // Contracts start with a nonce of 1 once created
+    assert_eq!(
+        provider.get_transaction_count(setup.txn_details.counter_address).pending().await?,
+        1
+    );
 
     Ok(())
 }
@@ -570,6 +847,154 @@ async fn test_eth_call() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn test_eth_create_access_list() -> Result<()> {
+    let setup = TestSetup::new().await?;
+    let provider = setup.harness.provider();
+    let request = setup.count1().from(setup.harness.accounts().alice.address);
+
+    setup.send_test_payloads().await?;
+
+    // The counter only exists in pending state, so reading count1 from the latest block does not
+    // execute any code
+    let latest = provider.create_access_list(&request).latest().await?;
+    let pending = provider.create_access_list(&request).pending().await?;
+    assert!(pending.error.is_none());
+    assert!(pending.gas_used > latest.gas_used);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_debug_trace_call() -> Result<()> {
+    let setup = TestSetup::new().await?;
+    let client = RpcClient::new_http(setup.harness.rpc_url().parse()?);
+    let counter_address = setup.txn_details.counter_address;
+
+    setup.send_test_payloads().await?;
+
+    let trace: GethTrace =
+        client.request("debug_traceCall", (setup.count1(), BlockNumberOrTag::Pending)).await?;
+    let GethTrace::Default(frame) = trace else { panic!("expected struct logger trace") };
+    assert!(!frame.failed);
+    assert_eq!(U256::from_be_slice(&frame.return_value), U256::from(2));
+
+    // User overrides are applied on top of the pending state
+    let opts = json!({
+        "stateOverrides": {
+            counter_address.to_string(): {
+                "stateDiff": { B256::ZERO.to_string(): B256::from(U256::from(5)).to_string() }
+            }
+        }
+    });
+    let trace: GethTrace = client
+        .request("debug_traceCall", (setup.count1(), BlockNumberOrTag::Pending, opts))
+        .await?;
+    let GethTrace::Default(frame) = trace else { panic!("expected struct logger trace") };
+    assert_eq!(U256::from_be_slice(&frame.return_value), U256::from(5));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_debug_trace_transaction_pending() -> Result<()> {
+    let setup = TestSetup::new().await?;
+    let client = RpcClient::new_http(setup.harness.rpc_url().parse()?);
+    let accounts = setup.harness.accounts();
+    let transfer_hash = setup.txn_details.alice_eth_transfer_hash;
+
+    setup.send_test_payloads().await?;
+
+    let opts = json!({ "tracer": "callTracer" });
+    let trace: GethTrace = client.request("debug_traceTransaction", (transfer_hash, opts)).await?;
+    let GethTrace::CallTracer(frame) = trace else { panic!("expected call tracer trace") };
+    assert_eq!(frame.from, accounts.alice.address);
+    assert_eq!(frame.to, Some(accounts.bob.address));
+    assert_eq!(frame.value, Some(U256::from_str("999999999000000000000000").unwrap()));
+
+    let opts = json!({ "tracer": "prestateTracer", "tracerConfig": { "diffMode": true } });
+    let trace: GethTrace = client.request("debug_traceTransaction", (transfer_hash, opts)).await?;
+    let GethTrace::PreStateTracer(PreStateFrame::Diff(diff)) = trace else {
+        panic!("expected prestate diff trace")
+    };
+    assert_eq!(diff.pre[&accounts.alice.address].nonce, Some(0));
+    assert_eq!(diff.post[&accounts.alice.address].nonce, Some(1));
+    assert!(diff.post.contains_key(&accounts.bob.address));
+
+    // The opcode logger runs on top of the preceding pending transactions
+    let trace: GethTrace = client
+        .request("debug_traceTransaction", (setup.txn_details.log_trigger_hash, json!({})))
+        .await?;
+    let GethTrace::Default(frame) = trace else { panic!("expected struct logger trace") };
+    assert!(!frame.failed);
+    assert!(frame.struct_logs.iter().any(|log| log.op == "LOG2"));
+
+    // Deposits are replayed with the rest of the block
+    let opts = json!({ "tracer": "callTracer" });
+    let trace: GethTrace =
+        client.request("debug_traceTransaction", (DEPOSIT_TX_HASH, opts)).await?;
+    let GethTrace::CallTracer(frame) = trace else { panic!("expected call tracer trace") };
+    assert_eq!(frame.from, DEPOSIT_SENDER);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_debug_trace_transaction_in_second_pending_block() -> Result<()> {
+    let setup = TestSetup::new().await?;
+    let client = RpcClient::new_http(setup.harness.rpc_url().parse()?);
+    let deployer = &setup.harness.accounts().deployer;
+    let counter =
+        DoubleCounterInstance::new(setup.txn_details.counter_address, setup.harness.provider());
+
+    let (increment_tx, _) = deployer
+        .sign_txn_request(counter.increment().into_transaction_request().nonce(6))
+        .expect("should be able to sign increment() txn");
+    let (count1_tx, count1_hash) = deployer
+        .sign_txn_request(counter.count1().into_transaction_request().nonce(7))
+        .expect("should be able to sign count1() txn");
+
+    setup.send_test_payloads().await?;
+    setup
+        .send_flashblock(Flashblock {
+            payload_id: PayloadId::new([1; 8]),
+            index: 0,
+            base: Some(ExecutionPayloadBaseV1 {
+                parent_beacon_block_root: B256::default(),
+                parent_hash: PENDING_BLOCK_HASH,
+                fee_recipient: Address::ZERO,
+                prev_randao: B256::default(),
+                block_number: 2,
+                gas_limit: 30_000_000,
+                timestamp: 2,
+                extra_data: Bytes::new(),
+                base_fee_per_gas: U256::ZERO,
+            }),
+            diff: ExecutionPayloadFlashblockDeltaV1 {
+                block_hash: B256::with_last_byte(2),
+                blob_gas_used: Some(0),
+                transactions: vec![
+                    l1_block_info_deposit(B256::with_last_byte(2)),
+                    increment_tx,
+                    count1_tx,
+                ],
+                ..Default::default()
+            },
+            metadata: Metadata { block_number: 2 },
+        })
+        .await?;
+
+    // The counter was incremented in the first pending block and again before the traced call in
+    // the second one
+    let trace: GethTrace =
+        client.request("debug_traceTransaction", (count1_hash, json!({}))).await?;
+    let GethTrace::Default(frame) = trace else { panic!("expected struct logger trace") };
+    assert!(!frame.failed);
+    assert_eq!(U256::from_be_slice(&frame.return_value), U256::from(3));
+
+    Ok(())
+}
+
 #[tokio::test]
 async fn test_eth_estimate_gas() -> Result<()> {
     let setup = TestSetup::new().await?;
@@ -657,6 +1082,59 @@ async fn test_eth_simulate_v1() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn test_meter_bundle_pending() -> Result<()> {
+    let setup = TestSetup::new().await?;
+    setup.send_test_payloads().await?;
+
+    // The deployer used nonces 0 to 5 in the pending block
+    let deployer = &setup.harness.accounts().deployer;
+    let (transfer_tx, transfer_hash) = deployer
+        .sign_txn_request(
+            OpTransactionRequest::default()
+                .from(deployer.address)
+                .transaction_type(TransactionType::Eip1559.into())
+                .gas_limit(21_000)
+                .nonce(6)
+                .to(TEST_ADDRESS)
+                .value(U256::from(1))
+                .into(),
+        )
+        .expect("should be able to sign transfer txn");
+    let bundle = Bundle { txs: vec![transfer_tx], block_number: 1, ..Default::default() };
+
+    let client = RpcClient::new_http(setup.harness.rpc_url().parse()?);
+    let response: MeterBundleResponse =
+        client.request("base_meterBundle", (bundle.clone(), BlockNumberOrTag::Pending)).await?;
+
+    assert_eq!(response.state_block_number, 1);
+    assert_eq!(response.state_flashblock_index, Some(1));
+    assert_eq!(response.results.len(), 1);
+    assert_eq!(response.results[0].tx_hash, transfer_hash);
+    assert_eq!(response.total_gas_used, 21_000);
+
+    // The nonce is too high for the latest canonical state
+    let latest: Result<MeterBundleResponse, _> =
+        client.request("base_meterBundle", (bundle.clone(), BlockNumberOrTag::Latest)).await;
+    assert!(latest.is_err());
+
+    // The bundle cannot require a timestamp after the pending block's
+    let pending_timestamp = setup
+        .harness
+        .provider()
+        .get_block_by_number(BlockNumberOrTag::Pending)
+        .await?
+        .expect("pending block expected")
+        .header
+        .timestamp;
+    let late_bundle = Bundle { min_timestamp: Some(pending_timestamp + 1), ..bundle };
+    let late: Result<MeterBundleResponse, _> =
+        client.request("base_meterBundle", (late_bundle, BlockNumberOrTag::Pending)).await;
+    assert!(late.is_err());
+
+    Ok(())
+}
+
 #[tokio::test]
 async fn test_send_raw_transaction_sync() -> Result<()> {
     let setup = TestSetup::new().await?;
@@ -666,7 +1144,11 @@ async fn test_send_raw_transaction_sync() -> Result<()> {
     // run the Tx sync and, in parallel, deliver the payload that contains the Tx
     let second_payload = setup.create_second_payload();
     let (receipt_result, payload_result) = tokio::join!(
-        setup.send_raw_transaction_sync(setup.txn_details.alice_eth_transfer_tx.clone(), None),
+        setup.send_raw_transaction_sync(
+            setup.txn_details.alice_eth_transfer_tx.clone(),
+            None,
+            None
+        ),
         async {
             tokio::time::sleep(std::time::Duration::from_millis(100)).await;
             setup.send_flashblock(second_payload).await
@@ -674,9 +1156,36 @@ async fn test_send_raw_transaction_sync() -> Result<()> {
     );
 
     payload_result?;
-    let receipt = receipt_result?;
+    let response = receipt_result?;
+
+    assert_eq!(response.receipt.transaction_hash(), setup.txn_details.alice_eth_transfer_hash);
+    assert_eq!(response.confirmation_level, ConfirmationLevel::Preconfirmed);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_send_raw_transaction_sync_canonical() -> Result<()> {
+    let setup = TestSetup::new().await?;
+    let transfer_tx = setup.txn_details.alice_eth_transfer_tx.clone();
+
+    let (response, block_result) = tokio::join!(
+        setup.send_raw_transaction_sync(
+            transfer_tx.clone(),
+            None,
+            Some(ConfirmationLevel::Canonical)
+        ),
+        async {
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+            setup.harness.build_block_from_transactions(vec![transfer_tx.clone()]).await
+        }
+    );
 
-    assert_eq!(receipt.transaction_hash(), setup.txn_details.alice_eth_transfer_hash);
+    block_result?;
+    let response = response?;
+
+    assert_eq!(response.receipt.transaction_hash(), setup.txn_details.alice_eth_transfer_hash);
+    assert_eq!(response.receipt.block_number(), Some(1));
+    assert_eq!(response.confirmation_level, ConfirmationLevel::Canonical);
     Ok(())
 }
 
@@ -686,7 +1195,7 @@ async fn test_send_raw_transaction_sync_timeout() {
 
     // fail request immediately by passing a timeout of 0 ms
     let receipt_result = setup
-        .send_raw_transaction_sync(setup.txn_details.alice_eth_transfer_tx.clone(), Some(0))
+        .send_raw_transaction_sync(setup.txn_details.alice_eth_transfer_tx.clone(), Some(0), None)
         .await;
 
     let error_code = EthRpcErrorCode::TransactionConfirmationTimeout.code();
@@ -866,37 +1375,115 @@ async fn test_get_logs_mixed_block_ranges() -> Result<()> {
 
 // eth_ subscription methods for flashblocks
 #[tokio::test]
-async fn test_eth_subscribe_new_flashblocks() -> eyre::Result<()> {
+async fn test_filter_changes_include_pending_logs() -> Result<()> {
     let setup = TestSetup::new().await?;
-    let _provider = setup.harness.provider();
-    let ws_url = setup.harness.ws_url();
-    let (mut ws_stream, _) = connect_async(&ws_url).await?;
+    let provider = setup.harness.provider();
 
-    ws_stream
-        .send(Message::Text(
-            json!({
-                "jsonrpc": "2.0",
-                "id": 1,
-                "method": "eth_subscribe",
-                "params": ["newFlashblocks"]
-            })
-            .to_string()
-            .into(),
-        ))
+    let filter_id = provider
+        .new_filter(
+            &alloy_rpc_types_eth::Filter::default()
+                .address(setup.txn_details.log_emitter_a_address),
+        )
         .await?;
 
-    let response = ws_stream.next().await.unwrap()?;
-    let sub: serde_json::Value = serde_json::from_str(response.to_text()?)?;
-    assert_eq!(sub["jsonrpc"], "2.0");
-    assert_eq!(sub["id"], 1);
-    let subscription_id = sub["result"].as_str().expect("subscription id expected");
+    setup.send_test_payloads().await?;
 
-    setup.send_flashblock(setup.create_first_payload()).await?;
+    let logs: Vec<alloy_rpc_types_eth::Log> = provider.get_filter_changes(filter_id).await?;
+    assert_eq!(logs.len(), 1);
+    assert_eq!(logs[0].transaction_hash, Some(setup.txn_details.log_trigger_hash));
+    assert!(!logs[0].removed);
 
-    let notification = ws_stream.next().await.unwrap()?;
-    let notif: serde_json::Value = serde_json::from_str(notification.to_text()?)?;
-    assert_eq!(notif["method"], "eth_subscription");
-    assert_eq!(notif["params"]["subscription"], subscription_id);
+    // Pending logs are only delivered once
+    let logs: Vec<alloy_rpc_types_eth::Log> = provider.get_filter_changes(filter_id).await?;
+    assert!(logs.is_empty());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_filter_changes_mark_dropped_pending_logs_removed() -> Result<()> {
+    let setup = TestSetup::new().await?;
+    let provider = setup.harness.provider();
+
+    let filter_id = provider.new_filter(&alloy_rpc_types_eth::Filter::default()).await?;
+
+    setup.send_test_payloads().await?;
+    let logs: Vec<alloy_rpc_types_eth::Log> = provider.get_filter_changes(filter_id).await?;
+    assert_eq!(logs.len(), 2);
+
+    // The canonical block does not include the pending log trigger transaction
+    setup.harness.build_block_from_transactions(vec![]).await?;
+
+    let mut removed = Vec::new();
+    for _ in 0..10 {
+        let logs: Vec<alloy_rpc_types_eth::Log> = provider.get_filter_changes(filter_id).await?;
+        removed.extend(logs);
+        if !removed.is_empty() {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    }
+
+    assert_eq!(removed.len(), 2);
+    assert!(removed.iter().all(|log| log.removed));
+    assert!(
+        removed.iter().all(|log| log.transaction_hash == Some(setup.txn_details.log_trigger_hash))
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_pending_transaction_filter_changes() -> Result<()> {
+    let setup = TestSetup::new().await?;
+    let provider = setup.harness.provider();
+
+    let filter_id = provider.new_pending_transactions_filter(false).await?;
+
+    setup.send_test_payloads().await?;
+
+    let hashes: Vec<B256> = provider.get_filter_changes(filter_id).await?;
+    assert!(hashes.contains(&setup.txn_details.alice_eth_transfer_hash));
+    assert!(hashes.contains(&setup.txn_details.log_trigger_hash));
+
+    let hashes: Vec<B256> = provider.get_filter_changes(filter_id).await?;
+    assert!(!hashes.contains(&setup.txn_details.alice_eth_transfer_hash));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_eth_subscribe_new_flashblocks() -> eyre::Result<()> {
+    let setup = TestSetup::new().await?;
+    let _provider = setup.harness.provider();
+    let ws_url = setup.harness.ws_url();
+    let (mut ws_stream, _) = connect_async(&ws_url).await?;
+
+    ws_stream
+        .send(Message::Text(
+            json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": "eth_subscribe",
+                "params": ["newFlashblocks"]
+            })
+            .to_string()
+            .into(),
+        ))
+        .await?;
+
+    let response = ws_stream.next().await.unwrap()?;
+    let sub: serde_json::Value = serde_json::from_str(response.to_text()?)?;
+    assert_eq!(sub["jsonrpc"], "2.0");
+    assert_eq!(sub["id"], 1);
+    let subscription_id = sub["result"].as_str().expect("subscription id expected");
+
+    setup.send_flashblock(setup.create_first_payload()).await?;
+
+    let notification = ws_stream.next().await.unwrap()?;
+    let notif: serde_json::Value = serde_json::from_str(notification.to_text()?)?;
+    assert_eq!(notif["method"], "eth_subscription");
+    assert_eq!(notif["params"]["subscription"], subscription_id);
 
     let block = &notif["params"]["result"];
     assert_eq!(block["number"], "0x1");
@@ -955,6 +1542,182 @@ async fn test_eth_subscribe_multiple_flashblocks() -> eyre::Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn test_eth_subscribe_new_flashblocks_deltas() -> eyre::Result<()> {
+    let setup = TestSetup::new().await?;
+    let ws_url = setup.harness.ws_url();
+
+    // Subscribe once to full blocks and once to deltas, to compare the bytes sent
+    let mut streams = Vec::new();
+    for params in [json!(["newFlashblocks"]), json!(["newFlashblocks", true])] {
+        let (mut ws_stream, _) = connect_async(&ws_url).await?;
+        ws_stream
+            .send(Message::Text(
+                json!({
+                    "jsonrpc": "2.0",
+                    "id": 1,
+                    "method": "eth_subscribe",
+                    "params": params
+                })
+                .to_string()
+                .into(),
+            ))
+            .await?;
+        let response = ws_stream.next().await.unwrap()?;
+        let sub: serde_json::Value = serde_json::from_str(response.to_text()?)?;
+        assert!(sub["result"].is_string());
+        streams.push(ws_stream);
+    }
+
+    let empty_payload = Flashblock {
+        payload_id: PayloadId::new([0; 8]),
+        index: 2,
+        base: None,
+        diff: ExecutionPayloadFlashblockDeltaV1 {
+            block_hash: PENDING_BLOCK_HASH,
+            blob_gas_used: Some(0),
+            ..Default::default()
+        },
+        metadata: Metadata { block_number: 1 },
+    };
+    let payloads = [setup.create_first_payload(), setup.create_second_payload(), empty_payload];
+
+    let mut full_bytes = Vec::new();
+    let mut deltas = Vec::new();
+    let mut delta_bytes = Vec::new();
+    for payload in payloads {
+        setup.send_flashblock(payload).await?;
+
+        let full = streams[0].next().await.unwrap()?;
+        full_bytes.push(full.len());
+
+        let delta = streams[1].next().await.unwrap()?;
+        delta_bytes.push(delta.len());
+        let delta: serde_json::Value = serde_json::from_str(delta.to_text()?)?;
+        deltas.push(delta["params"]["result"].clone());
+    }
+
+    // The first delta of a block carries the full header
+    assert_eq!(deltas[0]["blockNumber"], 1);
+    assert_eq!(deltas[0]["index"], 0);
+    assert_eq!(deltas[0]["header"]["number"], "0x1");
+    assert_eq!(deltas[0]["transactions"].as_array().unwrap().len(), 1);
+    assert_eq!(deltas[0]["receipts"].as_array().unwrap().len(), 1);
+
+    // Later deltas only carry the new transactions and receipts
+    assert_eq!(deltas[1]["index"], 1);
+    assert!(deltas[1].get("header").is_none());
+    assert_eq!(deltas[1]["hash"], json!(PENDING_BLOCK_HASH));
+    assert_eq!(deltas[1]["transactions"].as_array().unwrap().len(), 9);
+    assert_eq!(deltas[1]["receipts"].as_array().unwrap().len(), 9);
+
+    assert_eq!(deltas[2]["index"], 2);
+    assert!(deltas[2]["transactions"].as_array().unwrap().is_empty());
+
+    // Once the block has transactions, a delta is a fraction of the full block
+    assert!(
+        delta_bytes[2] * 10 < full_bytes[2],
+        "delta {} bytes, full block {} bytes",
+        delta_bytes[2],
+        full_bytes[2]
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_eth_subscribe_new_flashblocks_replays_from_cursor() -> eyre::Result<()> {
+    let setup = TestSetup::new().await?;
+    let ws_url = setup.harness.ws_url();
+
+    setup.send_flashblock(setup.create_first_payload()).await?;
+    setup.send_flashblock(setup.create_second_payload()).await?;
+
+    // A client that last received the first flashblock reconnects
+    let (mut ws_stream, _) = connect_async(&ws_url).await?;
+    ws_stream
+        .send(Message::Text(
+            json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": "eth_subscribe",
+                "params": [
+                    "newFlashblocks",
+                    { "cursor": { "blockNumber": 1, "flashblockIndex": 0 } }
+                ]
+            })
+            .to_string()
+            .into(),
+        ))
+        .await?;
+
+    let response = ws_stream.next().await.unwrap()?;
+    let sub: serde_json::Value = serde_json::from_str(response.to_text()?)?;
+    assert!(sub["result"].is_string(), "subscription id expected: {sub}");
+
+    // The missed flashblock is replayed without waiting for a new one
+    let notification = ws_stream.next().await.unwrap()?;
+    let notif: serde_json::Value = serde_json::from_str(notification.to_text()?)?;
+    let block = &notif["params"]["result"];
+    assert_eq!(block["pendingSnapshot"], json!({ "blockNumber": 1, "flashblockIndex": 1 }));
+    assert_eq!(block["transactions"].as_array().unwrap().len(), 10);
+
+    // Live flashblocks follow the replayed ones
+    setup
+        .send_flashblock(Flashblock {
+            payload_id: PayloadId::new([0; 8]),
+            index: 2,
+            base: None,
+            diff: ExecutionPayloadFlashblockDeltaV1 {
+                block_hash: PENDING_BLOCK_HASH,
+                blob_gas_used: Some(0),
+                ..Default::default()
+            },
+            metadata: Metadata { block_number: 1 },
+        })
+        .await?;
+
+    let notification = ws_stream.next().await.unwrap()?;
+    let notif: serde_json::Value = serde_json::from_str(notification.to_text()?)?;
+    assert_eq!(
+        notif["params"]["result"]["pendingSnapshot"],
+        json!({ "blockNumber": 1, "flashblockIndex": 2 })
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_eth_subscribe_new_flashblocks_evicted_cursor() -> eyre::Result<()> {
+    let setup = TestSetup::new().await?;
+    let (mut ws_stream, _) = connect_async(&setup.harness.ws_url()).await?;
+
+    setup.send_flashblock(setup.create_first_payload()).await?;
+
+    ws_stream
+        .send(Message::Text(
+            json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": "eth_subscribe",
+                "params": [
+                    "newFlashblocks",
+                    { "cursor": { "blockNumber": 0, "flashblockIndex": 3 } }
+                ]
+            })
+            .to_string()
+            .into(),
+        ))
+        .await?;
+
+    let response = ws_stream.next().await.unwrap()?;
+    let response: serde_json::Value = serde_json::from_str(response.to_text()?)?;
+    assert_eq!(response["error"]["code"], CURSOR_EVICTED_CODE);
+    assert_eq!(response["error"]["data"], json!({ "blockNumber": 1, "flashblockIndex": 0 }));
+
+    Ok(())
+}
+
 #[tokio::test]
 async fn test_eth_unsubscribe() -> eyre::Result<()> {
     let setup = TestSetup::new().await?;
@@ -1178,3 +1941,370 @@ async fn test_eth_subscribe_new_flashblock_transactions_full() -> eyre::Result<(
 
     Ok(())
 }
+
+#[tokio::test]
+async fn test_eth_subscribe_new_preconfirmed_transactions() -> eyre::Result<()> {
+    let setup = TestSetup::new().await?;
+    let ws_url = setup.harness.ws_url();
+    let (mut ws_stream, _) = connect_async(&ws_url).await?;
+    let bob = setup.harness.accounts().bob.address;
+
+    ws_stream
+        .send(Message::Text(
+            json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": "eth_subscribe",
+                "params": ["newPreconfirmedTransactions", { "to": [bob] }]
+            })
+            .to_string()
+            .into(),
+        ))
+        .await?;
+
+    let response = ws_stream.next().await.unwrap()?;
+    let sub: serde_json::Value = serde_json::from_str(response.to_text()?)?;
+    assert!(sub["result"].is_string(), "subscription id expected: {sub}");
+
+    setup.send_flashblock(setup.create_first_payload()).await?;
+    setup.send_flashblock(setup.create_second_payload()).await?;
+
+    // Only alice's transfer to bob matches, and it is sent together with its receipt
+    let notification = ws_stream.next().await.unwrap()?;
+    let notif: serde_json::Value = serde_json::from_str(notification.to_text()?)?;
+    let preconfirmed = notif["params"]["result"].as_array().expect("expected array");
+    assert_eq!(preconfirmed.len(), 1);
+    assert_eq!(
+        preconfirmed[0]["transaction"]["hash"],
+        json!(setup.txn_details.alice_eth_transfer_hash)
+    );
+    assert_eq!(
+        preconfirmed[0]["receipt"]["transactionHash"],
+        json!(setup.txn_details.alice_eth_transfer_hash)
+    );
+
+    // The transaction is not sent again by later flashblocks
+    setup
+        .send_flashblock(Flashblock {
+            payload_id: PayloadId::new([0; 8]),
+            index: 2,
+            base: None,
+            diff: ExecutionPayloadFlashblockDeltaV1 {
+                block_hash: PENDING_BLOCK_HASH,
+                blob_gas_used: Some(0),
+                ..Default::default()
+            },
+            metadata: Metadata { block_number: 1 },
+        })
+        .await?;
+    let next = tokio::time::timeout(std::time::Duration::from_millis(500), ws_stream.next()).await;
+    assert!(next.is_err(), "unexpected notification: {next:?}");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_eth_subscribe_new_preconfirmed_transactions_invalid_params() -> eyre::Result<()> {
+    let setup = TestSetup::new().await?;
+    let (mut ws_stream, _) = connect_async(&setup.harness.ws_url()).await?;
+
+    ws_stream
+        .send(Message::Text(
+            json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": "eth_subscribe",
+                "params": ["newPreconfirmedTransactions", { "sender": [] }]
+            })
+            .to_string()
+            .into(),
+        ))
+        .await?;
+
+    let response = ws_stream.next().await.unwrap()?;
+    let response: serde_json::Value = serde_json::from_str(response.to_text()?)?;
+    assert_eq!(response["error"]["code"], -32602);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_eth_subscribe_pending_account_updates() -> eyre::Result<()> {
+    let setup = TestSetup::new().await?;
+    let provider = setup.harness.provider();
+    let (mut ws_stream, _) = connect_async(&setup.harness.ws_url()).await?;
+    let alice = setup.harness.accounts().alice.address;
+    let bob = setup.harness.accounts().bob.address;
+
+    ws_stream
+        .send(Message::Text(
+            json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": "eth_subscribe",
+                "params": ["pendingAccountUpdates", [alice, bob]]
+            })
+            .to_string()
+            .into(),
+        ))
+        .await?;
+
+    let response = ws_stream.next().await.unwrap()?;
+    let sub: serde_json::Value = serde_json::from_str(response.to_text()?)?;
+    assert!(sub["result"].is_string(), "subscription id expected: {sub}");
+
+    setup.send_flashblock(setup.create_first_payload()).await?;
+    setup.send_flashblock(setup.create_second_payload()).await?;
+
+    // Alice's transfer to bob changes both accounts
+    let notification = ws_stream.next().await.unwrap()?;
+    let notif: serde_json::Value = serde_json::from_str(notification.to_text()?)?;
+    let updates = notif["params"]["result"].as_array().expect("expected array");
+    assert_eq!(updates.len(), 2);
+    for update in updates {
+        assert_eq!(update["status"], "preconfirmed");
+        assert_eq!(update["txHash"], json!(setup.txn_details.alice_eth_transfer_hash));
+        assert_eq!(update["blockNumber"], json!(1));
+        assert_eq!(update["flashblockIndex"], json!(1));
+    }
+
+    let alice_update =
+        updates.iter().find(|update| update["address"] == json!(alice)).expect("alice update");
+    assert_eq!(alice_update["nonce"], json!(1));
+
+    let bob_update =
+        updates.iter().find(|update| update["address"] == json!(bob)).expect("bob update");
+    let bob_balance = provider.get_balance(bob).pending().await?;
+    assert_eq!(bob_update["balance"], json!(bob_balance));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_eth_subscribe_pending_account_updates_requires_addresses() -> eyre::Result<()> {
+    let setup = TestSetup::new().await?;
+    let (mut ws_stream, _) = connect_async(&setup.harness.ws_url()).await?;
+
+    ws_stream
+        .send(Message::Text(
+            json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": "eth_subscribe",
+                "params": ["pendingAccountUpdates"]
+            })
+            .to_string()
+            .into(),
+        ))
+        .await?;
+
+    let response = ws_stream.next().await.unwrap()?;
+    let response: serde_json::Value = serde_json::from_str(response.to_text()?)?;
+    assert_eq!(response["error"]["code"], -32602);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_eth_subscribe_pending_invalidations() -> eyre::Result<()> {
+    let setup = TestSetup::new().await?;
+    let (mut ws_stream, _) = connect_async(&setup.harness.ws_url()).await?;
+
+    ws_stream
+        .send(Message::Text(
+            json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": "eth_subscribe",
+                "params": ["pendingInvalidations"]
+            })
+            .to_string()
+            .into(),
+        ))
+        .await?;
+
+    let response = ws_stream.next().await.unwrap()?;
+    let sub: serde_json::Value = serde_json::from_str(response.to_text()?)?;
+    assert!(sub["result"].is_string(), "subscription id expected: {sub}");
+
+    setup.send_test_payloads().await?;
+
+    // The canonical block does not include the preconfirmed transactions
+    setup.harness.build_block_from_transactions(vec![]).await?;
+
+    let notification = ws_stream.next().await.unwrap()?;
+    let notif: serde_json::Value = serde_json::from_str(notification.to_text()?)?;
+    let invalidation = &notif["params"]["result"];
+    assert_eq!(invalidation["reason"], "canonicalMismatch");
+    assert_eq!(invalidation["canonicalBlockNumber"], json!(1));
+    let transactions = invalidation["transactions"].as_array().expect("expected array");
+    assert!(transactions.contains(&json!(setup.txn_details.alice_eth_transfer_hash)));
+    assert!(transactions.contains(&json!(setup.txn_details.log_trigger_hash)));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_eth_subscribe_pending_logs_marks_dropped_logs_removed() -> eyre::Result<()> {
+    let setup = TestSetup::new().await?;
+    let (mut ws_stream, _) = connect_async(&setup.harness.ws_url()).await?;
+
+    ws_stream
+        .send(Message::Text(
+            json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": "eth_subscribe",
+                "params": ["pendingLogs"]
+            })
+            .to_string()
+            .into(),
+        ))
+        .await?;
+
+    let response = ws_stream.next().await.unwrap()?;
+    let sub: serde_json::Value = serde_json::from_str(response.to_text()?)?;
+    assert!(sub["result"].is_string(), "subscription id expected: {sub}");
+
+    setup.send_test_payloads().await?;
+
+    let notification = ws_stream.next().await.unwrap()?;
+    let notif: serde_json::Value = serde_json::from_str(notification.to_text()?)?;
+    let logs = notif["params"]["result"].as_array().expect("expected array");
+    assert_eq!(logs.len(), 2);
+    assert!(logs.iter().all(|log| log["removed"] == json!(false)));
+
+    // The canonical block does not include the log trigger transaction
+    setup.harness.build_block_from_transactions(vec![]).await?;
+
+    let notification = ws_stream.next().await.unwrap()?;
+    let notif: serde_json::Value = serde_json::from_str(notification.to_text()?)?;
+    let removed = notif["params"]["result"].as_array().expect("expected array");
+    assert_eq!(removed.len(), 2);
+    for log in removed {
+        assert_eq!(log["removed"], json!(true));
+        assert_eq!(log["transactionHash"], json!(setup.txn_details.log_trigger_hash));
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_eth_subscribe_new_raw_flashblocks() -> eyre::Result<()> {
+    let setup = TestSetup::new().await?;
+    let (mut ws_stream, _) = connect_async(&setup.harness.ws_url()).await?;
+
+    ws_stream
+        .send(Message::Text(
+            json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": "eth_subscribe",
+                "params": ["newRawFlashblocks"]
+            })
+            .to_string()
+            .into(),
+        ))
+        .await?;
+
+    let response = ws_stream.next().await.unwrap()?;
+    let sub: serde_json::Value = serde_json::from_str(response.to_text()?)?;
+    assert!(sub["result"].is_string(), "subscription id expected: {sub}");
+
+    setup.send_test_payloads().await?;
+    setup.send_flashblock(setup.create_second_payload()).await?;
+    // Flashblock 3 does not follow flashblock 1
+    setup.send_flashblock(Flashblock { index: 3, ..setup.create_second_payload() }).await?;
+
+    let mut raw_flashblocks = Vec::new();
+    for _ in 0..4 {
+        let notification = ws_stream.next().await.unwrap()?;
+        let notif: serde_json::Value = serde_json::from_str(notification.to_text()?)?;
+        raw_flashblocks.push(notif["params"]["result"].clone());
+    }
+
+    assert_eq!(raw_flashblocks[0]["index"], json!(0));
+    assert_eq!(raw_flashblocks[0]["status"], "accepted");
+    assert_eq!(raw_flashblocks[0]["payload_id"], json!(PayloadId::new([0; 8])));
+    assert_eq!(raw_flashblocks[0]["metadata"]["block_number"], json!(1));
+    assert!(raw_flashblocks[0]["base"].is_object());
+
+    assert_eq!(raw_flashblocks[1]["index"], json!(1));
+    assert_eq!(raw_flashblocks[1]["status"], "accepted");
+    assert!(raw_flashblocks[1]["base"].is_null());
+
+    assert_eq!(raw_flashblocks[2]["index"], json!(1));
+    assert_eq!(raw_flashblocks[2]["status"], "duplicate");
+
+    assert_eq!(raw_flashblocks[3]["index"], json!(3));
+    assert_eq!(raw_flashblocks[3]["status"], "rejected");
+    assert!(raw_flashblocks[3]["reason"].is_string());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_eth_subscribe_rejects_subscriptions_over_connection_limit() -> eyre::Result<()> {
+    let setup = TestSetup::new().await?;
+    let (mut ws_stream, _) = connect_async(&setup.harness.ws_url()).await?;
+
+    for id in 0..=DEFAULT_MAX_SUBSCRIPTIONS_PER_CONNECTION {
+        ws_stream
+            .send(Message::Text(
+                json!({
+                    "jsonrpc": "2.0",
+                    "id": id,
+                    "method": "eth_subscribe",
+                    "params": ["newFlashblocks"]
+                })
+                .to_string()
+                .into(),
+            ))
+            .await?;
+
+        let response = ws_stream.next().await.unwrap()?;
+        let response: serde_json::Value = serde_json::from_str(response.to_text()?)?;
+        if id < DEFAULT_MAX_SUBSCRIPTIONS_PER_CONNECTION {
+            assert!(response["result"].is_string(), "subscription id expected: {response}");
+        } else {
+            assert_eq!(response["error"]["code"], json!(SUBSCRIPTION_LIMIT_CODE));
+        }
+    }
+
+    // Standard subscriptions are not limited
+    ws_stream
+        .send(Message::Text(
+            json!({
+                "jsonrpc": "2.0",
+                "id": "heads",
+                "method": "eth_subscribe",
+                "params": ["newHeads"]
+            })
+            .to_string()
+            .into(),
+        ))
+        .await?;
+    let response = ws_stream.next().await.unwrap()?;
+    let response: serde_json::Value = serde_json::from_str(response.to_text()?)?;
+    assert!(response["result"].is_string(), "subscription id expected: {response}");
+
+    // Subscriptions of other connections are counted separately
+    let (mut other_stream, _) = connect_async(&setup.harness.ws_url()).await?;
+    other_stream
+        .send(Message::Text(
+            json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": "eth_subscribe",
+                "params": ["newFlashblocks"]
+            })
+            .to_string()
+            .into(),
+        ))
+        .await?;
+    let response = other_stream.next().await.unwrap()?;
+    let response: serde_json::Value = serde_json::from_str(response.to_text()?)?;
+    assert!(response["result"].is_string(), "subscription id expected: {response}");
+
+    Ok(())
+}
@@ -2,7 +2,7 @@
 
 use std::{any::Any, net::SocketAddr, sync::Arc};
 
-use alloy_eips::Encodable2718;
+use alloy_eips::{BlockNumberOrTag, Encodable2718};
 use alloy_primitives::{Bytes, U256, address, b256, bytes};
 use alloy_rpc_client::RpcClient;
 use base_bundles::{Bundle, MeterBundleResponse};
@@ -255,6 +255,22 @@ async fn test_meter_bundle_invalid_transaction() -> eyre::Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn test_meter_bundle_pending_requires_flashblocks() -> eyre::Result<()> {
+    let node = setup_node().await?;
+    let client = node.rpc_client().await?;
+
+    // The node is set up without flashblocks, so there is no pending state to meter on
+    let bundle = create_bundle(vec![], 0, None);
+
+    let result: Result<crate::MeterBundleResponse, _> =
+        client.request("base_meterBundle", (bundle, BlockNumberOrTag::Pending)).await;
+
+    assert!(result.is_err());
+
+    Ok(())
+}
+
 #[tokio::test]
 async fn test_meter_bundle_uses_latest_block() -> eyre::Result<()> {
     let node = setup_node().await?;
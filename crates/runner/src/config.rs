@@ -1,5 +1,6 @@
 //! Contains the Base node configuration structures.
 
+use base_reth_rpc::{LagPolicy, SubscriptionLimits};
 use reth_optimism_node::args::RollupArgs;
 
 use crate::extensions::FlashblocksCell;
@@ -33,6 +34,18 @@ pub struct FlashblocksConfig {
     pub websocket_url: String,
     /// Maximum number of pending flashblocks to retain in memory.
     pub max_pending_blocks_depth: u64,
+    /// Approximate memory limit for pending flashblocks state, in bytes.
+    pub max_pending_blocks_memory: Option<usize>,
+    /// Max timeout accepted by `eth_sendRawTransactionSync`, in milliseconds.
+    pub max_send_raw_transaction_sync_timeout_ms: u64,
+    /// Number of pending state updates buffered for each subscriber.
+    pub broadcast_buffer_size: usize,
+    /// Number of recent flashblocks kept for subscribers resuming from a cursor.
+    pub replay_history_size: usize,
+    /// What subscriptions do when the subscriber falls behind the buffered updates.
+    pub subscription_lag_policy: LagPolicy,
+    /// Limits on the flashblocks subscriptions.
+    pub subscription_limits: SubscriptionLimits,
 }
 
 /// Transaction tracing toggles.
@@ -45,6 +45,9 @@ impl BaseNodeExtension for FlashblocksCanonExtension {
                         Arc::new(FlashblocksState::new(
                             ctx.provider().clone(),
                             fb_config.max_pending_blocks_depth,
+                            fb_config.max_pending_blocks_memory,
+                            fb_config.broadcast_buffer_size,
+                            fb_config.replay_history_size,
                         ))
                     })
                     .clone();
@@ -16,4 +16,5 @@ mod tracing;
 pub use tracing::TransactionTracingExtension;
 
 mod types;
-pub use types::{FlashblocksCell, OpBuilder, OpProvider};
+pub(crate) use types::OpNodeHandle;
+pub use types::{FlashblocksCell, OpBuilder, OpProvider, WithRpcMiddleware};
@@ -4,8 +4,10 @@ use std::sync::Arc;
 
 use base_reth_flashblocks::{FlashblocksState, FlashblocksSubscriber};
 use base_reth_rpc::{
-    EthApiExt, EthApiOverrideServer, EthPubSub, EthPubSubApiServer, MeteringApiImpl,
-    MeteringApiServer, TransactionStatusApiImpl, TransactionStatusApiServer,
+    DebugApiExt, DebugApiOverrideServer, EthApiExt, EthApiOverrideServer, EthPubSub,
+    EthPubSubApiServer, FlashblockSnapshotApiImpl, FlashblockSnapshotApiServer,
+    FlashblocksFeeApiImpl, FlashblocksFeeApiServer, MeteringApiImpl, MeteringApiServer,
+    TransactionStatusApiImpl, TransactionStatusApiServer,
 };
 use tracing::info;
 use url::Url;
@@ -49,12 +51,6 @@ impl BaseNodeExtension for BaseRpcExtension {
         let sequencer_rpc = self.sequencer_rpc;
 
         builder.extend_rpc_modules(move |ctx| {
-            if metering_enabled {
-                info!(message = "Starting Metering RPC");
-                let metering_api = MeteringApiImpl::new(ctx.provider().clone());
-                ctx.modules.merge_configured(metering_api.into_rpc())?;
-            }
-
             let proxy_api =
                 TransactionStatusApiImpl::new(sequencer_rpc.clone(), ctx.pool().clone())
                     .expect("Failed to create transaction status proxy");
@@ -69,6 +65,9 @@ impl BaseNodeExtension for BaseRpcExtension {
                         Arc::new(FlashblocksState::new(
                             ctx.provider().clone(),
                             cfg.max_pending_blocks_depth,
+                            cfg.max_pending_blocks_memory,
+                            cfg.broadcast_buffer_size,
+                            cfg.replay_history_size,
                         ))
                     })
                     .clone();
@@ -81,18 +80,43 @@ impl BaseNodeExtension for BaseRpcExtension {
                     ctx.registry.eth_api().clone(),
                     ctx.registry.eth_handlers().filter.clone(),
                     fb.clone(),
-                );
+                )
+                .with_max_sync_timeout_ms(cfg.max_send_raw_transaction_sync_timeout_ms);
                 ctx.modules.replace_configured(api_ext.into_rpc())?;
 
+                let debug_ext =
+                    DebugApiExt::new(ctx.registry.debug_api(), ctx.provider().clone(), fb.clone());
+                ctx.modules.replace_configured(debug_ext.into_rpc())?;
+
+                let snapshot_api =
+                    FlashblockSnapshotApiImpl::new(ctx.registry.eth_api().clone(), fb.clone());
+                ctx.modules.merge_configured(snapshot_api.into_rpc())?;
+
+                let fee_api =
+                    FlashblocksFeeApiImpl::new(ctx.registry.eth_api().clone(), fb.clone());
+                ctx.modules.merge_configured(fee_api.into_rpc())?;
+
                 // Register the eth_subscribe subscription endpoint for flashblocks
                 // Uses replace_configured since eth_subscribe already exists from reth's standard module
                 // Pass eth_api to enable proxying standard subscription types to reth's implementation
-                let eth_pubsub = EthPubSub::new(ctx.registry.eth_api().clone(), fb);
+                let eth_pubsub = EthPubSub::new(ctx.registry.eth_api().clone(), fb)
+                    .with_lag_policy(cfg.subscription_lag_policy)
+                    .with_subscription_limits(cfg.subscription_limits);
                 ctx.modules.replace_configured(eth_pubsub.into_rpc())?;
             } else {
                 info!(message = "flashblocks integration is disabled");
             }
 
+            if metering_enabled {
+                info!(message = "Starting Metering RPC");
+                let mut metering_api = MeteringApiImpl::new(ctx.provider().clone());
+                // Bundles can be metered on top of the pending state once flashblocks are started
+                if let Some(fb) = flashblocks_cell.get() {
+                    metering_api = metering_api.with_flashblocks_state(fb.clone());
+                }
+                ctx.modules.merge_configured(metering_api.into_rpc())?;
+            }
+
             Ok(())
         })
     }
@@ -3,10 +3,14 @@
 use std::sync::Arc;
 
 use base_reth_flashblocks::FlashblocksState;
+use base_reth_rpc::PendingBlocksPinLayer;
 use once_cell::sync::OnceCell;
 use reth::{
-    api::{FullNodeTypesAdapter, NodeTypesWithDBAdapter},
-    builder::{Node, NodeBuilderWithComponents, WithLaunchContext},
+    api::{FullNodeComponents, FullNodeTypesAdapter, NodeTypesWithDBAdapter},
+    builder::{
+        Node, NodeAdapter, NodeBuilderWithComponents, NodeHandle, WithLaunchContext,
+        components::NodeComponentsBuilder, rpc::EthApiBuilder,
+    },
     providers::providers::BlockchainProvider,
 };
 use reth_db::DatabaseEnv;
@@ -14,7 +18,32 @@ use reth_optimism_node::OpNode;
 
 type OpNodeTypes = FullNodeTypesAdapter<OpNode, Arc<DatabaseEnv>, OpProvider>;
 type OpComponentsBuilder = <OpNode as Node<OpNodeTypes>>::ComponentsBuilder;
-type OpAddOns = <OpNode as Node<OpNodeTypes>>::AddOns;
+type OpAddOns =
+    <<OpNode as Node<OpNodeTypes>>::AddOns as WithRpcMiddleware<PendingBlocksPinLayer>>::AddOns;
+
+/// Names the OP node add-ons once an RPC middleware is installed on them.
+pub trait WithRpcMiddleware<M> {
+    /// The add-ons with the RPC middleware.
+    type AddOns;
+}
+
+impl<N, EthB, PVB, EB, EVB, M> WithRpcMiddleware<M>
+    for reth_optimism_node::OpAddOns<N, EthB, PVB, EB, EVB>
+where
+    N: FullNodeComponents,
+    EthB: EthApiBuilder<N>,
+{
+    type AddOns = reth_optimism_node::OpAddOns<N, EthB, PVB, EB, EVB, M>;
+}
+
+/// Handle to the launched OP node.
+pub(crate) type OpNodeHandle = NodeHandle<
+    NodeAdapter<
+        OpNodeTypes,
+        <OpComponentsBuilder as NodeComponentsBuilder<OpNodeTypes>>::Components,
+    >,
+    OpAddOns,
+>;
 
 /// A [`BlockchainProvider`] instance.
 pub type OpProvider = BlockchainProvider<NodeTypesWithDBAdapter<OpNode, Arc<DatabaseEnv>>>;
@@ -7,26 +7,24 @@ use std::{
 use derive_more::Debug;
 use eyre::Result;
 use futures_util::{FutureExt, future::BoxFuture};
-use reth::builder::NodeHandleFor;
-use reth_optimism_node::OpNode;
+
+use crate::extensions::OpNodeHandle;
 
 /// Handle to a launched Base node.
 ///
-/// This wraps the underlying [`NodeHandleFor`] so callers can await it directly to wait for node
+/// This wraps the underlying node handle so callers can await it directly to wait for node
 /// shutdown.
 #[must_use = "Dropping the handle will stop the node immediately"]
 #[derive(Debug, Default)]
 pub struct BaseNodeHandle {
     #[debug(skip)]
-    build_fut: Option<BoxFuture<'static, Result<NodeHandleFor<OpNode>>>>,
+    build_fut: Option<BoxFuture<'static, Result<OpNodeHandle>>>,
     #[debug(skip)]
-    handle: Option<Box<NodeHandleFor<OpNode>>>,
+    handle: Option<Box<OpNodeHandle>>,
 }
 
 impl BaseNodeHandle {
-    pub(crate) fn new(
-        fut: impl Future<Output = Result<NodeHandleFor<OpNode>>> + Send + 'static,
-    ) -> Self {
+    pub(crate) fn new(fut: impl Future<Output = Result<OpNodeHandle>> + Send + 'static) -> Self {
         Self { build_fut: Some(fut.boxed()), handle: None }
     }
 }
@@ -18,5 +18,5 @@ pub use config::{BaseNodeConfig, FlashblocksConfig, TracingConfig};
 mod extensions;
 pub use extensions::{
     BaseNodeExtension, BaseRpcExtension, ConfigurableBaseNodeExtension, FlashblocksCanonExtension,
-    FlashblocksCell, OpBuilder, OpProvider, TransactionTracingExtension,
+    FlashblocksCell, OpBuilder, OpProvider, TransactionTracingExtension, WithRpcMiddleware,
 };
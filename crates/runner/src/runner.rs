@@ -1,8 +1,10 @@
 //! Contains the [`BaseNodeRunner`], which is responsible for configuring and launching a Base node.
 
+use base_reth_flashblocks::FlashblocksAPI;
+use base_reth_rpc::PendingBlocksPinLayer;
 use eyre::Result;
 use reth::{
-    builder::{EngineNodeLauncher, Node, NodeHandleFor, TreeConfig},
+    builder::{EngineNodeLauncher, Node, TreeConfig},
     providers::providers::BlockchainProvider,
 };
 use reth_optimism_node::OpNode;
@@ -10,7 +12,7 @@ use tracing::info;
 
 use crate::{
     BaseNodeBuilder, BaseNodeConfig, BaseNodeHandle,
-    extensions::{BaseNodeExtension, ConfigurableBaseNodeExtension},
+    extensions::{BaseNodeExtension, ConfigurableBaseNodeExtension, OpNodeHandle},
 };
 
 /// Wraps the Base node configuration and orchestrates builder wiring.
@@ -53,15 +55,21 @@ impl BaseNodeRunner {
         config: BaseNodeConfig,
         extensions: Vec<Box<dyn BaseNodeExtension>>,
         builder: BaseNodeBuilder,
-    ) -> Result<NodeHandleFor<OpNode>> {
+    ) -> Result<OpNodeHandle> {
         info!(target: "base-runner", "starting custom Base node");
 
         let op_node = OpNode::new(config.rollup_args.clone());
 
+        // Pins the pending state once per RPC call or batch
+        let flashblocks_cell = config.flashblocks_cell.clone();
+        let pin_layer = PendingBlocksPinLayer::new(move || {
+            flashblocks_cell.get().and_then(|state| Option::clone(&state.get_pending_blocks()))
+        });
+
         let builder = builder
             .with_types_and_provider::<OpNode, BlockchainProvider<_>>()
             .with_components(op_node.components())
-            .with_add_ons(op_node.add_ons())
+            .with_add_ons(op_node.add_ons().with_rpc_middleware(pin_layer))
             .on_component_initialized(move |_ctx| Ok(()));
 
         let builder =
@@ -30,7 +30,7 @@ pub use harness::TestHarness;
 mod node;
 pub use node::{
     FlashblocksLocalNode, FlashblocksParts, LocalFlashblocksState, LocalNode, LocalNodeProvider,
-    OpAddOns, OpBuilder, OpComponentsBuilder, OpTypes, default_launcher,
+    OpAddOns, OpBuilder, OpComponentsBuilder, OpTypes, WithRpcMiddleware, default_launcher,
 };
 
 mod tracing;
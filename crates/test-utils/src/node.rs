@@ -11,17 +11,26 @@ use alloy_genesis::Genesis;
 use alloy_provider::RootProvider;
 use alloy_rpc_client::RpcClient;
 use base_flashtypes::Flashblock;
-use base_reth_flashblocks::{FlashblocksReceiver, FlashblocksState};
-use base_reth_rpc::{EthApiExt, EthApiOverrideServer, EthPubSub, EthPubSubApiServer};
+use base_reth_flashblocks::{
+    DEFAULT_BROADCAST_BUFFER_SIZE, DEFAULT_FLASHBLOCK_HISTORY_SIZE, FlashblocksAPI,
+    FlashblocksReceiver, FlashblocksState,
+};
+use base_reth_rpc::{
+    DebugApiExt, DebugApiOverrideServer, EthApiExt, EthApiOverrideServer, EthPubSub,
+    EthPubSubApiServer, FlashblockSnapshotApiImpl, FlashblockSnapshotApiServer,
+    FlashblocksFeeApiImpl, FlashblocksFeeApiServer, MeteringApiImpl, MeteringApiServer,
+    PendingBlocksPinLayer,
+};
 use eyre::Result;
 use futures_util::Future;
 use once_cell::sync::OnceCell;
 use op_alloy_network::Optimism;
 use reth::{
-    api::{FullNodeTypesAdapter, NodeTypesWithDBAdapter},
+    api::{FullNodeComponents, FullNodeTypesAdapter, NodeTypesWithDBAdapter},
     args::{DiscoveryArgs, NetworkArgs, RpcServerArgs},
     builder::{
         Node, NodeBuilder, NodeBuilderWithComponents, NodeConfig, NodeHandle, WithLaunchContext,
+        rpc::EthApiBuilder,
     },
     core::exit::NodeExitFuture,
     tasks::TaskManager,
@@ -178,6 +187,22 @@ impl FlashblocksNodeExtensions {
                 );
                 ctx.modules.replace_configured(api_ext.into_rpc())?;
 
+                let debug_ext =
+                    DebugApiExt::new(ctx.registry.debug_api(), ctx.provider().clone(), fb.clone());
+                ctx.modules.replace_configured(debug_ext.into_rpc())?;
+
+                let snapshot_api =
+                    FlashblockSnapshotApiImpl::new(ctx.registry.eth_api().clone(), fb.clone());
+                ctx.modules.merge_configured(snapshot_api.into_rpc())?;
+
+                let fee_api =
+                    FlashblocksFeeApiImpl::new(ctx.registry.eth_api().clone(), fb.clone());
+                ctx.modules.merge_configured(fee_api.into_rpc())?;
+
+                let metering_api =
+                    MeteringApiImpl::new(ctx.provider().clone()).with_flashblocks_state(fb.clone());
+                ctx.modules.merge_configured(metering_api.into_rpc())?;
+
                 // Register eth_subscribe subscription endpoint for flashblocks
                 // Uses replace_configured since eth_subscribe already exists from reth's standard module
                 // Pass eth_api to enable proxying standard subscription types to reth's implementation
@@ -225,8 +250,25 @@ pub type OpTypes =
     FullNodeTypesAdapter<OpNode, TmpDB, BlockchainProvider<NodeTypesWithDBAdapter<OpNode, TmpDB>>>;
 /// Builder that wires up the concrete node components.
 pub type OpComponentsBuilder = <OpNode as Node<OpTypes>>::ComponentsBuilder;
-/// Additional services attached to the node builder.
-pub type OpAddOns = <OpNode as Node<OpTypes>>::AddOns;
+/// Additional services attached to the node builder, with the pending state pinned per RPC call
+/// or batch.
+pub type OpAddOns =
+    <<OpNode as Node<OpTypes>>::AddOns as WithRpcMiddleware<PendingBlocksPinLayer>>::AddOns;
+
+/// Names the OP node add-ons once an RPC middleware is installed on them.
+pub trait WithRpcMiddleware<M> {
+    /// The add-ons with the RPC middleware.
+    type AddOns;
+}
+
+impl<N, EthB, PVB, EB, EVB, M> WithRpcMiddleware<M>
+    for reth_optimism_node::OpAddOns<N, EthB, PVB, EB, EVB>
+where
+    N: FullNodeComponents,
+    EthB: EthApiBuilder<N>,
+{
+    type AddOns = reth_optimism_node::OpAddOns<N, EthB, PVB, EB, EVB, M>;
+}
 /// Launcher builder used by the harness to customize node startup.
 pub type OpBuilder =
     WithLaunchContext<NodeBuilderWithComponents<OpTypes, OpComponentsBuilder, OpAddOns>>;
@@ -246,7 +288,7 @@ impl LocalNode {
         L: FnOnce(OpBuilder) -> LRet,
         LRet: Future<Output = eyre::Result<NodeHandle<Adapter<OpNode>, OpAddOns>>>,
     {
-        build_node(launcher).await
+        build_node(launcher, PendingBlocksPinLayer::new(|| None)).await
     }
 
     /// Creates a test database with a smaller map size to reduce memory usage.
@@ -298,7 +340,7 @@ impl LocalNode {
     }
 }
 
-async fn build_node<L, LRet>(launcher: L) -> Result<LocalNode>
+async fn build_node<L, LRet>(launcher: L, pin_layer: PendingBlocksPinLayer) -> Result<LocalNode>
 where
     L: FnOnce(OpBuilder) -> LRet,
     LRet: Future<Output = eyre::Result<NodeHandle<Adapter<OpNode>, OpAddOns>>>,
@@ -344,7 +386,7 @@ where
         .with_launch_context(exec.clone())
         .with_types_and_provider::<OpNode, BlockchainProvider<_>>()
         .with_components(node.components_builder())
-        .with_add_ons(node.add_ons());
+        .with_add_ons(node.add_ons().with_rpc_middleware(pin_layer));
 
     let NodeHandle { node: node_handle, node_exit_future } =
         builder.launch_with_fn(launcher).await?;
@@ -378,7 +420,13 @@ fn init_flashblocks_state(
     provider: &LocalNodeProvider,
 ) -> Arc<LocalFlashblocksState> {
     cell.get_or_init(|| {
-        let fb = Arc::new(FlashblocksState::new(provider.clone(), 5));
+        let fb = Arc::new(FlashblocksState::new(
+            provider.clone(),
+            5,
+            None,
+            DEFAULT_BROADCAST_BUFFER_SIZE,
+            DEFAULT_FLASHBLOCK_HISTORY_SIZE,
+        ));
         fb.start();
         fb
     })
@@ -436,8 +484,12 @@ impl FlashblocksLocalNode {
         LRet: Future<Output = eyre::Result<NodeHandle<Adapter<OpNode>, OpAddOns>>>,
     {
         let extensions = FlashblocksNodeExtensions::new(process_canonical);
+        let fb_cell = extensions.inner.fb_cell.clone();
+        let pin_layer = PendingBlocksPinLayer::new(move || {
+            fb_cell.get().and_then(|state| Option::clone(&state.get_pending_blocks()))
+        });
         let wrapped_launcher = extensions.wrap_launcher(launcher);
-        let node = LocalNode::new(wrapped_launcher).await?;
+        let node = build_node(wrapped_launcher, pin_layer).await?;
 
         let parts = extensions.parts()?;
         Ok(Self { node, parts })